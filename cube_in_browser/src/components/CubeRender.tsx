@@ -1,22 +1,46 @@
 // src/components/MainPage.tsx
-import React, { useEffect, useRef } from 'react';
-import init, { run } from '../../pkg/cube_take_two';
+import React, { useEffect, useRef, useState } from 'react';
+import init, { run, CubeHandle } from '../../pkg/cube_take_two';
 import '../Card.css'
+
+// tooltip text for whichever face the pointer is over
+const FACE_LORE: Record<string, string> = {
+  front: "The Face of Arrival: worn smooth by every gaze that ever found the cube.",
+  right: "The Ember Face: still warm from the fall that tore the tesseract apart.",
+  back: "The Hidden Face: it has never seen where it is going, only where it has been.",
+  left: "The Tide Face: it remembers a fourth direction the others have forgotten.",
+  top: "The Crown: on quiet nights it points toward the missing fragments.",
+  bottom: "The Foundation: it bears the weight of a dimension it cannot hold.",
+};
+
 const CubeRenderComponent: React.FC = () => {
 
   const canvasRef = useRef<HTMLCanvasElement>(document.getElementById("cube-container") as HTMLCanvasElement);
+  // recolors the running cube, e.g. handleRef.current?.setTheme("sunset")
+  const handleRef = useRef<CubeHandle | null>(null);
+  const [isometric, setIsometric] = useState(false);
+  const [hoveredFace, setHoveredFace] = useState<string | null>(null);
+  const [pointer, setPointer] = useState({ x: 0, y: 0 });
+  const [lastClicked, setLastClicked] = useState<string | null>(null);
+  const toggleProjection = () => {
+    handleRef.current?.setProjection(isometric ? "perspective" : "isometric");
+    setIsometric(!isometric);
+  };
   useEffect(() => {
     const initialize = async () => {
       console.log("begin");
       await init();
       console.log(window.devicePixelRatio, window.innerWidth, window.innerHeight);
       const canvas = canvasRef.current;
-      run(
+      handleRef.current = run(
         window.devicePixelRatio,
         window.innerWidth,
         window.innerHeight,
-        canvas
+        canvas,
+        "classic"
       );
+      handleRef.current.onFaceHover((face: string | null) => setHoveredFace(face));
+      handleRef.current.onFaceClick((face: string) => setLastClicked(face));
     };
     initialize();
   }, []);
@@ -25,21 +49,38 @@ const CubeRenderComponent: React.FC = () => {
 
   return (
     <div className="card" id = 'cube-container'>
-      <div className="card-artframe">
+      <div
+        className="card-artframe"
+        onMouseMove={(e) => {
+          const frame = e.currentTarget.getBoundingClientRect();
+          setPointer({ x: e.clientX - frame.left, y: e.clientY - frame.top });
+        }}
+      >
         <canvas
           ref={canvasRef}
           width={100}
           height={10}
           className="card-canvas"
         />
+        {hoveredFace && (
+          <div className="card-tooltip" style={{ left: pointer.x + 12, top: pointer.y + 12 }}>
+            {FACE_LORE[hoveredFace]}
+          </div>
+        )}
       </div>
       <div className="card-body">
         <div className="card-title">
           The Lost Cube
           </div>
-        <div className="card-type">Artifact — WGPU Renderer</div>
+        <div className="card-type">
+          Artifact — WGPU Renderer
+          <button className="card-toggle" onClick={toggleProjection}>
+            {isometric ? "perspective" : "isometric"}
+          </button>
+        </div>
         <div className="card-text">
           A lonely cube drifts through the void, seeking the fragments of its tesseract. Ironically, it's faces lacku the dimension to comprehend the truth it seeks. 
+          {lastClicked && <div className="card-flavor">{FACE_LORE[lastClicked]}</div>}
         </div>
       </div>
     </div>
@@ -0,0 +1,469 @@
+// What the cube floats in front of. A solid color is just the clear color, every
+// other mode is background.wgsl drawn over the whole target before the objects.
+// Skyboxes are cubemaps decoded on the cpu, either from six face images or
+// resampled from one equirectangular (usually HDR) panorama.
+use std::fmt;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use half::f16;
+use wgpu::{BindGroup, BindGroupLayoutEntry, Buffer, RenderPipeline, TextureFormat, TextureView};
+
+use crate::theme::srgb_to_linear;
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::{Cube, DEPTH_FORMAT};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Background {
+    Solid([f32; 4]),
+    // top of the screen to the bottom
+    Gradient { top: [f32; 4], bottom: [f32; 4] },
+    // the two colors tint the nebula, density 1 is a moderately busy sky
+    Starfield { nebula: [f32; 4], glow: [f32; 4], density: f32 },
+    // whatever cubemap was last loaded with set_skybox, black until then
+    Skybox { exposure: f32 },
+}
+
+impl Default for Background {
+    // the dark blue grey the cube has always cleared to
+    fn default() -> Self {
+        Background::Solid([0.05, 0.062, 0.08, 1.0])
+    }
+}
+
+pub const NEBULA_COLOR: [f32; 4] = [0.35, 0.2, 0.6, 1.0];
+pub const NEBULA_GLOW: [f32; 4] = [0.1, 0.35, 0.6, 1.0];
+
+impl Background {
+    // deep blue overhead fading to a dusky violet horizon
+    pub fn gradient() -> Self {
+        Background::Gradient { top: [0.02, 0.03, 0.08, 1.0], bottom: [0.25, 0.12, 0.3, 1.0] }
+    }
+
+    pub fn starfield() -> Self {
+        Background::Starfield { nebula: NEBULA_COLOR, glow: NEBULA_GLOW, density: 1.0 }
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        match self {
+            Background::Solid([r, g, b, a]) => wgpu::Color { r: *r as f64, g: *g as f64, b: *b as f64, a: *a as f64 },
+            _ => wgpu::Color::BLACK,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BackgroundError {
+    Decode(String),
+    // cubemap faces have to be square and all the same size
+    FaceSize { face: usize, width: u32, height: u32, expected: u32 },
+    // a resampled face has to be at least one texel and fit the device's textures
+    InvalidFaceSize { size: u32, max: u32 },
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BackgroundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackgroundError::Decode(e) => write!(f, "could not decode image: {e}"),
+            BackgroundError::FaceSize { face, width, height, expected } => {
+                write!(f, "cubemap face {face} is {width}x{height}, expected {expected}x{expected}")
+            }
+            BackgroundError::InvalidFaceSize { size, max } => write!(f, "face size {size} has to be between 1 and {max}"),
+            BackgroundError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for BackgroundError {}
+
+impl From<std::io::Error> for BackgroundError {
+    fn from(e: std::io::Error) -> Self {
+        BackgroundError::Io(e)
+    }
+}
+
+// decodes any supported format to linear rgba, 8 bit images are assumed to be sRGB
+fn decode_linear(bytes: &[u8]) -> Result<image::Rgba32FImage, BackgroundError> {
+    let decoded = image::load_from_memory(bytes).map_err(|e| BackgroundError::Decode(e.to_string()))?;
+    let is_float = matches!(decoded.color(), image::ColorType::Rgb32F | image::ColorType::Rgba32F);
+    let mut pixels = decoded.into_rgba32f();
+    if !is_float {
+        for p in pixels.pixels_mut() {
+            for c in &mut p.0[..3] {
+                *c = srgb_to_linear(*c);
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+// world direction through texel (u, v) of a cubemap face, both in -1..1 with v pointing down,
+// faces in wgpu's layer order +x, -x, +y, -y, +z, -z
+pub fn face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    let d = match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    };
+    let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+    [d[0] / len, d[1] / len, d[2] / len]
+}
+
+// linear rgba cubemap on the cpu
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubemapImage {
+    pub size: u32,
+    pub faces: [Vec<[f32; 4]>; 6],
+}
+
+impl CubemapImage {
+    // encoded images for the +x, -x, +y, -y, +z, -z faces
+    pub fn from_faces(faces: [&[u8]; 6]) -> Result<Self, BackgroundError> {
+        let mut size = 0;
+        let mut decoded = Vec::with_capacity(6);
+        for (face, bytes) in faces.into_iter().enumerate() {
+            let image = decode_linear(bytes)?;
+            let (width, height) = image.dimensions();
+            if face == 0 {
+                size = width;
+            }
+            if width != size || height != size {
+                return Err(BackgroundError::FaceSize { face, width, height, expected: size });
+            }
+            decoded.push(image.pixels().map(|p| p.0).collect::<Vec<_>>());
+        }
+        let faces = decoded.try_into().expect("six faces were decoded");
+        Ok(CubemapImage { size, faces })
+    }
+
+    // resamples a 2:1 longitude / latitude panorama, the middle of the image ends up along -z,
+    // max_size is the device's max_texture_dimension_2d
+    pub fn from_equirect(bytes: &[u8], face_size: u32, max_size: u32) -> Result<Self, BackgroundError> {
+        if face_size == 0 || face_size > max_size {
+            return Err(BackgroundError::InvalidFaceSize { size: face_size, max: max_size });
+        }
+        let image = decode_linear(bytes)?;
+        let (width, height) = image.dimensions();
+        let texel = |x: i64, y: i64| {
+            let x = x.rem_euclid(width as i64) as u32;
+            let y = y.clamp(0, height as i64 - 1) as u32;
+            image.get_pixel(x, y).0
+        };
+        // bilinear, wrapping around horizontally
+        let sample = |u: f32, v: f32| {
+            let (x, y) = (u * width as f32 - 0.5, v * height as f32 - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let (a, b, c, d) = (texel(x0, y0), texel(x0 + 1, y0), texel(x0, y0 + 1), texel(x0 + 1, y0 + 1));
+            std::array::from_fn(|i| {
+                let top = a[i] + (b[i] - a[i]) * fx;
+                let bottom = c[i] + (d[i] - c[i]) * fx;
+                top + (bottom - top) * fy
+            })
+        };
+        let faces = std::array::from_fn(|face| {
+            (0..face_size * face_size).map(|i| {
+                let u = ((i % face_size) as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let v = ((i / face_size) as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let [x, y, z] = face_direction(face, u, v);
+                let longitude = x.atan2(-z);
+                let latitude = y.clamp(-1.0, 1.0).asin();
+                sample(0.5 + longitude / (2.0 * std::f32::consts::PI), 0.5 - latitude / std::f32::consts::PI)
+            }).collect()
+        });
+        Ok(CubemapImage { size: face_size, faces })
+    }
+
+    // a directory holding px/nx/py/ny/pz/nz images (png, jpg or hdr), or a single panorama
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path, face_size: u32, max_size: u32) -> Result<Self, BackgroundError> {
+        if !path.is_dir() {
+            return Self::from_equirect(&std::fs::read(path)?, face_size, max_size);
+        }
+        let find = |name: &str| -> Result<Vec<u8>, BackgroundError> {
+            let file = ["png", "jpg", "jpeg", "hdr"].iter()
+                .map(|ext| path.join(format!("{name}.{ext}")))
+                .find(|p| p.exists())
+                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("no {name} face in {}", path.display())))?;
+            Ok(std::fs::read(file)?)
+        };
+        let faces = ["px", "nx", "py", "ny", "pz", "nz"].map(find);
+        let [px, nx, py, ny, pz, nz] = faces;
+        Self::from_faces([&px?, &nx?, &py?, &ny?, &pz?, &nz?])
+    }
+
+    // half the size, each texel the average of the 2x2 block it covers
+    pub fn downsample(&self) -> CubemapImage {
+        let size = (self.size / 2).max(1);
+        let faces = std::array::from_fn(|face| {
+            let texel = |x: u32, y: u32| self.faces[face][(y.min(self.size - 1) * self.size + x.min(self.size - 1)) as usize];
+            (0..size * size).map(|i| {
+                let (x, y) = (i % size * 2, i / size * 2);
+                let block = [texel(x, y), texel(x + 1, y), texel(x, y + 1), texel(x + 1, y + 1)];
+                std::array::from_fn(|c| block.iter().map(|t| t[c]).sum::<f32>() / 4.0)
+            }).collect()
+        });
+        CubemapImage { size, faces }
+    }
+
+    // with a full mip chain so blurry lookups (and the IBL bake) don't alias
+    pub fn create_texture(&self, driver: &Driver) -> TextureView {
+        let mut mips = vec![self.clone()];
+        while mips.last().is_some_and(|m| m.size > 1) {
+            let next = mips.last().map(CubemapImage::downsample).expect("mips starts non empty");
+            mips.push(next);
+        }
+        create_cube_texture(driver, "skybox", self.size, mips.len() as u32, |face, mip| {
+            mips[mip as usize].faces[face].iter().flat_map(|p| p.map(f16::from_f32)).collect()
+        })
+    }
+}
+
+// a Rgba16Float cube texture filled one face and mip level at a time
+fn create_cube_texture(driver: &Driver, label: &str, size: u32, mip_level_count: u32, face: impl Fn(usize, u32) -> Vec<f16>) -> TextureView {
+    let texture = driver.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 6 },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    for mip in 0..mip_level_count {
+        let mip_size = (size >> mip).max(1);
+        for layer in 0..6 {
+            driver.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: mip,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&face(layer as usize, mip)),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(8 * mip_size),
+                    rows_per_image: Some(mip_size),
+                },
+                wgpu::Extent3d { width: mip_size, height: mip_size, depth_or_array_layers: 1 },
+            );
+        }
+    }
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some(label),
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    })
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct BackgroundUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    top: [f32; 4],
+    bottom: [f32; 4],
+    params: [f32; 4],
+}
+
+pub(crate) const BACKGROUND_BGL_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<BackgroundUniform>() as u64),
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+// the pipeline, uniform and cubemap behind every mode except Solid
+pub struct BackgroundPass {
+    pipeline: RenderPipeline,
+    layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    uniform_buffer: Buffer,
+    bgl: wgpu::BindGroupLayout,
+    bind_group: BindGroup,
+    sampler: wgpu::Sampler,
+    pub(crate) sky_view: TextureView,
+}
+
+impl BackgroundPass {
+    pub fn new(driver: &Driver, color_format: TextureFormat, sample_count: u32) -> Self {
+        let bgl = Cube::create_bgl(driver, &BACKGROUND_BGL_ENTRIES, Some("background bgl"));
+        let layout = Cube::create_buffer_render_pipeline_layout(driver, &[&bgl]);
+        let shader = Cube::create_shader_from_source(driver, Some("background.wgsl"), include_str!("shaders/background.wgsl"));
+        let pipeline = Self::create_pipeline(driver, &layout, &shader, color_format, sample_count);
+        let uniform_buffer = driver.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("background uniform buffer"),
+            size: std::mem::size_of::<BackgroundUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("skybox sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sky_view = create_cube_texture(driver, "empty skybox", 1, 1, |_, _| vec![f16::ZERO, f16::ZERO, f16::ZERO, f16::ONE]);
+        let bind_group = Self::create_bind_group(driver, &bgl, &uniform_buffer, &sky_view, &sampler);
+        BackgroundPass { pipeline, layout, shader, uniform_buffer, bgl, bind_group, sampler, sky_view }
+    }
+
+    // follows the main pass when MSAA is switched
+    pub fn set_target(&mut self, driver: &Driver, color_format: TextureFormat, sample_count: u32) {
+        self.pipeline = Self::create_pipeline(driver, &self.layout, &self.shader, color_format, sample_count);
+    }
+
+    fn create_pipeline(driver: &Driver, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, color_format: TextureFormat, sample_count: u32) -> RenderPipeline {
+        driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("background pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            // drawn first and never written to the depth buffer, objects land on top of it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState { count: sample_count, ..Default::default() },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    fn create_bind_group(driver: &Driver, bgl: &wgpu::BindGroupLayout, uniform: &Buffer, sky: &TextureView, sampler: &wgpu::Sampler) -> BindGroup {
+        Cube::create_bg(driver, bgl, &[
+            wgpu::BindGroupEntry { binding: 0, resource: uniform.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(sky) },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ], Some("background bg"))
+    }
+
+    pub fn set_skybox(&mut self, driver: &Driver, cubemap: &CubemapImage) {
+        self.sky_view = cubemap.create_texture(driver);
+        self.bind_group = Self::create_bind_group(driver, &self.bgl, &self.uniform_buffer, &self.sky_view, &self.sampler);
+    }
+
+    // view_rotation is the view matrix without its translation
+    pub fn write_uniform(&self, driver: &Driver, background: &Background, projection: Matrix4<f32>, view_rotation: Matrix4<f32>, time_s: f32) {
+        let (mode, top, bottom, exposure, density) = match *background {
+            Background::Solid(color) => (0.0, color, color, 1.0, 0.0),
+            Background::Gradient { top, bottom } => (1.0, top, bottom, 1.0, 0.0),
+            Background::Starfield { nebula, glow, density } => (2.0, nebula, glow, 1.0, density),
+            Background::Skybox { exposure } => (3.0, [0.0; 4], [0.0; 4], exposure, 0.0),
+        };
+        let inv_view_proj = (projection * view_rotation).invert().unwrap_or(Matrix4::identity());
+        let uniform = BackgroundUniform {
+            inv_view_proj: inv_view_proj.into(),
+            top,
+            bottom,
+            params: [mode, time_s, exposure, density],
+        };
+        driver.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// the view matrix with the camera moved back to the origin
+pub fn view_rotation(view: Matrix4<f32>) -> Matrix4<f32> {
+    let mut rotation = view;
+    rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+    rotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(width, height, |x, y| image::Rgba(pixel(x, y)));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn face_centres_point_along_their_axis() {
+        let axes = [[1.0, 0.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, -1.0]];
+        for (face, axis) in axes.into_iter().enumerate() {
+            assert_eq!(face_direction(face, 0.0, 0.0), axis);
+        }
+    }
+
+    #[test]
+    fn equirect_top_and_bottom_land_on_the_y_faces() {
+        // white sky above the horizon, black ground below
+        let bytes = png(16, 8, |_, y| if y < 4 { [255; 4] } else { [0, 0, 0, 255] });
+        let cubemap = CubemapImage::from_equirect(&bytes, 4, 16).unwrap();
+        assert!(cubemap.faces[2].iter().all(|p| p[0] > 0.99));
+        assert!(cubemap.faces[3].iter().all(|p| p[0] < 0.01));
+
+        assert!(matches!(CubemapImage::from_equirect(&bytes, 0, 16), Err(BackgroundError::InvalidFaceSize { size: 0, .. })));
+        assert!(matches!(CubemapImage::from_equirect(&bytes, 32, 16), Err(BackgroundError::InvalidFaceSize { size: 32, .. })));
+    }
+
+    #[test]
+    fn faces_must_share_one_square_size() {
+        let square = png(4, 4, |_, _| [128, 128, 128, 255]);
+        let cubemap = CubemapImage::from_faces([&square; 6]).unwrap();
+        assert_eq!(cubemap.size, 4);
+        // 8 bit faces are sRGB and come out linear
+        assert!((cubemap.faces[0][0][0] - 0.2158).abs() < 1.0e-3);
+
+        let wide = png(8, 4, |_, _| [0; 4]);
+        let mut faces = [square.as_slice(); 6];
+        faces[3] = &wide;
+        assert!(matches!(CubemapImage::from_faces(faces), Err(BackgroundError::FaceSize { face: 3, .. })));
+    }
+}
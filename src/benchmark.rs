@@ -0,0 +1,66 @@
+// Native only frame timing summary for --benchmark.
+// Each time covers recording, submitting and waiting for one frame, so it is the
+// whole cpu plus gpu cost rather than whatever vsync would let through.
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    pub frames: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p99: Duration,
+    pub worst: Duration,
+}
+
+impl FrameStats {
+    // None without any frames to summarize
+    pub fn from_times(times: &[Duration]) -> Option<Self> {
+        if times.is_empty() {
+            return None;
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort();
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        Some(FrameStats {
+            frames: sorted.len(),
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            median: percentile(0.5),
+            p99: percentile(0.99),
+            worst: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} frames: mean {:.3} ms ({:.1} fps), median {:.3} ms, p99 {:.3} ms, worst {:.3} ms",
+            self.frames,
+            ms(self.mean),
+            1.0 / self.mean.as_secs_f64().max(f64::EPSILON),
+            ms(self.median),
+            ms(self.p99),
+            ms(self.worst),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_come_from_sorted_times() {
+        assert_eq!(FrameStats::from_times(&[]), None);
+        let times: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let stats = FrameStats::from_times(&times).unwrap();
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+        assert_eq!(stats.median, Duration::from_millis(51));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.worst, Duration::from_millis(100));
+    }
+}
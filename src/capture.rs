@@ -0,0 +1,187 @@
+// Reading a rendered frame back from the gpu and encoding it as png.
+// Texture to buffer copies need every row padded to COPY_BYTES_PER_ROW_ALIGNMENT,
+// the padding is stripped again once the buffer is mapped.
+use std::fmt;
+
+use wgpu::{TextureFormat, WasmNotSend};
+
+use crate::wasm_driver::Driver;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    // only 8 bit rgba and bgra targets can be read back
+    UnsupportedFormat(TextureFormat),
+    Map(wgpu::BufferAsyncError),
+    Encode(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::UnsupportedFormat(format) => write!(f, "cannot capture a {format:?} target"),
+            CaptureError::Map(e) => write!(f, "could not map the capture buffer: {e}"),
+            CaptureError::Encode(e) => write!(f, "could not encode png: {e}"),
+            CaptureError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(e: std::io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+// a captured frame as tightly packed rgba8 rows, top row first
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    // strips the row padding of a mapped copy and swizzles bgra targets to rgba
+    pub fn from_padded(data: &[u8], width: u32, height: u32, format: TextureFormat) -> Result<Self, CaptureError> {
+        let swap_red_blue = is_bgra(format)?;
+        let row = width as usize * 4;
+        let padded = padded_bytes_per_row(width) as usize;
+        let mut rgba = Vec::with_capacity(row * height as usize);
+        for line in data.chunks(padded).take(height as usize) {
+            rgba.extend_from_slice(&line[..row]);
+        }
+        if swap_red_blue {
+            rgba.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        Ok(Screenshot { width, height, rgba })
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, CaptureError> {
+        use image::ImageEncoder;
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png)
+            .write_image(&self.rgba, self.width, self.height, image::ExtendedColorType::Rgba8)
+            .map_err(|e| CaptureError::Encode(e.to_string()))?;
+        Ok(png)
+    }
+
+    pub fn save_png(&self, path: &std::path::Path) -> Result<(), CaptureError> {
+        std::fs::write(path, self.to_png()?)?;
+        Ok(())
+    }
+}
+
+fn is_bgra(format: TextureFormat) -> Result<bool, CaptureError> {
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Ok(false),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Ok(true),
+        other => Err(CaptureError::UnsupportedFormat(other)),
+    }
+}
+
+// 4 bytes per texel rounded up to what copy_texture_to_buffer accepts
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+// An offscreen color target the size of the surface that can be copied out of.
+// The caller draws into view, then read_back queues the copy and hands the frame
+// to on_done once the gpu is finished; native callers poll the device to get there.
+pub struct CaptureTarget {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl CaptureTarget {
+    pub fn new(driver: &Driver, format: TextureFormat, width: u32, height: u32) -> Result<Self, CaptureError> {
+        // checked up front so a bad format never costs a draw
+        is_bgra(format)?;
+        let texture = driver.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(CaptureTarget { texture, view })
+    }
+
+    pub fn read_back(self, driver: &Driver, on_done: impl FnOnce(Result<Screenshot, CaptureError>) + WasmNotSend + 'static) {
+        let (width, height, format) = (self.texture.width(), self.texture.height(), self.texture.format());
+        let buffer = driver.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture buffer"),
+            size: padded_bytes_per_row(width) as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("capture encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row(width)),
+                    rows_per_image: Some(height),
+                },
+            },
+            self.texture.size(),
+        );
+        driver.queue.submit(std::iter::once(encoder.finish()));
+
+        // buffers are reference counted handles, the clone keeps it alive inside the callback
+        let mapped = buffer.clone();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let screenshot = result.map_err(CaptureError::Map).and_then(|()| {
+                let data = mapped.slice(..).get_mapped_range();
+                Screenshot::from_padded(&data, width, height, format)
+            });
+            mapped.unmap();
+            on_done(screenshot);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_is_stripped_and_bgra_is_swizzled() {
+        let width = 3;
+        let padded = padded_bytes_per_row(width) as usize;
+        assert_eq!(padded % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize, 0);
+        assert!(padded >= 12);
+        let mut data = vec![0xee; padded * 2];
+        for y in 0..2 {
+            for x in 0..3 {
+                let i = y * padded + x * 4;
+                data[i..i + 4].copy_from_slice(&[1, 2, (x + y * 3) as u8, 255]);
+            }
+        }
+        let shot = Screenshot::from_padded(&data, width, 2, TextureFormat::Bgra8UnormSrgb).unwrap();
+        assert_eq!(shot.rgba.len(), 3 * 2 * 4);
+        assert!(shot.rgba.chunks(4).enumerate().all(|(i, p)| p == [i as u8, 2, 1, 255]));
+        assert!(matches!(
+            Screenshot::from_padded(&data, width, 2, TextureFormat::Rgba16Float),
+            Err(CaptureError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let shot = Screenshot { width: 2, height: 1, rgba: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+        let decoded = image::load_from_memory(&shot.to_png().unwrap()).unwrap().into_rgba8();
+        assert_eq!(decoded.dimensions(), (2, 1));
+        assert_eq!(decoded.into_raw(), shot.rgba);
+    }
+}
@@ -0,0 +1,527 @@
+// Native only command line options for the cube binary.
+// Everything parses into Options up front, anything unknown or malformed comes back
+// as a CliError so main can print it together with USAGE.
+use std::fmt;
+use std::path::PathBuf;
+
+use cgmath::Deg;
+use wgpu::{Backends, PresentMode};
+
+use crate::export::ExportSettings;
+use crate::input::Bindings;
+use crate::pacing::{present_mode_from_name, PRESENT_MODE_NAMES};
+use crate::post::{DepthOfField, Fog, PostSettings, Ssao};
+use crate::projection::{AspectFit, Projection, ProjectionSettings};
+use crate::shader_reload::SHADER_DIR;
+use crate::shadow::{Ground, GROUND_COLOR, SHADOW_CATCHER_OPACITY};
+use crate::wasm_driver::DEFAULT_FRAME_LATENCY;
+
+pub const USAGE: &str = "\
+usage: cube_take_two [OPTIONS]
+
+window:
+  --title TEXT              window title
+  --size WIDTHxHEIGHT       window size in points, or the image size in pixels for --headless, --export and --benchmark
+  --fullscreen              borderless fullscreen on the current monitor
+  --present-mode MODE       fifo, fifo-relaxed, mailbox, immediate, auto-vsync or auto-no-vsync
+  --vsync / --no-vsync      shorthand for auto-vsync / auto-no-vsync (auto-vsync is the default)
+  --frame-latency FRAMES    frames queued ahead of the display, 2 by default
+  --max-fps FPS             draw at most this many frames a second
+  --idle                    freeze the animation and only redraw when something changes
+
+rendering:
+  --backend LIST            comma separated: vulkan, metal, dx12, gl, or all (the default)
+  --msaa SAMPLES            1, 2, 4, 8 or 16 samples for the main pass
+  --pipeline-cache DIR      where compiled pipelines are kept between runs, the temp directory by default
+  --projection KIND         perspective (the default), orthographic, isometric or dimetric
+  --fov DEGREES             vertical field of view for perspective, 72 by default
+  --clip NEAR,FAR           clip plane distances, 0.1,100 by default
+  --fit POLICY              fit-width, fit-height or fit-contain (the default), how the view adapts to the window shape
+  --model PATH              draw a Wavefront .obj model instead of the cube
+  --cull FACES              back (the default), front or none, which faces of the --model are skipped
+  --front-face WINDING      ccw (the default) or cw, the winding the --model was authored with
+  --speed FACTOR            animation speed, 1 is one turn every 6.3 seconds, 0 stands still
+  --watch-shaders [DIR]     reload materials whose .wgsl in DIR is saved, the source tree's src/shaders by default
+
+scene:
+  --showcase LIST           comma separated extras next to the cube: materials, primitives, pbr, glass
+  --background KIND         gradient, stars, or a skybox: a .hdr/.png/.jpg panorama or a directory
+                            of px/nx/py/ny/pz/nz face images
+  --ground KIND             none (the default), plane for a lit floor or catcher for shadows only
+  --shadow-resolution N     shadow map size in texels, 2048 by default
+  --post LIST               comma separated post effects to start with: ssao, dof, fog
+  --screenshot-dir DIR      where P saves screenshots, the working directory by default
+
+input:
+  --bind TRIGGER=ACTION     rebind a key (KeyW, Digit1, BracketLeft...), MouseLeft, Gamepad0 or Tap,
+                            TRIGGER= unbinds it, repeat for more
+
+without a window:
+  --headless PATH           render the first frame to a png and exit
+  --export PATH             render the animation to a .png/.apng or a directory of frames
+  --fps N                   export frame rate, 30 by default
+  --duration SECONDS        export length, one full turn by default
+  --benchmark [FRAMES]      time FRAMES offscreen frames (500 by default) and print the stats
+
+  -h, --help                show this message";
+
+// the window size winit picks on its own is platform dependent, offscreen modes need one
+pub const DEFAULT_OFFSCREEN_SIZE: (u32, u32) = (512, 512);
+const DEFAULT_BENCHMARK_FRAMES: u32 = 500;
+// wgpu's default max_texture_dimension_2d
+const MAX_SIZE: u32 = 8192;
+// apng frame delays are a u16 fraction of a second
+const MAX_FPS: u32 = u16::MAX as u32;
+// ShadowMap clamps to this and the device limit anyway, outside it is a typo
+const SHADOW_RESOLUTION_RANGE: std::ops::RangeInclusive<u32> = 64..=MAX_SIZE;
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    // --help, not really an error but it ends the run the same way
+    Help,
+    Invalid(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{USAGE}"),
+            CliError::Invalid(e) => write!(f, "{e}\n\n{USAGE}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    Window,
+    Headless(PathBuf),
+    Export(ExportSettings),
+    Benchmark { frames: u32 },
+}
+
+// extra objects added around the cube
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Showcase {
+    Materials,
+    Primitives,
+    Pbr,
+    // turns the cube itself into glass, with a tesseract inside
+    Glass,
+}
+
+impl Showcase {
+    pub const ALL: [Showcase; 4] = [Showcase::Materials, Showcase::Primitives, Showcase::Pbr, Showcase::Glass];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Showcase::Materials => "materials",
+            Showcase::Primitives => "primitives",
+            Showcase::Pbr => "pbr",
+            Showcase::Glass => "glass",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackgroundOption {
+    Gradient,
+    Stars,
+    // a panorama file or a directory of cube faces
+    Skybox(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub title: String,
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub present_mode: PresentMode,
+    pub frame_latency: u32,
+    pub max_fps: Option<f32>,
+    pub idle: bool,
+    pub backends: Backends,
+    pub msaa: u32,
+    // None keeps the driver's pipeline cache in the temp directory
+    pub pipeline_cache: Option<PathBuf>,
+    pub projection: Projection,
+    pub projection_settings: ProjectionSettings,
+    pub model: Option<PathBuf>,
+    // passed to Material::with_culling for the model
+    pub model_cull: Option<wgpu::Face>,
+    pub model_front_face: wgpu::FrontFace,
+    pub speed: f32,
+    // hot reload shaders from this directory
+    pub watch_shaders: Option<PathBuf>,
+    pub showcases: Vec<Showcase>,
+    pub background: Option<BackgroundOption>,
+    pub ground: Ground,
+    pub shadow_resolution: Option<u32>,
+    pub post: PostSettings,
+    pub screenshot_dir: PathBuf,
+    pub bindings: Bindings,
+    pub mode: Mode,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            title: "cube with distinct face colors".into(),
+            size: None,
+            fullscreen: false,
+            present_mode: PresentMode::AutoVsync,
+            frame_latency: DEFAULT_FRAME_LATENCY,
+            max_fps: None,
+            idle: false,
+            backends: Backends::all(),
+            msaa: 1,
+            pipeline_cache: None,
+            projection: Projection::default(),
+            projection_settings: ProjectionSettings::default(),
+            model: None,
+            model_cull: Some(wgpu::Face::Back),
+            model_front_face: wgpu::FrontFace::Ccw,
+            speed: 1.0,
+            watch_shaders: None,
+            showcases: Vec::new(),
+            background: None,
+            ground: Ground::None,
+            shadow_resolution: None,
+            post: PostSettings::default(),
+            screenshot_dir: PathBuf::from("."),
+            bindings: Bindings::default(),
+            mode: Mode::Window,
+        }
+    }
+}
+
+impl Options {
+    // the size offscreen modes draw at
+    pub fn offscreen_size(&self) -> (u32, u32) {
+        self.size.unwrap_or(DEFAULT_OFFSCREEN_SIZE)
+    }
+
+    // args without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut options = Options::default();
+        let (mut export, mut fps, mut duration_s) = (None, None, None);
+        let mut modes = Vec::new();
+        let mut culling_set = false;
+        let mut args = args.into_iter().peekable();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| CliError::Invalid(format!("{flag} needs a value")));
+            match flag.as_str() {
+                "-h" | "--help" => return Err(CliError::Help),
+                "--title" => options.title = value()?,
+                "--size" => options.size = Some(parse_size(&value()?)?),
+                "--fullscreen" => options.fullscreen = true,
+                "--present-mode" => {
+                    let name = value()?;
+                    options.present_mode = present_mode_from_name(&name).ok_or_else(|| {
+                        CliError::Invalid(format!("--present-mode {name:?} should be one of {}", PRESENT_MODE_NAMES.join(", ")))
+                    })?;
+                }
+                "--vsync" => options.present_mode = PresentMode::AutoVsync,
+                "--no-vsync" => options.present_mode = PresentMode::AutoNoVsync,
+                "--frame-latency" => {
+                    options.frame_latency = parse_number(&flag, &value()?)?;
+                    if options.frame_latency == 0 {
+                        return Err(CliError::Invalid("--frame-latency needs at least one frame".into()));
+                    }
+                }
+                "--max-fps" => {
+                    let fps: f32 = parse_number(&flag, &value()?)?;
+                    if !(fps > 0.0 && fps.is_finite()) {
+                        return Err(CliError::Invalid("--max-fps has to be positive".into()));
+                    }
+                    options.max_fps = Some(fps);
+                }
+                "--idle" => options.idle = true,
+                "--backend" => options.backends = parse_backends(&value()?)?,
+                "--msaa" => {
+                    options.msaa = parse_number(&flag, &value()?)?;
+                    if ![1, 2, 4, 8, 16].contains(&options.msaa) {
+                        return Err(CliError::Invalid(format!("--msaa {} should be 1, 2, 4, 8 or 16", options.msaa)));
+                    }
+                }
+                "--pipeline-cache" => {
+                    // created on the first save, only something else already being there is a problem
+                    let dir = PathBuf::from(value()?);
+                    if dir.exists() && !dir.is_dir() {
+                        return Err(CliError::Invalid(format!("--pipeline-cache {} is not a directory", dir.display())));
+                    }
+                    options.pipeline_cache = Some(dir);
+                }
+                "--projection" => {
+                    let name = value()?;
+                    options.projection = Projection::named(&name)
+                        .ok_or_else(|| CliError::Invalid(format!("--projection {name:?} should be one of {}", Projection::NAMES.join(", "))))?;
+                }
+                "--fov" => {
+                    let degrees: f32 = parse_number(&flag, &value()?)?;
+                    if !(degrees > 0.0 && degrees < 180.0) {
+                        return Err(CliError::Invalid("--fov has to be between 0 and 180 degrees".into()));
+                    }
+                    options.projection_settings.fov_y = Deg(degrees).into();
+                }
+                "--clip" => {
+                    let value = value()?;
+                    let bad = || CliError::Invalid(format!("--clip {value:?} should look like 0.1,100 with 0 < near < far"));
+                    let (near, far) = value.split_once(',').ok_or_else(bad)?;
+                    let (near, far): (f32, f32) = (near.parse().map_err(|_| bad())?, far.parse().map_err(|_| bad())?);
+                    if !(near > 0.0 && far > near && far.is_finite()) {
+                        return Err(bad());
+                    }
+                    (options.projection_settings.near, options.projection_settings.far) = (near, far);
+                }
+                "--fit" => {
+                    let name = value()?;
+                    options.projection_settings.fit = AspectFit::from_name(&name)
+                        .ok_or_else(|| CliError::Invalid(format!("--fit {name:?} should be one of {}", AspectFit::NAMES.join(", "))))?;
+                }
+                "--model" => options.model = Some(PathBuf::from(value()?)),
+                "--cull" => {
+                    options.model_cull = match value()?.as_str() {
+                        "back" => Some(wgpu::Face::Back),
+                        "front" => Some(wgpu::Face::Front),
+                        "none" => None,
+                        other => return Err(CliError::Invalid(format!("--cull {other:?} should be back, front or none"))),
+                    };
+                    culling_set = true;
+                }
+                "--front-face" => {
+                    options.model_front_face = match value()?.as_str() {
+                        "ccw" => wgpu::FrontFace::Ccw,
+                        "cw" => wgpu::FrontFace::Cw,
+                        other => return Err(CliError::Invalid(format!("--front-face {other:?} should be ccw or cw"))),
+                    };
+                    culling_set = true;
+                }
+                "--speed" => {
+                    options.speed = parse_number(&flag, &value()?)?;
+                    if !(options.speed >= 0.0 && options.speed.is_finite()) {
+                        return Err(CliError::Invalid("--speed has to be 0 or more".into()));
+                    }
+                }
+                "--watch-shaders" => {
+                    // like --benchmark the directory is optional
+                    let dir = args.next_if(|next| !next.starts_with('-')).map_or_else(|| PathBuf::from(SHADER_DIR), PathBuf::from);
+                    if !dir.is_dir() {
+                        return Err(CliError::Invalid(format!("--watch-shaders {} is not a directory", dir.display())));
+                    }
+                    options.watch_shaders = Some(dir);
+                }
+                "--showcase" => options.showcases = parse_showcases(&value()?)?,
+                "--background" => {
+                    let value = value()?;
+                    options.background = Some(match value.as_str() {
+                        "gradient" => BackgroundOption::Gradient,
+                        "stars" => BackgroundOption::Stars,
+                        path if std::path::Path::new(path).exists() => BackgroundOption::Skybox(PathBuf::from(path)),
+                        other => return Err(CliError::Invalid(format!("--background {other:?} is not gradient, stars or an existing skybox path"))),
+                    });
+                }
+                "--ground" => {
+                    options.ground = match value()?.as_str() {
+                        "none" => Ground::None,
+                        "plane" => Ground::Plane(GROUND_COLOR),
+                        "catcher" => Ground::ShadowCatcher(SHADOW_CATCHER_OPACITY),
+                        other => return Err(CliError::Invalid(format!("--ground {other:?} should be none, plane or catcher"))),
+                    };
+                }
+                "--shadow-resolution" => {
+                    let resolution = parse_number(&flag, &value()?)?;
+                    if !SHADOW_RESOLUTION_RANGE.contains(&resolution) {
+                        return Err(CliError::Invalid(format!(
+                            "--shadow-resolution has to be between {} and {}", SHADOW_RESOLUTION_RANGE.start(), SHADOW_RESOLUTION_RANGE.end()
+                        )));
+                    }
+                    options.shadow_resolution = Some(resolution);
+                }
+                "--post" => options.post = parse_post(&value()?)?,
+                "--screenshot-dir" => {
+                    let dir = PathBuf::from(value()?);
+                    if !dir.is_dir() {
+                        return Err(CliError::Invalid(format!("--screenshot-dir {} is not a directory", dir.display())));
+                    }
+                    options.screenshot_dir = dir;
+                }
+                "--bind" => options.bindings.apply(&value()?).map_err(|e| CliError::Invalid(format!("--bind: {e}")))?,
+                "--headless" => modes.push(Mode::Headless(PathBuf::from(value()?))),
+                "--export" => export = Some(PathBuf::from(value()?)),
+                "--fps" => fps = Some(parse_number(&flag, &value()?)?),
+                "--duration" => duration_s = Some(parse_number(&flag, &value()?)?),
+                "--benchmark" => {
+                    // the frame count is optional, the next flag is not it
+                    let frames = match args.next_if(|next| !next.starts_with('-')) {
+                        Some(frames) => parse_number(&flag, &frames)?,
+                        None => DEFAULT_BENCHMARK_FRAMES,
+                    };
+                    if frames == 0 {
+                        return Err(CliError::Invalid("--benchmark needs at least one frame".into()));
+                    }
+                    modes.push(Mode::Benchmark { frames });
+                }
+                other => return Err(CliError::Invalid(format!("unknown option {other:?}"))),
+            }
+        }
+        if modes.len() + export.is_some() as usize > 1 {
+            return Err(CliError::Invalid("pick one of --headless, --export and --benchmark".into()));
+        }
+        options.mode = modes.pop().unwrap_or(Mode::Window);
+        if culling_set && options.model.is_none() {
+            return Err(CliError::Invalid("--cull and --front-face only apply to --model".into()));
+        }
+        match export {
+            Some(output) => {
+                let (width, height) = options.offscreen_size();
+                let mut settings = ExportSettings { width, height, ..ExportSettings::new(output) };
+                // one turn at the chosen speed, a cube standing still loops after any length
+                if options.speed > 0.0 {
+                    settings.duration_s /= options.speed;
+                }
+                settings.fps = fps.unwrap_or(settings.fps);
+                settings.duration_s = duration_s.unwrap_or(settings.duration_s);
+                if !(1..=MAX_FPS).contains(&settings.fps) {
+                    return Err(CliError::Invalid(format!("--fps has to be between 1 and {MAX_FPS}")));
+                }
+                if !(settings.duration_s > 0.0 && settings.duration_s.is_finite()) {
+                    return Err(CliError::Invalid("--duration has to be positive".into()));
+                }
+                options.mode = Mode::Export(settings);
+            }
+            None if fps.is_some() || duration_s.is_some() => {
+                return Err(CliError::Invalid("--fps and --duration only apply to --export".into()));
+            }
+            None => {}
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, CliError>
+where
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|e| CliError::Invalid(format!("{flag} {value:?}: {e}")))
+}
+
+// 640x480
+fn parse_size(value: &str) -> Result<(u32, u32), CliError> {
+    let bad = || CliError::Invalid(format!("--size {value:?} should look like 640x480, at most {MAX_SIZE} each way"));
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(bad)?;
+    let (width, height) = (width.trim().parse::<u32>().map_err(|_| bad())?, height.trim().parse::<u32>().map_err(|_| bad())?);
+    match (1..=MAX_SIZE).contains(&width) && (1..=MAX_SIZE).contains(&height) {
+        true => Ok((width, height)),
+        false => Err(bad()),
+    }
+}
+
+fn parse_showcases(value: &str) -> Result<Vec<Showcase>, CliError> {
+    value.split(',').map(str::trim).map(|name| {
+        Showcase::ALL.into_iter().find(|s| s.name() == name).ok_or_else(|| {
+            CliError::Invalid(format!("unknown showcase {name:?}, expected {}", Showcase::ALL.map(Showcase::name).join(", ")))
+        })
+    }).collect()
+}
+
+fn parse_post(value: &str) -> Result<PostSettings, CliError> {
+    value.split(',').map(str::trim).try_fold(PostSettings::default(), |mut settings, name| {
+        match name {
+            "ssao" => settings.ssao = Some(Ssao::default()),
+            "dof" => settings.dof = Some(DepthOfField::default()),
+            "fog" => settings.fog = Some(Fog::default()),
+            other => return Err(CliError::Invalid(format!("unknown post effect {other:?}, expected ssao, dof or fog"))),
+        }
+        Ok(settings)
+    })
+}
+
+fn parse_backends(value: &str) -> Result<Backends, CliError> {
+    value.split(',').map(str::trim).try_fold(Backends::empty(), |backends, name| {
+        let backend = match name {
+            "vulkan" => Backends::VULKAN,
+            "metal" => Backends::METAL,
+            "dx12" => Backends::DX12,
+            "gl" => Backends::GL,
+            "all" => Backends::all(),
+            other => return Err(CliError::Invalid(format!("unknown backend {other:?}"))),
+        };
+        Ok(backends | backend)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFormat;
+    use crate::input::{Action, Trigger};
+    use winit::keyboard::KeyCode;
+    use crate::wgpu_helpers::LOOP_MS;
+
+    fn parse(line: &str) -> Result<Options, CliError> {
+        Options::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn flags_fill_in_options() {
+        assert_eq!(parse("").unwrap(), Options::default());
+        let options = parse("--size 800x600 --fullscreen --no-vsync --backend vulkan,gl --msaa 4 --projection isometric --fit fit-width --fov 60 --clip 0.5,20 --speed 2 --benchmark").unwrap();
+        assert_eq!(options.size, Some((800, 600)));
+        assert!(options.fullscreen);
+        assert_eq!(options.projection, Projection::Isometric);
+        let lens = options.projection_settings;
+        assert_eq!((lens.fit, lens.fov_y, lens.near, lens.far), (AspectFit::Width, Deg(60.0).into(), 0.5, 20.0));
+        assert_eq!(options.present_mode, PresentMode::AutoNoVsync);
+        let scene = parse("--showcase pbr,glass --background stars --ground catcher --shadow-resolution 1024 --post ssao,fog --screenshot-dir .").unwrap();
+        assert_eq!(scene.showcases, vec![Showcase::Pbr, Showcase::Glass]);
+        assert_eq!(scene.background, Some(BackgroundOption::Stars));
+        assert_eq!(scene.ground, Ground::ShadowCatcher(SHADOW_CATCHER_OPACITY));
+        assert_eq!(scene.shadow_resolution, Some(1024));
+        assert_eq!((scene.post.ssao.is_some(), scene.post.dof.is_some(), scene.post.fog.is_some()), (true, false, true));
+        assert_eq!(parse("--background .").unwrap().background, Some(BackgroundOption::Skybox(PathBuf::from("."))));
+        let bound = parse("--bind KeyX=cycle-theme --bind Escape=").unwrap().bindings;
+        assert_eq!(bound.action(Trigger::Key(KeyCode::KeyX)), Some(Action::CycleTheme));
+        assert_eq!(bound.action(Trigger::Key(KeyCode::Escape)), None);
+        let paced = parse("--present-mode mailbox --frame-latency 1 --max-fps 30 --idle").unwrap();
+        assert_eq!((paced.present_mode, paced.frame_latency, paced.max_fps, paced.idle), (PresentMode::Mailbox, 1, Some(30.0), true));
+        assert_eq!(options.backends, Backends::VULKAN | Backends::GL);
+        assert_eq!((options.msaa, options.speed), (4, 2.0));
+        assert_eq!(options.mode, Mode::Benchmark { frames: DEFAULT_BENCHMARK_FRAMES });
+        assert_eq!(parse("--benchmark 20 --msaa 2").unwrap().mode, Mode::Benchmark { frames: 20 });
+        assert_eq!(parse("--watch-shaders --idle").unwrap().watch_shaders, Some(PathBuf::from(SHADER_DIR)));
+        assert_eq!(parse("--watch-shaders .").unwrap().watch_shaders, Some(PathBuf::from(".")));
+        let model = parse("--model mesh.obj --cull none --front-face cw").unwrap();
+        assert_eq!((model.model_cull, model.model_front_face), (None, wgpu::FrontFace::Cw));
+        assert_eq!(parse("--pipeline-cache /no/such/dir").unwrap().pipeline_cache, Some(PathBuf::from("/no/such/dir")));
+    }
+
+    #[test]
+    fn export_takes_size_speed_fps_and_duration() {
+        let Mode::Export(settings) = parse("--export clip.apng --size 320x240 --speed 2 --fps 24").unwrap().mode else {
+            panic!("expected an export");
+        };
+        assert_eq!(settings.format, ExportFormat::Apng);
+        assert_eq!((settings.width, settings.height, settings.fps), (320, 240, 24));
+        assert_eq!(settings.duration_s, LOOP_MS / 1000.0 / 2.0);
+        let Mode::Export(settings) = parse("--duration 2 --export frames").unwrap().mode else {
+            panic!("expected an export");
+        };
+        assert_eq!((settings.format, settings.duration_s), (ExportFormat::Sequence, 2.0));
+    }
+
+    #[test]
+    fn bad_input_is_rejected() {
+        assert_eq!(parse("--help"), Err(CliError::Help));
+        for bad in [
+            "--bogus", "--size 0x10", "--size big", "--msaa 3", "--fit stretch", "--present-mode sometimes", "--backend glide", "--max-fps 0", "--frame-latency 0",
+            "--projection fisheye", "--fov 180", "--clip 1", "--clip 5,1", "--speed -1", "--fps 30", "--export a.png --fps 0", "--export a.png --duration",
+            "--headless a.png --benchmark", "--benchmark 0",
+            "--bind KeyX", "--bind KeyX=fly", "--showcase pbr,spheres", "--background /no/such/sky.hdr", "--ground lava",
+            "--shadow-resolution 8", "--post ssao,bloom", "--screenshot-dir /no/such/dir", "--watch-shaders /no/such/dir", "--pipeline-cache Cargo.toml", "--cull none", "--model a.obj --cull sides", "--model a.obj --front-face left",
+        ] {
+            assert!(matches!(parse(bad), Err(CliError::Invalid(_))), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// What the input actions do to the cube, shared by the native window and the web canvas.
+// Select, Screenshot and Quit are left to the front end: one logs or reports the picked face,
+// saves a file or closes the window, the other has JS for that.
+use cgmath::{Rad, Vector3};
+
+use crate::background::Background;
+use crate::effects::{EffectKind, FaceEffect};
+use crate::input::Action;
+use crate::material::Material;
+use crate::post::{DepthOfField, Fog, Ssao};
+use crate::shadow::{DirectionalLight, Ground, GROUND_COLOR, SHADOW_CATCHER_OPACITY};
+use crate::theme::CubeTheme;
+use crate::transparency::TransparencyMode;
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::Cube;
+use crate::wireframe::OutlineStyle;
+
+const FOCUS_STEP: f32 = 0.25;
+// how far turn-light turns the light around the vertical axis
+const LIGHT_STEP_DEGREES: f32 = 30.0;
+// one zoom step (a wheel notch, a key press) narrows the view by this factor
+const ZOOM_STEP: f32 = 0.9;
+const FOV_RANGE: (f32, f32) = (10.0 * std::f32::consts::PI / 180.0, 2.0 * std::f32::consts::PI / 3.0);
+const ORTHO_HALF_HEIGHT_RANGE: (f32, f32) = (0.5, 20.0);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Controls {
+    // index into CubeTheme::NAMES
+    theme: usize,
+    // main cube swapped to polished metal
+    metal: bool,
+    // cycle-background includes the skybox
+    has_skybox: bool,
+}
+
+impl Controls {
+    pub fn new(has_skybox: bool) -> Self {
+        Controls { has_skybox, ..Default::default() }
+    }
+
+    // true when the scene changed and needs a frame
+    pub fn perform(&mut self, cube: &mut Cube, driver: &Driver, action: Action) -> bool {
+        match action {
+            Action::CycleDisplayMode => cube.set_display_mode(cube.display_mode.next()),
+            Action::ToggleOutline => {
+                let outline = cube.outline_style().is_none().then(OutlineStyle::default);
+                cube.set_outline(driver, outline);
+            }
+            Action::ToggleTransparency => {
+                let mode = match cube.transparency {
+                    TransparencyMode::Sorted => TransparencyMode::WeightedBlended,
+                    TransparencyMode::WeightedBlended => TransparencyMode::Sorted,
+                };
+                log::info!("transparency: {mode:?}");
+                cube.set_transparency_mode(driver, mode);
+            }
+            Action::CycleTheme => {
+                self.theme = (self.theme + 1) % CubeTheme::NAMES.len();
+                let name = CubeTheme::NAMES[self.theme];
+                log::info!("theme: {name}");
+                if let Ok(theme) = CubeTheme::named(name) {
+                    cube.set_theme(driver, theme);
+                }
+            }
+            Action::CycleEffect => {
+                let current = cube.effect().and_then(|e| EffectKind::ALL.iter().position(|k| *k == e.kind));
+                let next = match current {
+                    None => EffectKind::ALL.first(),
+                    Some(i) => EffectKind::ALL.get(i + 1),
+                };
+                log::info!("effect: {}", next.map_or("none", |k| k.name()));
+                cube.set_effect(driver, next.copied().map(FaceEffect::new));
+            }
+            Action::CycleBackground => {
+                let next = match cube.background {
+                    Background::Solid(_) => Background::gradient(),
+                    Background::Gradient { .. } => Background::starfield(),
+                    Background::Starfield { .. } if self.has_skybox => Background::Skybox { exposure: 1.0 },
+                    _ => Background::default(),
+                };
+                cube.set_background(next);
+            }
+            Action::ToggleMetal => {
+                self.metal = !self.metal;
+                let material = if self.metal { Material::polished_metal() } else { Material::unlit() };
+                cube.set_main_material(driver, material);
+            }
+            Action::CycleGround => {
+                let next = match cube.ground() {
+                    Ground::None => Ground::Plane(GROUND_COLOR),
+                    Ground::Plane(_) => Ground::ShadowCatcher(SHADOW_CATCHER_OPACITY),
+                    Ground::ShadowCatcher(_) => Ground::None,
+                };
+                cube.set_ground(driver, next);
+            }
+            Action::TurnLight => {
+                let turn = cgmath::Matrix3::from_angle_y(cgmath::Deg(LIGHT_STEP_DEGREES));
+                let direction = turn * Vector3::from(cube.light.direction);
+                cube.set_light(driver, DirectionalLight::new(direction.into()));
+            }
+            Action::ToggleSsao | Action::ToggleDepthOfField | Action::ToggleFog => {
+                let mut settings = cube.post_settings();
+                match action {
+                    Action::ToggleSsao => settings.ssao = settings.ssao.xor(Some(Ssao::default())),
+                    Action::ToggleDepthOfField => settings.dof = settings.dof.xor(Some(DepthOfField::default())),
+                    _ => settings.fog = settings.fog.xor(Some(Fog::default())),
+                }
+                log::info!("post effects: {settings:?}");
+                cube.set_post_settings(driver, settings);
+            }
+            // only while depth of field is on
+            Action::FocusNearer | Action::FocusFarther => {
+                let mut settings = cube.post_settings();
+                let Some(dof) = &mut settings.dof else { return false };
+                let step = if action == Action::FocusNearer { -FOCUS_STEP } else { FOCUS_STEP };
+                dof.focus_distance = (dof.focus_distance + step).max(0.0);
+                log::info!("focus distance: {}", dof.focus_distance);
+                cube.set_post_settings(driver, settings);
+            }
+            Action::CycleProjection => cube.set_projection(driver, cube.projection().next()),
+            Action::ZoomIn => return self.zoom(cube, driver, 1.0),
+            Action::ZoomOut => return self.zoom(cube, driver, -1.0),
+            Action::Select | Action::Screenshot | Action::Quit => return false,
+        }
+        true
+    }
+
+    // steps from InputState::take_zoom, narrowing the field of view and the orthographic height
+    // together so switching projections keeps roughly the same framing
+    pub fn zoom(&mut self, cube: &mut Cube, driver: &Driver, steps: f32) -> bool {
+        if steps == 0.0 {
+            return false;
+        }
+        let scale = ZOOM_STEP.powf(steps);
+        let mut settings = cube.projection_settings();
+        let half_tan = ((settings.fov_y.0 / 2.0).tan() * scale).atan();
+        settings.fov_y = Rad((2.0 * half_tan).clamp(FOV_RANGE.0, FOV_RANGE.1));
+        settings.ortho_half_height = (settings.ortho_half_height * scale).clamp(ORTHO_HALF_HEIGHT_RANGE.0, ORTHO_HALF_HEIGHT_RANGE.1);
+        let changed = settings != cube.projection_settings();
+        cube.set_projection_settings(driver, settings);
+        changed
+    }
+}
@@ -0,0 +1,42 @@
+// Surface sizes on high-DPI displays.
+// winit reports window and canvas sizes in physical pixels already (on the web its ResizeObserver
+// measures the canvas' device pixel content box, css size times devicePixelRatio), so the surface
+// is configured with that size as is. The only adjustment is an optional cap on the pixel ratio
+// for pages that would rather trade sharpness for fill rate on very dense screens.
+use winit::dpi::PhysicalSize;
+
+pub fn pixel_ratio(scale_factor: f64, max_pixel_ratio: Option<f64>) -> f64 {
+    match max_pixel_ratio {
+        Some(max) if max > 0.0 => scale_factor.min(max),
+        _ => scale_factor,
+    }
+}
+
+// size is in physical pixels at scale_factor, the result is at most max_pixel_ratio pixels per point
+pub fn backing_size(size: PhysicalSize<u32>, scale_factor: f64, max_pixel_ratio: Option<f64>) -> PhysicalSize<u32> {
+    match pixel_ratio(scale_factor, max_pixel_ratio) {
+        ratio if ratio < scale_factor => {
+            let scale = ratio / scale_factor;
+            PhysicalSize::new(
+                ((size.width as f64 * scale).round() as u32).max(1),
+                ((size.height as f64 * scale).round() as u32).max(1),
+            )
+        }
+        _ => size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_ratios_above_the_cap_shrink() {
+        let size = PhysicalSize::new(900, 600);
+        assert_eq!(backing_size(size, 3.0, None), size);
+        assert_eq!(backing_size(size, 3.0, Some(3.0)), size);
+        assert_eq!(backing_size(size, 1.5, Some(2.0)), size);
+        assert_eq!(backing_size(size, 3.0, Some(2.0)), PhysicalSize::new(600, 400));
+        assert_eq!(backing_size(PhysicalSize::new(1, 1), 3.0, Some(1.0)), PhysicalSize::new(1, 1));
+    }
+}
@@ -0,0 +1,86 @@
+// Procedural face effects for the main cube, all drawn by procedural.wgsl.
+// The effect and its knobs live in the material uniform, so switching between
+// them or tweaking them from JS is a buffer write on one shared pipeline.
+use crate::material::MaterialUniform;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EffectKind {
+    AnimatedGradient,
+    Plasma,
+    Grid,
+    FresnelRim,
+    HolographicFoil,
+}
+
+impl EffectKind {
+    // in the order procedural.wgsl switches on params.x
+    pub const ALL: [EffectKind; 5] = [
+        EffectKind::AnimatedGradient,
+        EffectKind::Plasma,
+        EffectKind::Grid,
+        EffectKind::FresnelRim,
+        EffectKind::HolographicFoil,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EffectKind::AnimatedGradient => "gradient",
+            EffectKind::Plasma => "plasma",
+            EffectKind::Grid => "grid",
+            EffectKind::FresnelRim => "fresnel",
+            EffectKind::HolographicFoil => "foil",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FaceEffect {
+    pub kind: EffectKind,
+    // multiplies the time the effect animates with, 0 freezes it
+    pub speed: f32,
+    // how many bands / cells / sparkles fit on a face
+    pub scale: f32,
+    // 0 is the plain vertex colors, 1 the full effect
+    pub intensity: f32,
+    // grid lines, rim glow and foil sparkle color
+    pub tint: [f32; 4],
+}
+
+impl FaceEffect {
+    pub fn new(kind: EffectKind) -> Self {
+        let (speed, scale, tint) = match kind {
+            EffectKind::AnimatedGradient => (1.0, 1.0, [1.0; 4]),
+            EffectKind::Plasma => (1.0, 1.0, [1.0; 4]),
+            EffectKind::Grid => (1.0, 2.0, [0.3, 1.0, 0.9, 1.0]),
+            EffectKind::FresnelRim => (1.0, 1.0, [0.4, 0.7, 1.0, 1.0]),
+            EffectKind::HolographicFoil => (1.0, 2.0, [1.0; 4]),
+        };
+        FaceEffect { kind, speed, scale, intensity: 1.0, tint }
+    }
+
+    pub fn uniform(&self) -> MaterialUniform {
+        let index = EffectKind::ALL.iter().position(|k| *k == self.kind).unwrap_or(0);
+        MaterialUniform {
+            base_color: self.tint,
+            params: [index as f32, self.speed, self.scale, self.intensity],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_round_trip_their_names_and_indices() {
+        for (i, kind) in EffectKind::ALL.into_iter().enumerate() {
+            assert_eq!(EffectKind::from_name(kind.name()), Some(kind));
+            assert_eq!(FaceEffect::new(kind).uniform().params[0], i as f32);
+        }
+        assert_eq!(EffectKind::from_name("sparkles"), None);
+    }
+}
@@ -0,0 +1,164 @@
+// Native only offline export of the cube animation.
+// Frames are rendered at a fixed timestep rather than the wall clock, so the same
+// settings always produce the same frames, and written as a png sequence or one animated png.
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::capture::{CaptureError, Screenshot};
+use crate::wgpu_helpers::LOOP_MS;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    Capture(CaptureError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{e}"),
+            ExportError::Png(e) => write!(f, "could not write png: {e}"),
+            ExportError::Capture(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for ExportError {
+    fn from(e: png::EncodingError) -> Self {
+        ExportError::Png(e)
+    }
+}
+
+impl From<CaptureError> for ExportError {
+    fn from(e: CaptureError) -> Self {
+        ExportError::Capture(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    // frame-0000.png, frame-0001.png, ... in a directory
+    Sequence,
+    // one looping animated png
+    Apng,
+}
+
+impl ExportFormat {
+    // a .png or .apng file becomes an animated png, anything else a directory of frames
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") || ext.eq_ignore_ascii_case("apng") => ExportFormat::Apng,
+            _ => ExportFormat::Sequence,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportSettings {
+    pub output: PathBuf,
+    pub format: ExportFormat,
+    pub fps: u32,
+    pub duration_s: f32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ExportSettings {
+    // one full turn at 30 fps and 512x512, which loops seamlessly
+    pub fn new(output: PathBuf) -> Self {
+        ExportSettings {
+            format: ExportFormat::for_path(&output),
+            output,
+            fps: 30,
+            duration_s: LOOP_MS / 1000.0,
+            width: 512,
+            height: 512,
+        }
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        ((self.duration_s * self.fps as f32).round() as u32).max(1)
+    }
+
+    // spread evenly over the duration so the frame after the last one would be frame 0 again
+    pub fn frame_time_ms(&self, frame: u32) -> f32 {
+        frame as f32 * self.duration_s * 1000.0 / self.frame_count() as f32
+    }
+}
+
+// where the frames go, opened before the first frame is rendered so a bad path fails early
+pub enum FrameWriter {
+    Sequence { dir: PathBuf, next: u32 },
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl FrameWriter {
+    pub fn create(settings: &ExportSettings) -> Result<Self, ExportError> {
+        match settings.format {
+            ExportFormat::Sequence => {
+                std::fs::create_dir_all(&settings.output)?;
+                Ok(FrameWriter::Sequence { dir: settings.output.clone(), next: 0 })
+            }
+            ExportFormat::Apng => {
+                let file = BufWriter::new(File::create(&settings.output)?);
+                let mut encoder = png::Encoder::new(file, settings.width, settings.height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                // 0 plays forever
+                encoder.set_animated(settings.frame_count(), 0)?;
+                encoder.set_frame_delay(1, settings.fps as u16)?;
+                Ok(FrameWriter::Apng(encoder.write_header()?))
+            }
+        }
+    }
+
+    pub fn write(&mut self, frame: &Screenshot) -> Result<(), ExportError> {
+        match self {
+            FrameWriter::Sequence { dir, next } => {
+                frame.save_png(&dir.join(format!("frame-{next:04}.png")))?;
+                *next += 1;
+            }
+            FrameWriter::Apng(writer) => writer.write_image_data(&frame.rgba)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), ExportError> {
+        if let FrameWriter::Apng(writer) = self {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apng_holds_every_frame() {
+        let output = std::env::temp_dir().join(format!("cube-export-test-{}.png", std::process::id()));
+        let settings = ExportSettings { width: 2, height: 1, fps: 10, duration_s: 0.3, ..ExportSettings::new(output.clone()) };
+        let mut writer = FrameWriter::create(&settings).unwrap();
+        for frame in 0..settings.frame_count() {
+            writer.write(&Screenshot { width: 2, height: 1, rgba: vec![frame as u8; 8] }).unwrap();
+        }
+        writer.finish().unwrap();
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&output).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (3, 0));
+        std::fs::remove_file(output).unwrap();
+    }
+}
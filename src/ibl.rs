@@ -0,0 +1,344 @@
+// Image based lighting for the pbr material. An environment cubemap is baked on the gpu
+// into a cosine convolved irradiance cube and a GGX prefiltered cube with one roughness
+// per mip, next to the split sum BRDF lookup table rendered once at startup.
+// pbr.wgsl reads all three through group 2.
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, RenderPipeline, ShaderModule, TextureFormat, TextureView};
+
+use crate::background::{face_direction, CubemapImage};
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::Cube;
+
+pub const IRRADIANCE_SIZE: u32 = 32;
+pub const PREFILTER_SIZE: u32 = 128;
+// roughness 0 at the top mip up to 1 at the last one
+pub const PREFILTER_MIPS: u32 = 5;
+pub const BRDF_LUT_SIZE: u32 = 256;
+const ENVIRONMENT_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+const BRDF_LUT_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+const IRRADIANCE_SAMPLES: u32 = 512;
+const PREFILTER_SAMPLES: u32 = 256;
+// used until a skybox is loaded
+const STUDIO_SIZE: u32 = 64;
+
+// x: face, y: roughness, z: source size, w: sample count, see ibl_bake.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct BakeUniform {
+    params: [f32; 4],
+}
+
+pub(crate) const BAKE_BGL_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            // every face and mip has its own slot in one buffer
+            has_dynamic_offset: true,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<BakeUniform>() as u64),
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+const fn cube_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+// group 2 of pbr.wgsl: irradiance, prefiltered specular, BRDF LUT and their sampler
+pub(crate) const ENVIRONMENT_BGL_ENTRIES: [BindGroupLayoutEntry; 4] = [
+    cube_entry(0),
+    cube_entry(1),
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+// a soft grey studio: bright overhead, dark floor and two softboxes for metals to reflect
+pub fn studio_environment(size: u32) -> CubemapImage {
+    let softboxes: [([f32; 3], f32, f32); 2] = [([0.6, 0.5, 0.62], 0.92, 4.0), ([-0.7, 0.25, -0.67], 0.95, 2.5)];
+    let faces = std::array::from_fn(|face| {
+        (0..size * size).map(|i| {
+            let u = ((i % size) as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let v = ((i / size) as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let d = face_direction(face, u, v);
+            let t = ((d[1] + 0.2) / 0.6).clamp(0.0, 1.0);
+            let horizon = t * t * (3.0 - 2.0 * t);
+            let mut c = 0.08 + (0.75 - 0.08) * horizon;
+            for (dir, cos_angle, intensity) in softboxes {
+                let len = (dir[0] * dir[0] + dir[1] * dir[1] + dir[2] * dir[2]).sqrt();
+                let cos = (d[0] * dir[0] + d[1] * dir[1] + d[2] * dir[2]) / len;
+                if cos > cos_angle {
+                    c += intensity;
+                }
+            }
+            [c, c, c * 1.03, 1.0]
+        }).collect()
+    });
+    CubemapImage { size, faces }
+}
+
+fn bake_pipeline(driver: &Driver, layout: &wgpu::PipelineLayout, module: &ShaderModule, entry_point: &str, format: TextureFormat) -> RenderPipeline {
+    driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            targets: &[Some(format.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn render_texture(driver: &Driver, label: &str, size: u32, layers: u32, mip_level_count: u32, format: TextureFormat) -> wgpu::Texture {
+    driver.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: layers },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+fn fullscreen_pass(encoder: &mut wgpu::CommandEncoder, target: &TextureView, pipeline: &RenderPipeline, bind_group: Option<(&BindGroup, u32)>) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("IBL bake pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    if let Some((bind_group, offset)) = bind_group {
+        render_pass.set_bind_group(0, bind_group, &[offset]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+// the baking pipelines plus the group 2 bind group for whatever environment was baked last
+pub struct Ibl {
+    pub(crate) bgl: BindGroupLayout,
+    bake_bgl: BindGroupLayout,
+    irradiance_pipeline: RenderPipeline,
+    prefilter_pipeline: RenderPipeline,
+    brdf_lut: TextureView,
+    bake_sampler: wgpu::Sampler,
+    sampler: wgpu::Sampler,
+    bind_group: BindGroup,
+}
+
+impl Ibl {
+    // renders the BRDF LUT and bakes the studio environment
+    pub fn new(driver: &Driver) -> Self {
+        let bgl = Cube::create_bgl(driver, &ENVIRONMENT_BGL_ENTRIES, Some("environment bgl"));
+        let bake_bgl = Cube::create_bgl(driver, &BAKE_BGL_ENTRIES, Some("ibl bake bgl"));
+        let module = Cube::create_shader_from_source(driver, Some("ibl_bake.wgsl"), include_str!("shaders/ibl_bake.wgsl"));
+        let bake_layout = Cube::create_buffer_render_pipeline_layout(driver, &[&bake_bgl]);
+        let lut_layout = Cube::create_buffer_render_pipeline_layout(driver, &[]);
+        let irradiance_pipeline = bake_pipeline(driver, &bake_layout, &module, "fs_irradiance", ENVIRONMENT_FORMAT);
+        let prefilter_pipeline = bake_pipeline(driver, &bake_layout, &module, "fs_prefilter", ENVIRONMENT_FORMAT);
+        let lut_pipeline = bake_pipeline(driver, &lut_layout, &module, "fs_brdf_lut", BRDF_LUT_FORMAT);
+
+        let lut_texture = render_texture(driver, "brdf lut", BRDF_LUT_SIZE, 1, 1, BRDF_LUT_FORMAT);
+        let brdf_lut = lut_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("brdf lut encoder") });
+        fullscreen_pass(&mut encoder, &brdf_lut, &lut_pipeline, None);
+        driver.queue.submit(std::iter::once(encoder.finish()));
+
+        let bake_sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ibl bake sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("environment sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let studio = studio_environment(STUDIO_SIZE);
+        let source = studio.create_texture(driver);
+        let (irradiance, prefiltered) = Self::bake_cubes(driver, &bake_bgl, &irradiance_pipeline, &prefilter_pipeline, &bake_sampler, &source, studio.size);
+        let bind_group = Self::create_bind_group(driver, &bgl, &irradiance, &prefiltered, &brdf_lut, &sampler);
+        Ibl { bgl, bake_bgl, irradiance_pipeline, prefilter_pipeline, brdf_lut, bake_sampler, sampler, bind_group }
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    // source is a cube view with its full mip chain, like CubemapImage::create_texture makes
+    pub fn bake(&mut self, driver: &Driver, source: &TextureView, source_size: u32) {
+        let (irradiance, prefiltered) = Self::bake_cubes(
+            driver, &self.bake_bgl, &self.irradiance_pipeline, &self.prefilter_pipeline, &self.bake_sampler, source, source_size,
+        );
+        self.bind_group = Self::create_bind_group(driver, &self.bgl, &irradiance, &prefiltered, &self.brdf_lut, &self.sampler);
+    }
+
+    fn create_bind_group(driver: &Driver, bgl: &BindGroupLayout, irradiance: &TextureView, prefiltered: &TextureView, brdf_lut: &TextureView, sampler: &wgpu::Sampler) -> BindGroup {
+        Cube::create_bg(driver, bgl, &[
+            BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(irradiance) },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(prefiltered) },
+            BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(brdf_lut) },
+            BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(sampler) },
+        ], Some("environment bg"))
+    }
+
+    // six irradiance faces, then six faces for each prefilter mip, all in one submission
+    fn bake_cubes(
+        driver: &Driver,
+        bake_bgl: &BindGroupLayout,
+        irradiance_pipeline: &RenderPipeline,
+        prefilter_pipeline: &RenderPipeline,
+        sampler: &wgpu::Sampler,
+        source: &TextureView,
+        source_size: u32,
+    ) -> (TextureView, TextureView) {
+        let stride = (std::mem::size_of::<BakeUniform>() as u32).max(driver.device.limits().min_uniform_buffer_offset_alignment);
+        let mut uniforms = Vec::new();
+        let mut slot = |params: [f32; 4]| {
+            let offset = uniforms.len() as u32;
+            uniforms.extend_from_slice(bytemuck::bytes_of(&BakeUniform { params }));
+            uniforms.resize((offset + stride) as usize, 0);
+            offset
+        };
+        let irradiance_draws: Vec<(u32, u32)> = (0..6)
+            .map(|face| (face, slot([face as f32, 0.0, source_size as f32, IRRADIANCE_SAMPLES as f32])))
+            .collect();
+        let mut prefilter_draws = Vec::new();
+        for mip in 0..PREFILTER_MIPS {
+            let roughness = mip as f32 / (PREFILTER_MIPS - 1) as f32;
+            for face in 0..6 {
+                prefilter_draws.push((mip, face, slot([face as f32, roughness, source_size as f32, PREFILTER_SAMPLES as f32])));
+            }
+        }
+        let uniform_buffer = driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ibl bake uniforms"),
+            contents: &uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = Cube::create_bg(driver, bake_bgl, &[
+            BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<BakeUniform>() as u64),
+                }),
+            },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(source) },
+            BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ], Some("ibl bake bg"));
+
+        let irradiance = render_texture(driver, "irradiance", IRRADIANCE_SIZE, 6, 1, ENVIRONMENT_FORMAT);
+        let prefiltered = render_texture(driver, "prefiltered environment", PREFILTER_SIZE, 6, PREFILTER_MIPS, ENVIRONMENT_FORMAT);
+        let face_view = |texture: &wgpu::Texture, mip: u32, face: u32| texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("ibl bake face"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_mip_level: mip,
+            mip_level_count: Some(1),
+            base_array_layer: face,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+
+        let mut encoder = driver.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("ibl bake encoder") });
+        for (face, offset) in irradiance_draws {
+            fullscreen_pass(&mut encoder, &face_view(&irradiance, 0, face), irradiance_pipeline, Some((&bind_group, offset)));
+        }
+        for (mip, face, offset) in prefilter_draws {
+            fullscreen_pass(&mut encoder, &face_view(&prefiltered, mip, face), prefilter_pipeline, Some((&bind_group, offset)));
+        }
+        driver.queue.submit(std::iter::once(encoder.finish()));
+
+        let cube_view = |texture: &wgpu::Texture| texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        (cube_view(&irradiance), cube_view(&prefiltered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn studio_is_bright_above_and_dark_below() {
+        let studio = studio_environment(8);
+        let mean = |face: usize| studio.faces[face].iter().map(|p| p[0]).sum::<f32>() / studio.faces[face].len() as f32;
+        assert!(mean(2) > 0.7);
+        assert!(mean(3) < 0.1);
+        // the downsampled chain keeps the average
+        let half = studio.downsample();
+        assert_eq!(half.size, 4);
+        let half_mean = half.faces[3].iter().map(|p| p[0]).sum::<f32>() / half.faces[3].len() as f32;
+        assert!((half_mean - mean(3)).abs() < 1e-5);
+    }
+}
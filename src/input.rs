@@ -0,0 +1,489 @@
+// Input state shared by the native and web front ends.
+// Window events from winit (keys, mouse, scroll and touch, which winit delivers the same way in
+// the browser) and gamepad buttons polled by the front end are tracked here, and presses are
+// turned into named actions through a bindings table that can be changed at runtime.
+// Continuous input (scroll, pinch, dragging) is accumulated until the front end takes it.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use winit::event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+// a touch shorter than this that stayed within TAP_SLOP is a tap
+const TAP_MS: f64 = 300.0;
+// and one held at least this long a long press
+const LONG_PRESS_MS: f64 = 600.0;
+const TAP_SLOP: f32 = 12.0;
+// pixel scrolling (touchpads, browsers) to the line steps of a mouse wheel
+const PIXELS_PER_LINE: f32 = 50.0;
+// zoom steps for doubling the distance between two fingers
+const PINCH_STEPS_PER_DOUBLING: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    CycleDisplayMode,
+    ToggleOutline,
+    ToggleTransparency,
+    CycleTheme,
+    CycleEffect,
+    CycleBackground,
+    ToggleMetal,
+    CycleGround,
+    TurnLight,
+    ToggleSsao,
+    ToggleDepthOfField,
+    ToggleFog,
+    FocusNearer,
+    FocusFarther,
+    CycleProjection,
+    ZoomIn,
+    ZoomOut,
+    // these three are carried out by the front end, see Controls::perform
+    Select,
+    Screenshot,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 20] = [
+        Action::CycleDisplayMode, Action::ToggleOutline, Action::ToggleTransparency, Action::CycleTheme,
+        Action::CycleEffect, Action::CycleBackground, Action::ToggleMetal, Action::CycleGround, Action::TurnLight,
+        Action::ToggleSsao, Action::ToggleDepthOfField, Action::ToggleFog, Action::FocusNearer, Action::FocusFarther,
+        Action::CycleProjection, Action::ZoomIn, Action::ZoomOut, Action::Select, Action::Screenshot, Action::Quit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::CycleDisplayMode => "cycle-display-mode",
+            Action::ToggleOutline => "toggle-outline",
+            Action::ToggleTransparency => "toggle-transparency",
+            Action::CycleTheme => "cycle-theme",
+            Action::CycleEffect => "cycle-effect",
+            Action::CycleBackground => "cycle-background",
+            Action::ToggleMetal => "toggle-metal",
+            Action::CycleGround => "cycle-ground",
+            Action::TurnLight => "turn-light",
+            Action::ToggleSsao => "toggle-ssao",
+            Action::ToggleDepthOfField => "toggle-depth-of-field",
+            Action::ToggleFog => "toggle-fog",
+            Action::FocusNearer => "focus-nearer",
+            Action::FocusFarther => "focus-farther",
+            Action::CycleProjection => "cycle-projection",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::Select => "select",
+            Action::Screenshot => "screenshot",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self, InputError> {
+        Self::ALL.into_iter().find(|a| a.name() == name).ok_or_else(|| InputError::UnknownAction(name.to_string()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gesture {
+    Tap,
+    LongPress,
+}
+
+// something physical a binding reacts to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // index in the standard gamepad layout, 0 is the bottom face button
+    GamepadButton(u32),
+    Gesture(Gesture),
+}
+
+impl Trigger {
+    // KeyboardEvent.code names for keys ("KeyW", "Digit1", "BracketLeft"), MouseLeft, MouseRight,
+    // MouseMiddle, Gamepad0 to Gamepad16, Tap and LongPress
+    pub fn named(name: &str) -> Result<Self, InputError> {
+        let unknown = || InputError::UnknownTrigger(name.to_string());
+        match name {
+            "MouseLeft" => Ok(Trigger::Mouse(MouseButton::Left)),
+            "MouseRight" => Ok(Trigger::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Ok(Trigger::Mouse(MouseButton::Middle)),
+            "Tap" => Ok(Trigger::Gesture(Gesture::Tap)),
+            "LongPress" => Ok(Trigger::Gesture(Gesture::LongPress)),
+            _ => match name.strip_prefix("Gamepad") {
+                Some(index) => index.parse().ok().filter(|i| *i <= 16).map(Trigger::GamepadButton).ok_or_else(unknown),
+                None => key_code_named(name).map(Trigger::Key).ok_or_else(unknown),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Key(code) => write!(f, "{code:?}"),
+            Trigger::Mouse(button) => write!(f, "Mouse{button:?}"),
+            Trigger::GamepadButton(index) => write!(f, "Gamepad{index}"),
+            Trigger::Gesture(gesture) => write!(f, "{gesture:?}"),
+        }
+    }
+}
+
+macro_rules! key_codes {
+    ($($code:ident)*) => {
+        fn key_code_named(name: &str) -> Option<KeyCode> {
+            match name {
+                $(stringify!($code) => Some(KeyCode::$code),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+// the keys that can be bound by name
+key_codes!(
+    KeyA KeyB KeyC KeyD KeyE KeyF KeyG KeyH KeyI KeyJ KeyK KeyL KeyM
+    KeyN KeyO KeyP KeyQ KeyR KeyS KeyT KeyU KeyV KeyW KeyX KeyY KeyZ
+    Digit0 Digit1 Digit2 Digit3 Digit4 Digit5 Digit6 Digit7 Digit8 Digit9
+    ArrowUp ArrowDown ArrowLeft ArrowRight Space Enter Escape Tab Backspace
+    BracketLeft BracketRight Minus Equal Comma Period Slash Semicolon Quote Backquote Backslash
+    F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12
+);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputError {
+    UnknownAction(String),
+    UnknownTrigger(String),
+    // "KeyX=cycle-theme" style bindings without the =
+    BadBinding(String),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::UnknownAction(s) => write!(f, "no action {s:?}, expected one of {}", Action::ALL.map(Action::name).join(", ")),
+            InputError::UnknownTrigger(s) => {
+                write!(f, "no key, button or gesture {s:?}, expected a KeyboardEvent.code like KeyW, MouseLeft, Gamepad0, Tap or LongPress")
+            }
+            InputError::BadBinding(s) => write!(f, "{s:?} should look like KeyX=cycle-theme"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+// Which action each trigger performs. Several triggers can share an action, a trigger has one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    map: HashMap<Trigger, Action>,
+}
+
+impl Default for Bindings {
+    // the keys the native window has always had, plus mouse, touch and gamepad
+    fn default() -> Self {
+        use Action::*;
+        let keys = [
+            (KeyCode::KeyW, CycleDisplayMode), (KeyCode::KeyO, ToggleOutline), (KeyCode::KeyT, ToggleTransparency),
+            (KeyCode::KeyC, CycleTheme), (KeyCode::KeyE, CycleEffect), (KeyCode::KeyB, CycleBackground),
+            (KeyCode::KeyM, ToggleMetal), (KeyCode::KeyG, CycleGround), (KeyCode::KeyL, TurnLight),
+            (KeyCode::Digit1, ToggleSsao), (KeyCode::Digit2, ToggleDepthOfField), (KeyCode::Digit3, ToggleFog),
+            (KeyCode::BracketLeft, FocusNearer), (KeyCode::BracketRight, FocusFarther), (KeyCode::KeyV, CycleProjection),
+            (KeyCode::Equal, ZoomIn), (KeyCode::Minus, ZoomOut), (KeyCode::KeyP, Screenshot), (KeyCode::Escape, Quit),
+        ];
+        // a b x y, the shoulder buttons and the d-pad's up and down
+        let gamepad = [(0, Select), (1, CycleTheme), (2, CycleDisplayMode), (3, CycleProjection), (4, CycleEffect), (5, CycleBackground), (12, ZoomIn), (13, ZoomOut)];
+        let map = keys.into_iter().map(|(code, action)| (Trigger::Key(code), action))
+            .chain(gamepad.into_iter().map(|(index, action)| (Trigger::GamepadButton(index), action)))
+            .chain([
+                (Trigger::Mouse(MouseButton::Left), Select),
+                (Trigger::Gesture(Gesture::Tap), Select),
+                (Trigger::Gesture(Gesture::LongPress), CycleProjection),
+            ])
+            .collect();
+        Bindings { map }
+    }
+}
+
+impl Bindings {
+    pub fn action(&self, trigger: Trigger) -> Option<Action> {
+        self.map.get(&trigger).copied()
+    }
+
+    // replaces whatever the trigger did before
+    pub fn bind(&mut self, trigger: Trigger, action: Action) {
+        self.map.insert(trigger, action);
+    }
+
+    pub fn unbind(&mut self, trigger: Trigger) {
+        self.map.remove(&trigger);
+    }
+
+    pub fn triggers(&self, action: Action) -> impl Iterator<Item = Trigger> + '_ {
+        self.map.iter().filter(move |(_, a)| **a == action).map(|(t, _)| *t)
+    }
+
+    // "KeyX=cycle-theme" binds, "KeyX=" unbinds
+    pub fn apply(&mut self, binding: &str) -> Result<(), InputError> {
+        let (trigger, action) = binding.split_once('=').ok_or_else(|| InputError::BadBinding(binding.to_string()))?;
+        let trigger = Trigger::named(trigger.trim())?;
+        match action.trim() {
+            "" => self.unbind(trigger),
+            action => self.bind(trigger, Action::named(action)?),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TouchPoint {
+    start: [f32; 2],
+    position: [f32; 2],
+    start_ms: f64,
+    // moved past TAP_SLOP or shared the screen with another finger, so no tap or long press
+    gesture_over: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    bindings: Bindings,
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+    gamepad_buttons: Vec<bool>,
+    gamepad_axes: Vec<f32>,
+    // physical pixels, the mouse or the last finger down
+    cursor: Option<[f32; 2]>,
+    pointer_delta: [f32; 2],
+    // positive zooms in, one step per wheel notch
+    zoom: f32,
+    touches: HashMap<u64, TouchPoint>,
+    actions: Vec<Action>,
+}
+
+impl InputState {
+    pub fn new(bindings: Bindings) -> Self {
+        InputState { bindings, ..Default::default() }
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    fn trigger(&mut self, trigger: Trigger) {
+        if let Some(action) = self.bindings.action(trigger) {
+            self.actions.push(action);
+        }
+    }
+
+    // false for events that are not input, now_ms is only compared with itself for gestures
+    pub fn handle_event(&mut self, event: &WindowEvent, now_ms: f64) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(code), state, .. }, .. } => {
+                match state {
+                    // held keys repeat, only the first press triggers
+                    ElementState::Pressed if self.keys.insert(*code) => self.trigger(Trigger::Key(*code)),
+                    ElementState::Pressed => {}
+                    ElementState::Released => {
+                        self.keys.remove(code);
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed if self.buttons.insert(*button) => self.trigger(Trigger::Mouse(*button)),
+                ElementState::Pressed => {}
+                ElementState::Released => {
+                    self.buttons.remove(button);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => self.move_pointer([position.x as f32, position.y as f32]),
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / PIXELS_PER_LINE,
+                };
+            }
+            WindowEvent::Touch(touch) => self.touch(touch, now_ms),
+            WindowEvent::Focused(false) => {
+                // releases are lost while unfocused
+                self.keys.clear();
+                self.buttons.clear();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn move_pointer(&mut self, position: [f32; 2]) {
+        if let Some([x, y]) = self.cursor {
+            self.pointer_delta[0] += position[0] - x;
+            self.pointer_delta[1] += position[1] - y;
+        }
+        self.cursor = Some(position);
+    }
+
+    fn touch(&mut self, touch: &Touch, now_ms: f64) {
+        let position = [touch.location.x as f32, touch.location.y as f32];
+        match touch.phase {
+            TouchPhase::Started => {
+                let crowded = !self.touches.is_empty();
+                for other in self.touches.values_mut() {
+                    other.gesture_over = true;
+                }
+                self.touches.insert(touch.id, TouchPoint { start: position, position, start_ms: now_ms, gesture_over: crowded });
+                if !crowded {
+                    self.cursor = Some(position);
+                }
+            }
+            TouchPhase::Moved => {
+                let Some(previous) = self.touches.get(&touch.id).copied() else { return };
+                match self.touches.len() {
+                    // pinching, the other finger is the pivot
+                    2 => {
+                        let other = self.touches.iter().find(|(id, _)| **id != touch.id).map(|(_, p)| p.position).expect("two touches");
+                        let (before, after) = (distance(previous.position, other), distance(position, other));
+                        if before > 0.0 && after > 0.0 {
+                            self.zoom += (after / before).log2() * PINCH_STEPS_PER_DOUBLING;
+                        }
+                    }
+                    1 => self.move_pointer(position),
+                    _ => {}
+                }
+                let point = self.touches.get_mut(&touch.id).expect("checked above");
+                point.position = position;
+                point.gesture_over |= distance(point.start, position) > TAP_SLOP;
+            }
+            TouchPhase::Ended => {
+                if let Some(point) = self.touches.remove(&touch.id).filter(|p| !p.gesture_over) {
+                    self.cursor = Some(position);
+                    match now_ms - point.start_ms {
+                        held if held < TAP_MS => self.trigger(Trigger::Gesture(Gesture::Tap)),
+                        held if held >= LONG_PRESS_MS => self.trigger(Trigger::Gesture(Gesture::LongPress)),
+                        _ => {}
+                    }
+                }
+            }
+            TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+            }
+        }
+    }
+
+    // the standard gamepad layout's buttons and axes, polled once a frame by the front end
+    pub fn set_gamepad(&mut self, buttons: &[bool], axes: &[f32]) {
+        for (index, pressed) in buttons.iter().enumerate() {
+            if *pressed && !self.gamepad_buttons.get(index).copied().unwrap_or(false) {
+                self.trigger(Trigger::GamepadButton(index as u32));
+            }
+        }
+        self.gamepad_buttons = buttons.to_vec();
+        self.gamepad_axes = axes.to_vec();
+    }
+
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.actions)
+    }
+
+    // wheel notches and pinches since the last call, positive zooms in
+    pub fn take_zoom(&mut self) -> f32 {
+        std::mem::take(&mut self.zoom)
+    }
+
+    // mouse movement and one finger drags since the last call, in physical pixels
+    pub fn take_pointer_delta(&mut self) -> [f32; 2] {
+        std::mem::take(&mut self.pointer_delta)
+    }
+
+    pub fn cursor(&self) -> Option<[f32; 2]> {
+        self.cursor
+    }
+
+    pub fn is_key_down(&self, code: KeyCode) -> bool {
+        self.keys.contains(&code)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn gamepad_axis(&self, index: usize) -> f32 {
+        self.gamepad_axes.get(index).copied().unwrap_or(0.0)
+    }
+
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    // any key or button bound to the action is held down
+    pub fn is_held(&self, action: Action) -> bool {
+        self.bindings.triggers(action).any(|trigger| match trigger {
+            Trigger::Key(code) => self.keys.contains(&code),
+            Trigger::Mouse(button) => self.buttons.contains(&button),
+            Trigger::GamepadButton(index) => self.gamepad_buttons.get(index as usize).copied().unwrap_or(false),
+            Trigger::Gesture(_) => false,
+        })
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, Force};
+
+    fn touch(id: u64, phase: TouchPhase, x: f64, y: f64) -> WindowEvent {
+        WindowEvent::Touch(Touch {
+            device_id: unsafe { DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None::<Force>,
+            id,
+        })
+    }
+
+    #[test]
+    fn bindings_parse_and_rebind() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.action(Trigger::Key(KeyCode::KeyC)), Some(Action::CycleTheme));
+        bindings.apply("KeyX=cycle-theme").unwrap();
+        bindings.apply("KeyC=").unwrap();
+        assert_eq!(bindings.action(Trigger::Key(KeyCode::KeyC)), None);
+        assert_eq!(bindings.triggers(Action::CycleTheme).count(), 2);
+        assert_eq!(Trigger::named("Gamepad3"), Ok(Trigger::GamepadButton(3)));
+        assert_eq!(Trigger::named("MouseRight").unwrap().to_string(), "MouseRight");
+        assert_eq!(Trigger::named("BracketLeft").unwrap().to_string(), "BracketLeft");
+        assert!(matches!(bindings.apply("KeyX"), Err(InputError::BadBinding(_))));
+        assert!(matches!(bindings.apply("Hyper=quit"), Err(InputError::UnknownTrigger(_))));
+        assert!(matches!(bindings.apply("KeyX=fly"), Err(InputError::UnknownAction(_))));
+    }
+
+    #[test]
+    fn taps_pinches_and_gamepad_edges() {
+        let mut input = InputState::new(Bindings::default());
+        input.handle_event(&touch(1, TouchPhase::Started, 100.0, 100.0), 0.0);
+        input.handle_event(&touch(1, TouchPhase::Ended, 102.0, 101.0), 120.0);
+        assert_eq!(input.take_actions(), vec![Action::Select]);
+        assert_eq!(input.cursor(), Some([102.0, 101.0]));
+
+        // fingers 100 apart spread to 200, one doubling
+        input.handle_event(&touch(1, TouchPhase::Started, 100.0, 100.0), 1000.0);
+        input.handle_event(&touch(2, TouchPhase::Started, 200.0, 100.0), 1010.0);
+        input.handle_event(&touch(2, TouchPhase::Moved, 300.0, 100.0), 1100.0);
+        input.handle_event(&touch(1, TouchPhase::Ended, 100.0, 100.0), 1150.0);
+        input.handle_event(&touch(2, TouchPhase::Ended, 300.0, 100.0), 1160.0);
+        assert!((input.take_zoom() - PINCH_STEPS_PER_DOUBLING).abs() < 1e-5);
+        assert!(input.take_actions().is_empty());
+
+        input.set_gamepad(&[true, false], &[0.5]);
+        input.set_gamepad(&[true, true], &[0.5]);
+        assert_eq!(input.take_actions(), vec![Action::Select, Action::CycleTheme]);
+        assert!(input.is_held(Action::Select));
+        assert_eq!(input.gamepad_axis(0), 0.5);
+    }
+}
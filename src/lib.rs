@@ -1,161 +1,261 @@
-use std::{sync::Arc, time::{self, Instant}};
+use std::sync::Arc;
 
 use cgmath::{Point3, Vector3};
 use wasm_bindgen::prelude::*;
 use wasm_driver::Driver;
-use web_sys::{console, HtmlCanvasElement, Node};
-use wgpu::{rwh::HasWindowHandle, SurfaceConfiguration};
+use web_sys::HtmlCanvasElement;
+use pacing::FramePacer;
+use projection::Projection;
+use visibility::Visibility;
+use controls::Controls;
+use input::{Action, Bindings, InputState};
+use web_api::{CubeCommand, CubeHandle};
 use wgpu_helpers::Cube;
-use winit::{dpi::{LogicalSize, PhysicalSize}, event::{Event, StartCause, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::Window};
+use winit::{dpi::LogicalSize, event::{Event, WindowEvent}, event_loop::EventLoop, window::Window};
+pub mod background;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+pub mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod controls;
+pub mod dpi;
+pub mod effects;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod export;
+pub mod ibl;
+pub mod input;
+pub mod material;
+pub mod model;
+pub mod pacing;
+pub mod picking;
+pub mod pipeline_cache;
+pub mod post;
+pub mod primitives;
+pub mod projection;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shader_reload;
+pub mod shader_validation;
+pub mod shadow;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod state;
+pub mod theme;
+pub mod transparency;
+pub mod visibility;
 pub mod wasm_driver;
+pub mod web_api;
 pub mod wgpu_helpers;
+pub mod winding;
+pub mod wireframe;
 
 
 
-const IS_PERSPECTIVE: bool = true;
+// css size of the canvas run creates when it is not given one
+const DEFAULT_CSS_SIZE: LogicalSize<u32> = LogicalSize::new(450, 400);
 
-pub async fn run_wasm(event_loop: EventLoop<()>, window:Arc<Window>, location: (u32,u32,f32), ratio: f32, canvas: Option<HtmlCanvasElement>) {
-    console::log_1(&"in run wasm".into());
-    let driver = Driver::new(&window, canvas).await;
-    let surface_capabilities = driver.surface.get_capabilities(&driver.adapter);
-    let surface_format = surface_capabilities.formats.iter().copied().
-    find(|f| f
-        .is_srgb())
-        .unwrap_or(surface_capabilities.formats[0]);
-    let inner_size = window.inner_size();
-    let mut driver_config = SurfaceConfiguration{
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: surface_format,
-        width: inner_size.width,
-        height: inner_size.height,
-        present_mode: surface_capabilities.present_modes[0],
-        desired_maximum_frame_latency: 2,
-        alpha_mode: surface_capabilities.alpha_modes[0],
-        view_formats: vec![],
-    };
-
-    if driver_config.width == 0 || driver_config.height == 0 {
-    console::log_1(&format!("inner window dimensions are {} and {}!", inner_size.width,inner_size.height).into());
-    console::log_1(&format!("Driver config dimensions are {} and {}!", driver_config.width, driver_config.height).into());
-    panic!();
+fn performance_now() -> f64 {
+    web_sys::window()
+    .unwrap()
+    .performance()
+    .unwrap()
+    .now()
+}
 
+// buttons and axes of the first connected gamepad, browsers only list one after a button press
+fn poll_gamepad() -> Option<(Vec<bool>, Vec<f32>)> {
+    let gamepads = web_sys::window()?.navigator().get_gamepads().ok()?;
+    let gamepad = gamepads.iter()
+        .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+        .find(|pad| pad.connected())?;
+    let buttons = gamepad.buttons().iter().map(|b| b.unchecked_into::<web_sys::GamepadButton>().pressed()).collect();
+    let axes = gamepad.axes().iter().map(|a| a.as_f64().unwrap_or(0.0) as f32).collect();
+    Some((buttons, axes))
 }
 
+// Web front end: the canvas backed window, the browser clock and the winit event loop.
+// All gpu work goes through the shared Cube renderer.
+pub async fn run_wasm(event_loop: EventLoop<()>, window:Arc<Window>, canvas: Option<HtmlCanvasElement>, handle: CubeHandle) {
+    log::info!("in run wasm");
+    let driver = Driver::new(&window, canvas).await;
+    // set through CubeHandle.setMaxPixelRatio
+    let mut max_pixel_ratio: Option<f64> = None;
+    handle.set_max_texture_size(driver.device.limits().max_texture_dimension_2d);
+    // the canvas may not have been measured yet, the first Resized brings the real size
+    let backing = dpi::backing_size(window.inner_size(), window.scale_factor(), max_pixel_ratio);
+    let driver_config = driver.surface_config(backing.width.max(1), backing.height.max(1));
+    driver.configure(&driver_config);
+
     let camera_eye: Point3<f32> = (3.0, 1.5, 3.0).into();
     let look_dir: Point3<f32> = (0.0, 0.0, 0.0).into();
     let up_dir: Vector3<f32> = cgmath::Vector3::unit_y();
-    let model_mat = wgpu_helpers::create_transforms([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
-    let (view_mat, project_mat, view_project_mat) = wgpu_helpers::create_view_projection(camera_eye, look_dir, up_dir, driver_config.width as f32/ driver_config.height as f32, IS_PERSPECTIVE);
-    let mvp_mat = view_project_mat * model_mat;
-    let mvp_ref = mvp_mat.as_ref();
-    let bgls = &[wgpu::BindGroupLayoutEntry {
-        binding: 0,
-        visibility: wgpu::ShaderStages::VERTEX,
-        ty: wgpu::BindingType::Buffer {
-            ty: wgpu::BufferBindingType::Uniform,
-            has_dynamic_offset: false,
-            min_binding_size: None,
-            },
-        count: None,
-    }];
-    let uniform_buffer = Cube::create_buffer(&driver, mvp_ref);
-    let bge = &[wgpu::BindGroupEntry {
-        binding: 0,
-        resource: uniform_buffer.as_entire_binding()
-     }];
-
-     let uniform_bgl = Cube::create_bgl(&driver, bgls, None);
-     let uniform_bg = Cube::create_bg(&driver, &uniform_bgl, bge, None);
-     let buffer_pll = Cube::create_buffer_render_pipeline_layout(&driver, &[&uniform_bgl]);
-     let buffer_shader = &Cube::create_shader(&driver, None);
-     let driver_cfg_clone = driver_config.clone();
-    
-    let vertex_buffer =Cube::create_vertex_buffer(&driver);
-    let buffer_render_pipeline = Cube::create_buffer_render_pipeline(&driver, Some(&buffer_pll), buffer_shader, driver_config.format);
-     let mut cube_render = Cube {
-        config: driver_config,
-        model_mat: model_mat,
-        uniform_bg,
-        uniform_buffer: uniform_buffer,
-        uniform_bgl,
-        vertex_buffer,
-        buffer_render_pipeline, 
-        view_mat,
-        project_mat,
-    };
-    
-    console::log_1(&"cube render created".into());
+    let mut cube_render = Cube::new(&driver, &driver_config, camera_eye, look_dir, up_dir, Projection::default());
+    cube_render.set_pixel_ratio(&driver, dpi::pixel_ratio(window.scale_factor(), max_pixel_ratio) as f32);
+    log::info!("cube render created");
+
     let win_clone = window.clone();
-    console::log_1(&"window render".into());
-    driver.surface.configure(&driver.device, &driver_cfg_clone);
-    console::log_1(&"surface configure".into());
-    let render_start_time = web_sys::window()
-    .unwrap()
-    .performance()
-    .unwrap()
-    .now() as f32 % 100_000_000.0;
-    //console::log_1(String::from(render_start_time).as_str());
-    //cube_render.render(&driver);
-    event_loop.run(move |event, control_flow| {
-        control_flow.set_control_flow(ControlFlow::Poll);
+    let mut pacer = FramePacer::new(None, false);
+    let mut input = InputState::new(Bindings::default());
+    let mut controls = Controls::new(false);
+    // stops drawing while the canvas is scrolled away or the tab is hidden
+    #[cfg(target_arch = "wasm32")]
+    let visibility = {
+        use winit::platform::web::WindowExtWebSys;
+        window.canvas().and_then(|canvas| {
+            Visibility::watch(&canvas, event_loop.create_proxy())
+                .map_err(|e| log::warn!("not watching canvas visibility: {e:?}"))
+                .ok()
+        })
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let visibility: Option<Visibility> = None;
+    let _ = event_loop.run(move |event, control_flow| {
         match event {
-            Event::WindowEvent {
-                event: WindowEvent::Resized(size),
-                ..
-            } => {
-                cube_render.config.width = size.width;
-                cube_render.config.height = size.height;
-                driver.surface.configure(&driver.device, &cube_render.config);
-                win_clone.request_redraw();
-            }
-            Event::AboutToWait => {win_clone.request_redraw()}
-
-            Event::WindowEvent { window_id, event: WindowEvent::RedrawRequested, ..} => {
-                let curr_time:f32 = web_sys::window().unwrap().performance().unwrap().now() as f32 % 1_000_000.0;
-                let time_diff = curr_time - render_start_time;
-                console::log_1(&format!("curr_time: {}, time diff: {}, render_start_time: {}", curr_time, time_diff,time_diff).into());
-                cube_render.update_cube_render(&driver, time_diff);
-                cube_render.render(&driver);
+            // CubeHandle wakes the loop whenever JS queues a command, Visibility when it changes
+            Event::UserEvent(()) => pacer.request_frame(),
+            Event::AboutToWait => {
+                // applied even while the canvas is hidden, so setters and screenshots never wait on it,
+                // only drawing does
+                for command in handle.drain() {
+                    match command {
+                        CubeCommand::FrameCap(fps) => pacer.set_max_fps(fps),
+                        CubeCommand::Idle(idle) => pacer.set_idle(idle),
+                        CubeCommand::MaxPixelRatio(ratio) => {
+                            max_pixel_ratio = ratio;
+                            resize_backing(&mut cube_render, &driver, win_clone.inner_size(), win_clone.scale_factor(), max_pixel_ratio);
+                        }
+                        CubeCommand::Bind(trigger, Some(action)) => input.bindings_mut().bind(trigger, action),
+                        CubeCommand::Bind(trigger, None) => input.bindings_mut().unbind(trigger),
+                        command => command.apply(&mut cube_render, &driver),
+                    }
+                }
+                if let Some(visibility) = &visibility {
+                    pacer.set_visible(visibility.visible());
+                }
+                let (flow, redraw) = pacer.control_flow(performance_now());
+                control_flow.set_control_flow(flow);
+                if redraw {
+                    win_clone.request_redraw();
+                }
             }
-            Event::NewEvents(start_cause) => {
-
-                console::log_1(&format!("event loop new events {0:#?}", start_cause).into());
+            Event::WindowEvent { event, .. } => {
+                // every window event goes past the input state first, like State::input on native
+                if input.handle_event(&event, performance_now()) {
+                    // input positions are canvas pixels, picking wants surface pixels
+                    let cursor = input.cursor().map(|c| to_backing(c, win_clone.scale_factor(), max_pixel_ratio));
+                    if update_hover(&mut cube_render, &driver, cursor, &handle) {
+                        pacer.request_frame();
+                    }
+                    if apply_input(&mut input, &mut controls, &mut cube_render, &driver, &handle) {
+                        pacer.request_frame();
+                    }
+                }
+                match event {
+                    // physical size, configuring the surface with it sets the canvas' backing store
+                    WindowEvent::Resized(size) => {
+                        resize_backing(&mut cube_render, &driver, size, win_clone.scale_factor(), max_pixel_ratio);
+                        pacer.request_frame();
+                    }
+                    // a Resized with the new physical size follows whenever it changed
+                    WindowEvent::ScaleFactorChanged { .. } => pacer.request_frame(),
+                    // the browser can still ask for a redraw of a hidden canvas, it keeps until shown
+                    WindowEvent::RedrawRequested if !pacer.visible() => {}
+                    WindowEvent::RedrawRequested => {
+                        // gamepads have no events, they are read once a frame
+                        if let Some((buttons, axes)) = poll_gamepad() {
+                            input.set_gamepad(&buttons, &axes);
+                            if apply_input(&mut input, &mut controls, &mut cube_render, &driver, &handle) {
+                                pacer.request_frame();
+                            }
+                        }
+                        let animation_ms = pacer.frame_started(performance_now());
+                        if cube_render.advance_projection(&driver, performance_now()) {
+                            pacer.request_frame();
+                        }
+                        cube_render.update_cube_render(&driver, animation_ms as f32);
+                        // the cube turns under a still pointer too
+                        let cursor = input.cursor().map(|c| to_backing(c, win_clone.scale_factor(), max_pixel_ratio));
+                        update_hover(&mut cube_render, &driver, cursor, &handle);
+                        if let Err(e) = cube_render.render(&driver) {
+                            log::warn!("surface error: {e:?}");
+                        }
+                    }
+                    _ => {}
+                }
             }
-            _ => {console::log_1(&"random event".into());}
+            _ => {}
         }
     });
 
 }
 
-fn calculate_dimensions(res: i32, width: u32, height: u32) -> (u32, u32, f32){
-    let aspect_ratio = height as f64/ width as f64;
-    let x_pixels = (res as isize as f64/aspect_ratio).sqrt().floor() as u32;
-    let pixel_size = width as f64/ x_pixels as f64;
-    let y_pixels = (height as f64/pixel_size).floor() as u32;
-    (x_pixels, y_pixels, pixel_size as f32)
+
+
+// size is the canvas' physical size, the surface gets at most max_pixel_ratio pixels per css pixel of it
+fn resize_backing(cube: &mut Cube, driver: &Driver, size: winit::dpi::PhysicalSize<u32>, scale_factor: f64, max_pixel_ratio: Option<f64>) {
+    let backing = dpi::backing_size(size, scale_factor, max_pixel_ratio);
+    if backing.width > 0 && backing.height > 0 {
+        cube.set_pixel_ratio(driver, dpi::pixel_ratio(scale_factor, max_pixel_ratio) as f32);
+        cube.resize(driver, backing.width, backing.height);
+    }
+}
+
+// canvas pixels to surface pixels, they differ when max_pixel_ratio caps the backing store
+fn to_backing(position: [f32; 2], scale_factor: f64, max_pixel_ratio: Option<f64>) -> [f32; 2] {
+    let scale = (dpi::pixel_ratio(scale_factor, max_pixel_ratio) / scale_factor) as f32;
+    [position[0] * scale, position[1] * scale]
 }
 
+// runs what the input triggered since the last call, true when the scene changed
+fn apply_input(input: &mut InputState, controls: &mut Controls, cube: &mut Cube, driver: &Driver, handle: &CubeHandle) -> bool {
+    let mut changed = controls.zoom(cube, driver, input.take_zoom());
+    for action in input.take_actions() {
+        match action {
+            // a tap moves the cursor to where it landed, so the hover is already up to date
+            Action::Select => {
+                if let Some(face) = cube.hovered_face() {
+                    handle.face_clicked(face);
+                }
+            }
+            action => changed |= controls.perform(cube, driver, action),
+        }
+        handle.action_triggered(action);
+    }
+    changed
+}
 
+// highlights the face under the cursor and tells JS when that changed
+fn update_hover(cube: &mut Cube, driver: &Driver, cursor: Option<[f32; 2]>, handle: &CubeHandle) -> bool {
+    let face = cursor.and_then(|cursor| cube.face_at(cursor));
+    let changed = cube.set_hovered_face(driver, face);
+    if changed {
+        handle.face_hovered(face);
+    }
+    changed
+}
 
+// theme is one of CubeHandle.themeNames(), the returned handle changes colors later on.
+// width and height are the css size of a canvas run creates itself, a canvas passed in keeps
+// whatever size the page's css gives it. Either way the backing store is that size times
+// devicePixelRatio, read as it changes, so _pixel_ratio is only there for older callers.
 #[wasm_bindgen]
-pub fn run(pixel_ratio: f32, width: u32, height: u32,canvas: Option<HtmlCanvasElement>) {
-    let res = 1000;
-    let event_loop = EventLoop::new().unwrap();
-    let dimensions = calculate_dimensions(res, width, height);
-    
-    if !(dimensions.0 == 0 || dimensions.1 == 0) {
-        console::log_1(&format!("Calculated dimensions are res:{0}\twidth:{1}\theight:{2}",dimensions.0,dimensions.1, dimensions.2).into());
+pub fn run(_pixel_ratio: f32, width: u32, height: u32,canvas: Option<HtmlCanvasElement>, theme: Option<String>) -> Result<CubeHandle, JsError> {
+    let handle = CubeHandle::default();
+    if let Some(theme) = theme {
+        handle.set_theme(&theme)?;
     }
+    let event_loop = EventLoop::new().unwrap();
+    handle.set_waker(event_loop.create_proxy());
+    let css_size = match (width, height) {
+        (0, _) | (_, 0) => DEFAULT_CSS_SIZE,
+        _ => LogicalSize::new(width, height),
+    };
 
     
-    
     #[cfg(target_arch = "wasm32")] //, target_os = "unknown"))]
     use winit::platform::web::WindowExtWebSys;
 
-    use wasm_bindgen_futures;
-    use web_sys;
     console_log::init().expect("could not initialize logger");
-    console::log_1(&"Hello from before run".into());
+    log::info!("Hello from before run");
     let window_builder = {
     #[cfg(target_arch = "wasm32")]
     {
@@ -173,8 +273,11 @@ pub fn run(pixel_ratio: f32, width: u32, height: u32,canvas: Option<HtmlCanvasEl
     }
 };
 
+    let window_builder = match canvas {
+        Some(_) => window_builder,
+        None => window_builder.with_inner_size(css_size),
+    };
     let window = Arc::new(window_builder
-        .with_inner_size(PhysicalSize::new(450, 400))
         .build(&event_loop)
         .unwrap());
 
@@ -207,9 +310,8 @@ pub fn run(pixel_ratio: f32, width: u32, height: u32,canvas: Option<HtmlCanvasEl
                 wasm_bindgen_futures::spawn_local(run_wasm(
                     event_loop,
                     window,
-                    dimensions,
-                    pixel_ratio * dimensions.2,
                     canvas,
+                    handle.clone(),
                 ));
-            
+                Ok(handle)
         }
@@ -1,70 +1,109 @@
-use std::sync::Arc;
-use std::time;
-
-use wgpu::SurfaceError;
-use winit::dpi::PhysicalSize;
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::event::*;
-//#[cfg(target_arch = "wasm32")]
-//use winit::platform::pump_events::EventLoopExtPumpEvents;
-use winit::window::WindowBuilder;
-
-mod state;
-pub mod wasm_driver;
-pub mod wgpu_helpers;
-mod camera;
-
-
-
+// The native window and the offscreen modes. The browser build starts from the library's run()
+// instead, so on wasm this binary is empty.
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    std::sync::Arc,
+    std::time,
+    cube_take_two::cli::{CliError, Mode, Options},
+    cube_take_two::pacing::FramePacer,
+    cube_take_two::state,
+    wgpu::SurfaceError,
+    winit::dpi::LogicalSize,
+    winit::event_loop::EventLoop,
+    winit::event::*,
+    winit::window::{Fullscreen, WindowBuilder},
+};
 
+#[cfg(target_arch = "wasm32")]
+fn main() {}
 
+#[cfg(not(target_arch = "wasm32"))]
 fn main() {
     env_logger::init();
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(CliError::Help) => {
+            println!("{}", CliError::Help);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    if options.mode != Mode::Window {
+        if let Err(e) = run_headless(&options) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop: EventLoop<()> = EventLoop::new().unwrap();
-    let window = Arc::new(WindowBuilder::new().build(&event_loop).unwrap());
-    window.set_title("cube with distinct face colors");
+    let mut window_builder = WindowBuilder::new().with_title(&options.title);
+    if let Some((width, height)) = options.size {
+        window_builder = window_builder.with_inner_size(LogicalSize::new(width, height));
+    }
+    if options.fullscreen {
+        window_builder = window_builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = Arc::new(window_builder.build(&event_loop).unwrap());
     let window_clone = window.clone();
-    let mut state = pollster::block_on(state::State::new(&window));
+    let mut state = pollster::block_on(state::State::new(&window, &options));
+    state.set_scale_factor(window.scale_factor());
     let render_start_time = time::Instant::now();
+    let now_ms = move || render_start_time.elapsed().as_secs_f64() * 1000.0;
+    let mut pacer = FramePacer::new(options.max_fps, options.idle);
     let _ = event_loop.run(move |event, control_flow| {
         match event {
-            Event::AboutToWait => {window_clone.request_redraw();}
+            Event::AboutToWait => {
+                let (flow, redraw) = pacer.control_flow(now_ms());
+                control_flow.set_control_flow(flow);
+                if redraw {
+                    window_clone.request_redraw();
+                }
+            }
             Event::WindowEvent {
                 ref event,
                 window_id,
             } if window_id == window_clone.id() => {
-                if !state.input(event) {
-                    match event { WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {..} => {
-                            control_flow.exit();
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { scale_factor,.. } => {
-                            // Use scale factor to adjust the size and request the inner size change
-                            let new_size = PhysicalSize {
-                                width: (*scale_factor * state.size.width as f64) as u32,
-                                height: (*scale_factor * state.size.height as f64) as u32,
-                            };
-                            state.resize(new_size);
-                            
+                // bound input changes what is on screen, idle mode has to draw it
+                if state.input(event, now_ms()) {
+                    pacer.request_frame();
+                }
+                if state.quit_requested() {
+                    control_flow.exit();
+                    return;
+                }
+                match event {
+                    WindowEvent::CloseRequested => control_flow.exit(),
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                        pacer.request_frame();
+                    }
+                    // winit resizes the window to keep its logical size and reports the physical
+                    // result, which may not come as a separate Resized on every platform
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        state.set_scale_factor(*scale_factor);
+                        state.resize(window_clone.inner_size());
+                        pacer.request_frame();
+                    }
+                    WindowEvent::RedrawRequested => {
+                        let animation_ms = pacer.frame_started(now_ms());
+                        if state.advance_projection(now_ms()) {
+                            pacer.request_frame();
                         }
-                        WindowEvent::RedrawRequested => {
-                            let current_time = std::time::Instant::now();
-                            let time_diff = current_time - render_start_time;
-                            state.update(time_diff);
-                            match state.render() {
-                                Ok(_) => {}
-                                Err(SurfaceError::Lost) => state.resize(state.size),
-                                Err(SurfaceError::OutOfMemory) => {
-                                    control_flow.exit();
-                                }
-                                Err(e) => eprintln!("{:?}", e),
+                        state.update(time::Duration::from_secs_f64(animation_ms / 1000.0));
+                        match state.render() {
+                            Ok(_) => {}
+                            Err(SurfaceError::Lost) => state.resize(state.size),
+                            Err(SurfaceError::OutOfMemory) => {
+                                control_flow.exit();
                             }
+                            Err(e) => eprintln!("{:?}", e),
                         }
-                        _ => {}
                     }
+                    _ => {}
                 }
             }
             _ => {}
@@ -74,3 +113,22 @@ fn main() {
 );
 }
 
+// --headless, --export and --benchmark draw offscreen without ever opening a window
+#[cfg(not(target_arch = "wasm32"))]
+fn run_headless(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let mut state = pollster::block_on(state::State::headless(options));
+    match &options.mode {
+        Mode::Headless(path) => {
+            state.update(time::Duration::ZERO);
+            state.capture_frame()?.save_png(path)?;
+            println!("wrote {}", path.display());
+        }
+        Mode::Export(settings) => {
+            state.export(settings)?;
+            println!("wrote {} frames to {}", settings.frame_count(), settings.output.display());
+        }
+        Mode::Benchmark { frames } => println!("{}", state.benchmark(*frames)?),
+        Mode::Window => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,632 @@
+// Materials describe how an object is shaded: which wgsl file, how it blends,
+// what gets culled and how it treats the depth buffer, plus a small uniform block.
+// Objects only hold a MaterialId, the library owns the gpu side and shares a
+// pipeline between every material that ends up with the same PipelineKey.
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer, PipelineLayout, ShaderModule, TextureFormat};
+
+use crate::effects::FaceEffect;
+use crate::ibl::Ibl;
+use crate::shadow::{ShadowMap, ShadowSettings};
+use crate::pipeline_cache::{PassKind, PipelineCache, RenderPipelineKey};
+use crate::transparency::DrawPass;
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::{Cube, Vertex, DEPTH_FORMAT, UNIFORM_BGL_ENTRIES};
+use crate::wireframe::OutlineStyle;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialShader {
+    Unlit,
+    Lit,
+    Textured,
+    Barycentric,
+    Outline,
+    Procedural,
+    Pbr,
+    Ground,
+}
+
+// the scene wide bind group a shader reads from group 2
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SceneGroup {
+    Environment,
+    Shadows,
+}
+
+impl MaterialShader {
+    pub const ALL: [MaterialShader; 8] = [
+        MaterialShader::Unlit,
+        MaterialShader::Lit,
+        MaterialShader::Textured,
+        MaterialShader::Barycentric,
+        MaterialShader::Outline,
+        MaterialShader::Procedural,
+        MaterialShader::Pbr,
+        MaterialShader::Ground,
+    ];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            MaterialShader::Unlit => "shader.wgsl",
+            MaterialShader::Lit => "lit.wgsl",
+            MaterialShader::Textured => "textured.wgsl",
+            MaterialShader::Barycentric => "wireframe.wgsl",
+            MaterialShader::Outline => "outline.wgsl",
+            MaterialShader::Procedural => "procedural.wgsl",
+            MaterialShader::Pbr => "pbr.wgsl",
+            MaterialShader::Ground => "ground.wgsl",
+        }
+    }
+
+    pub fn source(self) -> &'static str {
+        match self {
+            MaterialShader::Unlit => include_str!("shaders/shader.wgsl"),
+            MaterialShader::Lit => include_str!("shaders/lit.wgsl"),
+            MaterialShader::Textured => include_str!("shaders/textured.wgsl"),
+            MaterialShader::Barycentric => include_str!("shaders/wireframe.wgsl"),
+            MaterialShader::Outline => include_str!("shaders/outline.wgsl"),
+            MaterialShader::Procedural => include_str!("shaders/procedural.wgsl"),
+            MaterialShader::Pbr => include_str!("shaders/pbr.wgsl"),
+            MaterialShader::Ground => include_str!("shaders/ground.wgsl"),
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.file_name() == name)
+    }
+
+    // shaders with an fs_oit entry point for weighted blended transparency
+    pub fn supports_oit(self) -> bool {
+        matches!(self, MaterialShader::Unlit | MaterialShader::Lit | MaterialShader::Textured)
+    }
+
+    pub fn scene_group(self) -> Option<SceneGroup> {
+        match self {
+            MaterialShader::Pbr => Some(SceneGroup::Environment),
+            MaterialShader::Ground => Some(SceneGroup::Shadows),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Opaque,
+    AlphaBlend,
+    Additive,
+}
+
+impl BlendMode {
+    pub fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        }
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct MaterialUniform {
+    pub base_color: [f32; 4],
+    // meaning depends on the shader, see the material_uniform struct in each wgsl file
+    pub params: [f32; 4],
+}
+
+pub const MATERIAL_UNIFORM_SIZE: u64 = std::mem::size_of::<MaterialUniform>() as u64;
+
+// group 1 of every material shader, shaders that don't sample just ignore the texture
+pub const MATERIAL_BGL_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(MATERIAL_UNIFORM_SIZE),
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    pub shader: MaterialShader,
+    pub blend: BlendMode,
+    pub topology: wgpu::PrimitiveTopology,
+    pub cull_mode: Option<wgpu::Face>,
+    // which winding counts as the front, the generated meshes are all counter clockwise
+    pub front_face: wgpu::FrontFace,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+    // blended materials draw their back faces in a pass before the front ones
+    pub two_sided: bool,
+    pub uniform: MaterialUniform,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            shader: MaterialShader::Unlit,
+            blend: BlendMode::Opaque,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            front_face: wgpu::FrontFace::Ccw,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth_write: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            two_sided: false,
+            uniform: MaterialUniform {
+                base_color: [1.0, 1.0, 1.0, 1.0],
+                params: [0.0; 4],
+            },
+        }
+    }
+}
+
+impl Material {
+    // vertex colors as they are, what the cube has always drawn with
+    pub fn unlit() -> Self {
+        Material {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        }
+    }
+
+    pub fn lit(ambient: f32) -> Self {
+        Material {
+            shader: MaterialShader::Lit,
+            cull_mode: Some(wgpu::Face::Back),
+            uniform: MaterialUniform { base_color: [1.0; 4], params: [ambient, 0.0, 0.0, 0.0] },
+            ..Default::default()
+        }
+    }
+
+    pub fn textured(uv_scale: f32) -> Self {
+        Material {
+            shader: MaterialShader::Textured,
+            cull_mode: Some(wgpu::Face::Back),
+            uniform: MaterialUniform { base_color: [1.0; 4], params: [uv_scale, uv_scale, 0.0, 0.0] },
+            ..Default::default()
+        }
+    }
+
+    // blended over what is behind it and leaves the depth buffer alone
+    pub fn transparent(alpha: f32) -> Self {
+        Material {
+            blend: BlendMode::AlphaBlend,
+            depth_write: false,
+            uniform: MaterialUniform { base_color: [1.0, 1.0, 1.0, alpha], params: [0.0; 4] },
+            ..Default::default()
+        }
+    }
+
+    // lit, tinted and see through from both sides, tint.a is the opacity
+    pub fn glass(tint: [f32; 4]) -> Self {
+        Material {
+            shader: MaterialShader::Lit,
+            blend: BlendMode::AlphaBlend,
+            depth_write: false,
+            two_sided: true,
+            uniform: MaterialUniform { base_color: tint, params: [0.35, 0.0, 0.0, 0.0] },
+            ..Default::default()
+        }
+    }
+
+    // every triangle edge via Features::POLYGON_MODE_LINE, without it the library swaps in
+    // the barycentric shader, params.x is the line width that shader draws with
+    pub fn wireframe(color: [f32; 4]) -> Self {
+        Material {
+            polygon_mode: wgpu::PolygonMode::Line,
+            uniform: MaterialUniform { base_color: color, params: [1.5, 0.0, 0.0, 0.0] },
+            ..Default::default()
+        }
+    }
+
+    // for the line list meshes wireframe::feature_edges builds, drawn over the solid faces
+    pub fn edges(color: [f32; 4]) -> Self {
+        Material {
+            topology: wgpu::PrimitiveTopology::LineList,
+            uniform: MaterialUniform { base_color: color, params: [0.0; 4] },
+            ..Default::default()
+        }
+    }
+
+    // inverted hull, the viewport size in params.zw is kept up to date by the renderer
+    pub fn outline(style: OutlineStyle) -> Self {
+        Material {
+            shader: MaterialShader::Outline,
+            cull_mode: Some(wgpu::Face::Front),
+            uniform: MaterialUniform { base_color: style.color, params: [style.thickness_px, 0.0, 1.0, 1.0] },
+            ..Default::default()
+        }
+    }
+
+    // animated effects from procedural.wgsl, changing the effect later is only a uniform update
+    pub fn procedural(effect: FaceEffect) -> Self {
+        Material {
+            shader: MaterialShader::Procedural,
+            cull_mode: Some(wgpu::Face::Back),
+            uniform: effect.uniform(),
+            ..Default::default()
+        }
+    }
+
+    // glTF style metallic-roughness lit by the environment, vertex colors tint the base color
+    pub fn pbr(base_color: [f32; 4], metallic: f32, roughness: f32) -> Self {
+        Material {
+            shader: MaterialShader::Pbr,
+            cull_mode: Some(wgpu::Face::Back),
+            uniform: MaterialUniform { base_color, params: [metallic, roughness, 1.0, 1.0] },
+            ..Default::default()
+        }
+    }
+
+    // mirror-like silver that ignores the face colors
+    pub fn polished_metal() -> Self {
+        let mut material = Material::pbr([0.97, 0.96, 0.94, 1.0], 1.0, 0.08);
+        material.uniform.params[3] = 0.0;
+        material
+    }
+
+    // open meshes or ones authored clockwise override what the constructor picked, see --cull and --front-face
+    pub fn with_culling(self, cull_mode: Option<wgpu::Face>, front_face: wgpu::FrontFace) -> Self {
+        Material { cull_mode, front_face, ..self }
+    }
+
+    pub fn pipeline_key(&self) -> PipelineKey {
+        PipelineKey {
+            shader: self.shader,
+            blend: self.blend,
+            topology: self.topology,
+            cull_mode: self.cull_mode,
+            front_face: self.front_face,
+            polygon_mode: self.polygon_mode,
+            depth_write: self.depth_write,
+            depth_compare: self.depth_compare,
+        }
+    }
+}
+
+// everything about a material that ends up baked into the render pipeline
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: MaterialShader,
+    pub blend: BlendMode,
+    pub topology: wgpu::PrimitiveTopology,
+    pub cull_mode: Option<wgpu::Face>,
+    pub front_face: wgpu::FrontFace,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub usize);
+
+struct MaterialEntry {
+    material: Material,
+    // one pipeline per pass the material can be drawn in, the first is DrawPass::Single
+    render_keys: Vec<(DrawPass, RenderPipelineKey)>,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+// object and material groups, plus the scene group for shaders that read one
+struct MaterialLayouts {
+    plain: PipelineLayout,
+    environment: PipelineLayout,
+    shadows: PipelineLayout,
+}
+
+impl MaterialLayouts {
+    // a field of its own so callers can use it while the pipeline cache is borrowed mutably
+    fn for_shader(&self, shader: MaterialShader) -> &PipelineLayout {
+        match shader.scene_group() {
+            None => &self.plain,
+            Some(SceneGroup::Environment) => &self.environment,
+            Some(SceneGroup::Shadows) => &self.shadows,
+        }
+    }
+}
+
+pub struct MaterialLibrary {
+    pub object_bgl: BindGroupLayout,
+    pub material_bgl: BindGroupLayout,
+    layouts: MaterialLayouts,
+    pub environment: Ibl,
+    pub shadows: ShadowMap,
+    color_format: TextureFormat,
+    sample_count: u32,
+    shaders: HashMap<MaterialShader, ShaderModule>,
+    pipelines: PipelineCache,
+    materials: Vec<MaterialEntry>,
+    white_texture: wgpu::TextureView,
+    checker_texture: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+}
+
+impl MaterialLibrary {
+    pub fn new(driver: &Driver, color_format: TextureFormat) -> Self {
+        let object_bgl = Cube::create_bgl(driver, &UNIFORM_BGL_ENTRIES, Some("object_bgl"));
+        let material_bgl = Cube::create_bgl(driver, &MATERIAL_BGL_ENTRIES, Some("material_bgl"));
+        let environment = Ibl::new(driver);
+        let shadows = ShadowMap::new(driver, &object_bgl, ShadowSettings::default());
+        let layouts = MaterialLayouts {
+            plain: Cube::create_buffer_render_pipeline_layout(driver, &[&object_bgl, &material_bgl]),
+            environment: Cube::create_buffer_render_pipeline_layout(driver, &[&object_bgl, &material_bgl, &environment.bgl]),
+            shadows: Cube::create_buffer_render_pipeline_layout(driver, &[&object_bgl, &material_bgl, &shadows.bgl]),
+        };
+        let sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("material sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        MaterialLibrary {
+            object_bgl,
+            material_bgl,
+            layouts,
+            environment,
+            shadows,
+            color_format,
+            sample_count: 1,
+            shaders: HashMap::new(),
+            pipelines: PipelineCache::new(driver),
+            materials: Vec::new(),
+            white_texture: create_rgba_texture(driver, "white texture", 1, &[255; 4]),
+            checker_texture: create_rgba_texture(driver, "checker texture", 8, &checker_pixels(8)),
+            sampler,
+        }
+    }
+
+    // every call is a new entry with its own buffers, runtime switches go through replace instead
+    pub fn add(&mut self, driver: &Driver, material: Material) -> MaterialId {
+        let uniform_buffer = driver.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("material uniform buffer"),
+            size: MATERIAL_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let entry = self.create_entry(driver, material, uniform_buffer);
+        self.materials.push(entry);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    // puts another material behind an existing id, its uniform buffer is rewritten in place
+    pub fn replace(&mut self, driver: &Driver, id: MaterialId, material: Material) {
+        let current = &self.materials[id.0];
+        if current.material.pipeline_key() == material.pipeline_key() {
+            self.set_uniform(driver, id, material.uniform);
+            self.materials[id.0].material = material;
+            return;
+        }
+        let uniform_buffer = current.uniform_buffer.clone();
+        self.materials[id.0] = self.create_entry(driver, material, uniform_buffer);
+    }
+
+    fn create_entry(&mut self, driver: &Driver, material: Material, uniform_buffer: Buffer) -> MaterialEntry {
+        let render_keys = self.render_keys(driver, &material);
+        for (_, key) in &render_keys {
+            self.ensure_pipeline(driver, key);
+        }
+        driver.queue.write_buffer(&uniform_buffer, 0, bytemuck::bytes_of(&material.uniform));
+        let texture = match material.shader {
+            MaterialShader::Textured => &self.checker_texture,
+            _ => &self.white_texture,
+        };
+        let bind_group = Cube::create_bg(driver, &self.material_bgl, &[
+            BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(texture) },
+            BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+        ], Some("material bg"));
+        MaterialEntry { material, render_keys, uniform_buffer, bind_group }
+    }
+
+    pub fn material(&self, id: MaterialId) -> &Material {
+        &self.materials[id.0].material
+    }
+
+    pub fn has_pass(&self, id: MaterialId, pass: DrawPass) -> bool {
+        self.materials[id.0].render_keys.iter().any(|(p, _)| *p == pass)
+    }
+
+    // uniform changes are a buffer write, anything baked into the pipeline needs a new material
+    pub fn set_uniform(&mut self, driver: &Driver, id: MaterialId, uniform: MaterialUniform) {
+        let entry = &mut self.materials[id.0];
+        entry.material.uniform = uniform;
+        driver.queue.write_buffer(&entry.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    // moving to another target format or sample count only compiles combinations not seen before
+    pub fn set_target(&mut self, driver: &Driver, color_format: TextureFormat, sample_count: u32) {
+        if color_format == self.color_format && sample_count == self.sample_count {
+            return;
+        }
+        self.color_format = color_format;
+        self.sample_count = sample_count;
+        for i in 0..self.materials.len() {
+            let render_keys = self.render_keys(driver, &self.materials[i].material);
+            for (_, key) in &render_keys {
+                self.ensure_pipeline(driver, key);
+            }
+            self.materials[i].render_keys = render_keys;
+        }
+        self.pipelines.persist();
+    }
+
+    // saves newly compiled pipelines to the driver cache, cheap when nothing changed
+    pub fn persist_pipelines(&mut self) {
+        self.pipelines.persist();
+    }
+
+    // the single pass key plus the extra passes a blended material can take part in
+    fn render_keys(&self, driver: &Driver, material: &Material) -> Vec<(DrawPass, RenderPipelineKey)> {
+        let single = self.render_key(driver, material);
+        let mut keys = Vec::new();
+        if single.material.blend.is_transparent() && material.two_sided {
+            for (pass, cull_mode) in [(DrawPass::BackFaces, wgpu::Face::Front), (DrawPass::FrontFaces, wgpu::Face::Back)] {
+                let mut key = single.clone();
+                key.material.cull_mode = Some(cull_mode);
+                keys.push((pass, key));
+            }
+        }
+        if single.material.blend == BlendMode::AlphaBlend && single.material.shader.supports_oit() {
+            let mut key = single.clone();
+            key.material.cull_mode = None;
+            key.material.depth_write = false;
+            key.pass = PassKind::OitAccumulate;
+            keys.push((DrawPass::OitAccumulate, key));
+        }
+        keys.insert(0, (DrawPass::Single, single));
+        keys
+    }
+
+    fn render_key(&self, driver: &Driver, material: &Material) -> RenderPipelineKey {
+        let mut key = material.pipeline_key();
+        if key.polygon_mode == wgpu::PolygonMode::Line
+            && !driver.device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            log::info!("no POLYGON_MODE_LINE, using the barycentric wireframe shader");
+            key.polygon_mode = wgpu::PolygonMode::Fill;
+            key.shader = MaterialShader::Barycentric;
+            key.blend = BlendMode::AlphaBlend;
+        }
+        RenderPipelineKey {
+            material: key,
+            vertex_layout: (&Vertex::desc()).into(),
+            color_format: self.color_format,
+            depth_format: Some(DEPTH_FORMAT),
+            sample_count: self.sample_count,
+            pass: PassKind::Forward,
+        }
+    }
+
+    fn shader(&mut self, driver: &Driver, shader: MaterialShader) -> &ShaderModule {
+        self.shaders.entry(shader).or_insert_with(|| {
+            Cube::create_shader_from_source(driver, Some(shader.file_name()), shader.source())
+        })
+    }
+
+    fn ensure_pipeline(&mut self, driver: &Driver, key: &RenderPipelineKey) {
+        if self.pipelines.get(key).is_some() {
+            return;
+        }
+        self.shader(driver, key.material.shader);
+        let module = &self.shaders[&key.material.shader];
+        let layout = self.layouts.for_shader(key.material.shader);
+        self.pipelines.get_or_create(key.clone(), |cache| {
+            Cube::create_buffer_render_pipeline(driver, Some(layout), module, key, cache)
+        });
+    }
+
+    // binds the pipeline, material group and scene group for an object, group 0 is up to the caller
+    // passes the material was not built for fall back to its single pass pipeline
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass<'_>, id: MaterialId, pass: DrawPass) {
+        let entry = &self.materials[id.0];
+        let key = entry.render_keys.iter().find(|(p, _)| *p == pass).unwrap_or(&entry.render_keys[0]);
+        if let Some(pipeline) = self.pipelines.get(&key.1) {
+            render_pass.set_pipeline(pipeline);
+        }
+        render_pass.set_bind_group(1, &entry.bind_group, &[]);
+        match key.1.material.shader.scene_group() {
+            None => {}
+            Some(SceneGroup::Environment) => render_pass.set_bind_group(2, self.environment.bind_group(), &[]),
+            Some(SceneGroup::Shadows) => render_pass.set_bind_group(2, self.shadows.bind_group(), &[]),
+        }
+    }
+
+    // rebuilds every cached pipeline that uses the shader, keeping the old ones if wgpu rejects the new source
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_shader(&mut self, driver: &Driver, shader: MaterialShader, source: &str) -> Result<(), wgpu::Error> {
+        driver.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = Cube::create_shader_from_source(driver, Some(shader.file_name()), source);
+        let keys: Vec<RenderPipelineKey> = self.pipelines.keys().filter(|k| k.material.shader == shader).cloned().collect();
+        let rebuilt: Vec<(RenderPipelineKey, wgpu::RenderPipeline)> = keys.into_iter().map(|key| {
+            let pipeline = Cube::create_buffer_render_pipeline(driver, Some(self.layouts.for_shader(shader)), &module, &key, self.pipelines.driver_cache());
+            (key, pipeline)
+        }).collect();
+        if let Some(err) = pollster::block_on(driver.device.pop_error_scope()) {
+            return Err(err);
+        }
+        self.shaders.insert(shader, module);
+        for (key, pipeline) in rebuilt {
+            self.pipelines.insert(key, pipeline);
+        }
+        Ok(())
+    }
+}
+
+fn create_rgba_texture(driver: &Driver, label: &str, size: u32, pixels: &[u8]) -> wgpu::TextureView {
+    let extent = wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 };
+    let texture = driver.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    driver.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        pixels,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size),
+            rows_per_image: Some(size),
+        },
+        extent,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// two tone checker board so textured faces show their uv layout
+fn checker_pixels(size: u32) -> Vec<u8> {
+    (0..size * size).flat_map(|i| {
+        let (x, y) = (i % size, i / size);
+        if (x + y) % 2 == 0 { [230, 230, 230, 255] } else { [40, 40, 60, 255] }
+    }).collect()
+}
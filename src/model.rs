@@ -0,0 +1,167 @@
+// Wavefront OBJ loading for swapping the main cube for another mesh.
+// Only geometry is read: positions (with the common "v x y z r g b" color extension),
+// uvs, normals and polygon faces, which are fanned into triangles. Materials, groups
+// and smoothing are ignored. The result is centered and scaled to the cube's -1..1 box.
+use std::fmt;
+use std::path::Path;
+
+use crate::wgpu_helpers::Vertex;
+
+const WHITE: [f32; 4] = [1.0; 4];
+
+#[derive(Debug)]
+pub enum ModelError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+    // parsed fine but no faces
+    Empty,
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Io(e) => write!(f, "{e}"),
+            ModelError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ModelError::Empty => write!(f, "the model has no faces"),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<std::io::Error> for ModelError {
+    fn from(e: std::io::Error) -> Self {
+        ModelError::Io(e)
+    }
+}
+
+pub fn load_obj(path: &Path) -> Result<Vec<Vertex>, ModelError> {
+    parse_obj(&std::fs::read_to_string(path)?)
+}
+
+// unindexed triangle list, faces without normals get flat ones
+pub fn parse_obj(source: &str) -> Result<Vec<Vertex>, ModelError> {
+    let mut positions: Vec<([f32; 3], [f32; 4])> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut vertices = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| ModelError::Parse { line: index + 1, message };
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else { continue };
+        let numbers = |words: std::str::SplitWhitespace| -> Result<Vec<f32>, ModelError> {
+            words.map(|w| w.parse::<f32>().map_err(|e| error(format!("{w:?}: {e}")))).collect()
+        };
+        match keyword {
+            "v" => match numbers(words)?[..] {
+                [x, y, z] | [x, y, z, _] => positions.push(([x, y, z], WHITE)),
+                [x, y, z, r, g, b] => positions.push(([x, y, z], [r, g, b, 1.0])),
+                _ => return Err(error("v needs 3 coordinates".into())),
+            },
+            "vt" => match numbers(words)?[..] {
+                // obj puts v = 0 at the bottom, textures here have it at the top
+                [u] => uvs.push([u, 1.0]),
+                [u, v, ..] => uvs.push([u, 1.0 - v]),
+                _ => return Err(error("vt needs a coordinate".into())),
+            },
+            "vn" => match numbers(words)?[..] {
+                [x, y, z] => normals.push([x, y, z]),
+                _ => return Err(error("vn needs 3 coordinates".into())),
+            },
+            "f" => {
+                let corners = words.map(|corner| {
+                    // v, v/vt, v//vn or v/vt/vn, 1 based or negative from the end
+                    let mut parts = corner.split('/');
+                    let mut reference = |count: usize| -> Result<Option<usize>, ModelError> {
+                        match parts.next().filter(|p| !p.is_empty()) {
+                            None => Ok(None),
+                            Some(p) => {
+                                let i: i64 = p.parse().map_err(|e| error(format!("{corner:?}: {e}")))?;
+                                let resolved = if i < 0 { count as i64 + i } else { i - 1 };
+                                match (0..count as i64).contains(&resolved) {
+                                    true => Ok(Some(resolved as usize)),
+                                    false => Err(error(format!("{corner:?} points past the {count} defined so far"))),
+                                }
+                            }
+                        }
+                    };
+                    let position = reference(positions.len())?.ok_or_else(|| error(format!("{corner:?} has no position")))?;
+                    Ok((position, reference(uvs.len())?, reference(normals.len())?))
+                }).collect::<Result<Vec<_>, ModelError>>()?;
+                if corners.len() < 3 {
+                    return Err(error("a face needs at least 3 corners".into()));
+                }
+                for i in 1..corners.len() - 1 {
+                    let triangle = [corners[0], corners[i], corners[i + 1]];
+                    let flat = flat_normal(triangle.map(|(p, _, _)| positions[p].0));
+                    for (p, uv, n) in triangle {
+                        let (position, color) = positions[p];
+                        let normal = n.map_or(flat, |n| normals[n]);
+                        vertices.push(Vertex::new(position, color, normal, uv.map_or([0.0, 0.0], |uv| uvs[uv])));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if vertices.is_empty() {
+        return Err(ModelError::Empty);
+    }
+    fit_unit_box(&mut vertices);
+    Ok(vertices)
+}
+
+fn flat_normal([a, b, c]: [[f32; 3]; 3]) -> [f32; 3] {
+    let (u, v) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+    let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt().max(f32::EPSILON);
+    [n[0] / len, n[1] / len, n[2] / len]
+}
+
+// centered on the origin with the longest side running -1..1, like the cube it replaces
+fn fit_unit_box(vertices: &mut [Vertex]) {
+    let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+    for v in vertices.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(v.position[axis]);
+            max[axis] = max[axis].max(v.position[axis]);
+        }
+    }
+    let center: [f32; 3] = std::array::from_fn(|axis| (min[axis] + max[axis]) / 2.0);
+    let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0, f32::max).max(f32::EPSILON);
+    for v in vertices.iter_mut() {
+        for (p, c) in v.position.iter_mut().zip(center) {
+            *p = (*p - c) * 2.0 / extent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quads_are_fanned_and_fitted() {
+        let source = "# a 4x2 quad off to the side\n\
+            v 10 0 0\nv 14 0 0\nv 14 2 0 1 0 0\nv 10 2 0\n\
+            vt 0 0\nvt 1 1\n\
+            f 1/1 2 -2/2 -1\n";
+        let vertices = parse_obj(source).unwrap();
+        assert_eq!(vertices.len(), 6);
+        let xs: Vec<f32> = vertices.iter().map(|v| v.position[0]).collect();
+        assert_eq!(xs.iter().cloned().fold(f32::MAX, f32::min), -1.0);
+        assert_eq!(xs.iter().cloned().fold(f32::MIN, f32::max), 1.0);
+        // counter clockwise seen from +z
+        assert_eq!(vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertices[0].uv, [0.0, 1.0]);
+        assert_eq!(vertices[2].color, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn bad_files_say_where() {
+        assert!(matches!(parse_obj("v 0 0 0\nf 1 2 3\n"), Err(ModelError::Parse { line: 2, .. })));
+        assert!(matches!(parse_obj("v 0 0 zero\n"), Err(ModelError::Parse { line: 1, .. })));
+        assert!(matches!(parse_obj("v 0 0 0\n"), Err(ModelError::Empty)));
+    }
+}
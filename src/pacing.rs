@@ -0,0 +1,221 @@
+// When the front ends draw, shared by the native and web event loops.
+// Times are milliseconds from whatever clock the front end has (Instant or
+// performance.now()), the pacer only ever compares them with each other.
+use std::time::Duration;
+
+use wgpu::PresentMode;
+use winit::event_loop::ControlFlow;
+
+pub const PRESENT_MODE_NAMES: [&str; 6] = ["fifo", "fifo-relaxed", "mailbox", "immediate", "auto-vsync", "auto-no-vsync"];
+
+pub fn present_mode_from_name(name: &str) -> Option<PresentMode> {
+    match name {
+        "fifo" => Some(PresentMode::Fifo),
+        "fifo-relaxed" => Some(PresentMode::FifoRelaxed),
+        "mailbox" => Some(PresentMode::Mailbox),
+        "immediate" => Some(PresentMode::Immediate),
+        "auto-vsync" => Some(PresentMode::AutoVsync),
+        "auto-no-vsync" => Some(PresentMode::AutoNoVsync),
+        _ => None,
+    }
+}
+
+// Animation time that only advances while running, so pausing and resuming
+// picks up where it left off instead of jumping by the time spent paused.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AnimationClock {
+    elapsed_ms: f64,
+    last_ms: Option<f64>,
+    paused: bool,
+}
+
+impl AnimationClock {
+    pub fn tick(&mut self, now_ms: f64) -> f64 {
+        if !self.paused {
+            if let Some(last) = self.last_ms {
+                self.elapsed_ms += (now_ms - last).max(0.0);
+            }
+            self.last_ms = Some(now_ms);
+        }
+        self.elapsed_ms
+    }
+
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            self.paused = paused;
+            // the first tick after resuming only sets the reference point
+            self.last_ms = None;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NextFrame {
+    Now,
+    // the frame cap holds the next frame back until this time
+    At(f64),
+    // idle and nothing changed, only an event brings the next frame
+    WhenRequested,
+    // nothing can be seen, wait until something is shown again
+    Hidden,
+}
+
+// Decides whether the next frame is due: as fast as the present mode allows, capped
+// to max_fps, or in idle mode only after request_frame says something changed.
+// Idle mode also freezes the animation so a static card stays static, and while hidden
+// nothing is drawn and the animation waits where it was.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramePacer {
+    max_fps: Option<f32>,
+    idle: bool,
+    visible: bool,
+    clock: AnimationClock,
+    last_frame_ms: Option<f64>,
+    requested: bool,
+}
+
+impl FramePacer {
+    pub fn new(max_fps: Option<f32>, idle: bool) -> Self {
+        let mut pacer = FramePacer {
+            max_fps: None,
+            idle: false,
+            visible: true,
+            clock: AnimationClock::default(),
+            last_frame_ms: None,
+            // the first frame is always drawn
+            requested: true,
+        };
+        pacer.set_max_fps(max_fps);
+        pacer.set_idle(idle);
+        pacer
+    }
+
+    pub fn max_fps(&self) -> Option<f32> {
+        self.max_fps
+    }
+
+    // None or anything not above 0 removes the cap
+    pub fn set_max_fps(&mut self, max_fps: Option<f32>) {
+        self.max_fps = max_fps.filter(|fps| *fps > 0.0);
+    }
+
+    pub fn idle(&self) -> bool {
+        self.idle
+    }
+
+    pub fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+        self.clock.set_paused(self.idle || !self.visible);
+        self.requested = true;
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    // showing again draws straight away, whatever was requested while hidden included
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.clock.set_paused(self.idle || !self.visible);
+            self.requested |= visible;
+        }
+    }
+
+    // something on screen changed, draw it even when idle
+    pub fn request_frame(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn next_frame(&self, now_ms: f64) -> NextFrame {
+        if !self.visible {
+            return NextFrame::Hidden;
+        }
+        if self.idle && !self.requested {
+            return NextFrame::WhenRequested;
+        }
+        match (self.max_fps, self.last_frame_ms) {
+            (Some(fps), Some(last)) if now_ms < last + 1000.0 / fps as f64 => NextFrame::At(last + 1000.0 / fps as f64),
+            _ => NextFrame::Now,
+        }
+    }
+
+    // the animation time to draw with, call once per drawn frame
+    pub fn frame_started(&mut self, now_ms: f64) -> f64 {
+        self.last_frame_ms = Some(now_ms);
+        self.requested = false;
+        self.clock.tick(now_ms)
+    }
+
+    // how the event loop should wait after AboutToWait, true when a redraw is due now
+    pub fn control_flow(&self, now_ms: f64) -> (ControlFlow, bool) {
+        match self.next_frame(now_ms) {
+            NextFrame::Now => (ControlFlow::Poll, true),
+            NextFrame::At(due) => (ControlFlow::wait_duration(Duration::from_secs_f64((due - now_ms) / 1000.0)), false),
+            NextFrame::WhenRequested | NextFrame::Hidden => (ControlFlow::Wait, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_skips_paused_time() {
+        let mut clock = AnimationClock::default();
+        assert_eq!(clock.tick(1000.0), 0.0);
+        assert_eq!(clock.tick(1100.0), 100.0);
+        clock.set_paused(true);
+        assert_eq!(clock.tick(5000.0), 100.0);
+        clock.set_paused(false);
+        assert_eq!(clock.tick(9000.0), 100.0);
+        assert_eq!(clock.tick(9016.0), 116.0);
+    }
+
+    #[test]
+    fn cap_and_idle_hold_frames_back() {
+        let mut pacer = FramePacer::new(Some(20.0), false);
+        assert_eq!(pacer.next_frame(0.0), NextFrame::Now);
+        pacer.frame_started(0.0);
+        assert_eq!(pacer.next_frame(10.0), NextFrame::At(50.0));
+        assert_eq!(pacer.next_frame(50.0), NextFrame::Now);
+        assert_eq!(pacer.frame_started(50.0), 50.0);
+
+        pacer.set_idle(true);
+        assert_eq!(pacer.next_frame(100.0), NextFrame::Now);
+        pacer.frame_started(100.0);
+        assert_eq!(pacer.next_frame(500.0), NextFrame::WhenRequested);
+        pacer.request_frame();
+        assert_eq!(pacer.next_frame(500.0), NextFrame::Now);
+        // frozen while idle
+        assert_eq!(pacer.frame_started(500.0), 50.0);
+    }
+
+    #[test]
+    fn every_present_mode_name_parses() {
+        for name in PRESENT_MODE_NAMES {
+            assert!(present_mode_from_name(name).is_some(), "{name}");
+        }
+        assert_eq!(present_mode_from_name("mailbox"), Some(PresentMode::Mailbox));
+        assert_eq!(present_mode_from_name("sometimes"), None);
+    }
+
+    #[test]
+    fn hidden_pauses_without_a_jump() {
+        let mut pacer = FramePacer::new(None, false);
+        pacer.frame_started(0.0);
+        assert_eq!(pacer.frame_started(16.0), 16.0);
+        pacer.set_visible(false);
+        pacer.request_frame();
+        assert_eq!(pacer.next_frame(5000.0), NextFrame::Hidden);
+        pacer.set_visible(true);
+        assert_eq!(pacer.next_frame(60_000.0), NextFrame::Now);
+        assert_eq!(pacer.frame_started(60_000.0), 16.0);
+        assert_eq!(pacer.frame_started(60_016.0), 32.0);
+    }
+}
@@ -0,0 +1,98 @@
+// Which face of the main cube is under the pointer, worked out on the cpu.
+// The cursor is unprojected through the inverse of project * view * model, which gives a ray
+// in the cube's own space, and that ray is tested against the cube's twelve triangles.
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+use crate::theme::Face;
+use crate::wgpu_helpers::Vertex;
+
+// how far a hovered face is lifted towards white
+pub const HIGHLIGHT: f32 = 0.35;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    // not normalized, distances along the ray are in units of it
+    pub direction: Vector3<f32>,
+}
+
+// cursor in physical pixels from the top left of a width x height surface, None when the
+// matrix can't be inverted
+pub fn ray_from_cursor(cursor: [f32; 2], size: [f32; 2], mvp: Matrix4<f32>) -> Option<Ray> {
+    let inverse = mvp.invert()?;
+    let x = 2.0 * cursor[0] / size[0] - 1.0;
+    let y = 1.0 - 2.0 * cursor[1] / size[1];
+    // wgpu clip depth runs from 0 at the near plane to 1 at the far one
+    let unproject = |depth: f32| {
+        let p = inverse * Vector4::new(x, y, depth, 1.0);
+        Point3::new(p.x / p.w, p.y / p.w, p.z / p.w)
+    };
+    let (near, far) = (unproject(0.0), unproject(1.0));
+    Some(Ray { origin: near, direction: far - near })
+}
+
+// Möller-Trumbore, the distance along the ray to a hit from either side
+pub fn intersect_triangle(ray: &Ray, [a, b, c]: [Point3<f32>; 3]) -> Option<f32> {
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let to_origin = ray.origin - a;
+    let u = to_origin.dot(p) / det;
+    let q = to_origin.cross(ab);
+    let v = ray.direction.dot(q) / det;
+    let t = ac.dot(q) / det;
+    (u >= 0.0 && v >= 0.0 && u + v <= 1.0 && t >= 0.0).then_some(t)
+}
+
+// vertices laid out like Cube::create_cube_vertices, two triangles per face in Face order
+pub fn pick_face(ray: &Ray, vertices: &[Vertex]) -> Option<Face> {
+    let point = |v: &Vertex| Point3::new(v.position[0], v.position[1], v.position[2]);
+    vertices.chunks_exact(3)
+        .enumerate()
+        .filter_map(|(i, tri)| intersect_triangle(ray, [point(&tri[0]), point(&tri[1]), point(&tri[2])]).map(|t| (i / 2, t)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .and_then(|(face, _)| Face::ALL.get(face).copied())
+}
+
+pub fn highlight(color: [f32; 4]) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    let lift = |c: f32| c + (1.0 - c) * HIGHLIGHT;
+    [lift(r), lift(g), lift(b), a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::{Projection, ProjectionSettings};
+    use crate::wgpu_helpers::Cube;
+
+    #[test]
+    fn nearest_face_wins() {
+        let vertices = Cube::create_cube_vertices();
+        let front = vertices[0].normal;
+        // from outside the front face straight through the cube, the back face is hit too but further away
+        let origin = Point3::new(0.2 + front[0] * 5.0, 0.3 + front[1] * 5.0, 0.1 + front[2] * 5.0);
+        let ray = Ray { origin, direction: -Vector3::from(front) };
+        assert_eq!(pick_face(&ray, &vertices), Some(Face::Front));
+        let miss = Ray { origin, direction: Vector3::from(front) };
+        assert_eq!(pick_face(&miss, &vertices), None);
+        assert_eq!(highlight([0.0, 1.0, 0.5, 0.5]), [HIGHLIGHT, 1.0, 0.5 + 0.5 * HIGHLIGHT, 0.5]);
+    }
+
+    #[test]
+    fn cursor_rays_go_through_the_pixel() {
+        let view = Matrix4::look_at_rh(Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        for projection in [Projection::Perspective, Projection::Orthographic] {
+            let mvp = projection.matrix(&ProjectionSettings::default(), 2.0) * view;
+            let ray = ray_from_cursor([300.0, 50.0], [400.0, 200.0], mvp).unwrap();
+            // halfway right and halfway up on screen
+            let hit = ray.origin + ray.direction * 0.5;
+            let clip = mvp * hit.to_homogeneous();
+            assert!((clip.x / clip.w - 0.5).abs() < 1e-4 && (clip.y / clip.w - 0.5).abs() < 1e-4);
+            assert!(ray.direction.normalize().z < -0.5);
+        }
+    }
+}
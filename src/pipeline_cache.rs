@@ -0,0 +1,196 @@
+// Render pipelines keyed by everything that goes into their descriptor, so asking
+// for the same combination twice hands back the existing pipeline instead of
+// compiling a new one. On native builds the driver level wgpu::PipelineCache is
+// also loaded from and written back to disk (--pipeline-cache, the temp directory by
+// default) so warm starts skip shader compilation.
+use std::collections::HashMap;
+
+use wgpu::{RenderPipeline, TextureFormat, VertexAttribute, VertexBufferLayout, VertexStepMode};
+
+use crate::material::PipelineKey;
+use crate::wasm_driver::Driver;
+
+// owned copy of a VertexBufferLayout so it can sit in a hash key
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayoutKey {
+    pub array_stride: u64,
+    pub step_mode: VertexStepMode,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl From<&VertexBufferLayout<'_>> for VertexLayoutKey {
+    fn from(layout: &VertexBufferLayout<'_>) -> Self {
+        VertexLayoutKey {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes: layout.attributes.to_vec(),
+        }
+    }
+}
+
+// which render pass a pipeline draws into, the OIT accumulation pass writes two
+// offscreen targets through the shader's fs_oit entry point instead of fs_main
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub enum PassKind {
+    #[default]
+    Forward,
+    OitAccumulate,
+}
+
+// the material's own state plus what the render target imposes
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderPipelineKey {
+    pub material: PipelineKey,
+    pub vertex_layout: VertexLayoutKey,
+    pub color_format: TextureFormat,
+    pub depth_format: Option<TextureFormat>,
+    pub sample_count: u32,
+    pub pass: PassKind,
+}
+
+pub struct PipelineCache {
+    pipelines: HashMap<RenderPipelineKey, RenderPipeline>,
+    driver_cache: Option<wgpu::PipelineCache>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_file: Option<std::path::PathBuf>,
+    // pipelines compiled since the driver cache was last written
+    dirty: bool,
+}
+
+impl PipelineCache {
+    pub fn new(driver: &Driver) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (driver_cache, cache_file) = Self::load_driver_cache(driver).unzip();
+            PipelineCache {
+                pipelines: HashMap::new(),
+                driver_cache,
+                cache_file,
+                dirty: false,
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = driver;
+            PipelineCache {
+                pipelines: HashMap::new(),
+                driver_cache: None,
+                dirty: false,
+            }
+        }
+    }
+
+    // only some backends (vulkan) support pipeline caches, everything else just skips this
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_driver_cache(driver: &Driver) -> Option<(wgpu::PipelineCache, std::path::PathBuf)> {
+        if !driver.device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+        let file_name = wgpu::util::pipeline_cache_key(&driver.adapter.get_info())?;
+        let dir = driver.pipeline_cache_dir.clone().unwrap_or_else(|| std::env::temp_dir().join("cube_take_two"));
+        let cache_file = dir.join(file_name);
+        let data = std::fs::read(&cache_file).ok();
+        log::info!("pipeline cache {} ({})", cache_file.display(), if data.is_some() { "warm" } else { "cold" });
+        // SAFETY: the data was written by get_data() of an earlier run, and fallback
+        // makes wgpu discard it if it came from another driver or wgpu version
+        let cache = unsafe {
+            driver.device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("cube pipeline cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        Some((cache, cache_file))
+    }
+
+    pub fn get(&self, key: &RenderPipelineKey) -> Option<&RenderPipeline> {
+        self.pipelines.get(key)
+    }
+
+    // build is only called on a miss and gets the driver cache to hand to wgpu
+    pub fn get_or_create(&mut self, key: RenderPipelineKey, build: impl FnOnce(Option<&wgpu::PipelineCache>) -> RenderPipeline) -> &RenderPipeline {
+        let driver_cache = self.driver_cache.as_ref();
+        let dirty = &mut self.dirty;
+        self.pipelines.entry(key).or_insert_with(|| {
+            *dirty = true;
+            build(driver_cache)
+        })
+    }
+
+    pub fn driver_cache(&self) -> Option<&wgpu::PipelineCache> {
+        self.driver_cache.as_ref()
+    }
+
+    pub fn insert(&mut self, key: RenderPipelineKey, pipeline: RenderPipeline) {
+        self.dirty = true;
+        self.pipelines.insert(key, pipeline);
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &RenderPipelineKey> {
+        self.pipelines.keys()
+    }
+
+    // writes the driver cache out if anything was compiled since the last save
+    pub fn persist(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Some(cache), Some(path)) = (&self.driver_cache, &self.cache_file)
+            && let Some(data) = cache.get_data() {
+            // write to a temp file first so a crash mid write can't leave a truncated cache behind
+            let tmp = path.with_extension("tmp");
+            let written = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&tmp, &data))
+                .and_then(|_| std::fs::rename(&tmp, path));
+            if let Err(e) = written {
+                log::warn!("could not save pipeline cache to {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        self.persist();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::wgpu_helpers::Vertex;
+
+    fn key(material: &Material, color_format: TextureFormat) -> RenderPipelineKey {
+        RenderPipelineKey {
+            material: material.pipeline_key(),
+            vertex_layout: (&Vertex::desc()).into(),
+            color_format,
+            depth_format: Some(TextureFormat::Depth24Plus),
+            sample_count: 1,
+            pass: PassKind::Forward,
+        }
+    }
+
+    #[test]
+    fn same_state_gives_the_same_key() {
+        let mut keys = std::collections::HashSet::new();
+        keys.insert(key(&Material::unlit(), TextureFormat::Bgra8UnormSrgb));
+        // uniform values are not part of the pipeline
+        keys.insert(key(&Material::wireframe([1.0, 0.0, 0.0, 1.0]), TextureFormat::Bgra8UnormSrgb));
+        keys.insert(key(&Material::wireframe([0.0, 1.0, 0.0, 1.0]), TextureFormat::Bgra8UnormSrgb));
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn target_state_changes_the_key() {
+        let material = Material::unlit();
+        let base = key(&material, TextureFormat::Bgra8UnormSrgb);
+        assert_ne!(base, key(&material, TextureFormat::Rgba8UnormSrgb));
+        assert_ne!(base, RenderPipelineKey { sample_count: 4, ..base.clone() });
+        assert_ne!(base, RenderPipelineKey { depth_format: None, ..base.clone() });
+        assert_ne!(base, RenderPipelineKey { pass: PassKind::OitAccumulate, ..base.clone() });
+    }
+}
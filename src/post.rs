@@ -0,0 +1,319 @@
+// Depth driven screen space effects: ambient occlusion, depth of field and fog.
+// While any of them is on the scene is drawn into an offscreen color target instead
+// of the surface, and post.wgsl reads that plus the depth buffer to produce the frame.
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix4, SquareMatrix};
+use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer, RenderPipeline, TextureFormat, TextureView};
+
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::Cube;
+
+const AO_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ssao {
+    // how far around a point occluders are searched for, in world units
+    pub radius: f32,
+    // 0 leaves the scene alone, 1 is full strength
+    pub intensity: f32,
+    // depth difference ignored to keep flat surfaces from occluding themselves
+    pub bias: f32,
+}
+
+impl Default for Ssao {
+    fn default() -> Self {
+        Ssao { radius: 0.5, intensity: 1.0, bias: 0.025 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DepthOfField {
+    // distance from the camera that stays sharp
+    pub focus_distance: f32,
+    // how far from the focus distance the blur reaches its maximum
+    pub focus_range: f32,
+    pub max_blur_px: f32,
+}
+
+impl Default for DepthOfField {
+    // the orbiting camera sits about 3.6 units from the cube
+    fn default() -> Self {
+        DepthOfField { focus_distance: 3.6, focus_range: 3.0, max_blur_px: 8.0 }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fog {
+    // alpha scales how opaque the fog gets
+    pub color: [f32; 4],
+    // distance at which the fog starts
+    pub start: f32,
+    pub density: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog { color: [0.05, 0.062, 0.08, 1.0], start: 3.0, density: 0.25 }
+    }
+}
+
+// None turns an effect off
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PostSettings {
+    pub ssao: Option<Ssao>,
+    pub dof: Option<DepthOfField>,
+    pub fog: Option<Fog>,
+}
+
+impl PostSettings {
+    pub fn is_active(&self) -> bool {
+        self.ssao.is_some() || self.dof.is_some() || self.fog.is_some()
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PostUniform {
+    projection: [[f32; 4]; 4],
+    inv_projection: [[f32; 4]; 4],
+    ssao: [f32; 4],
+    dof: [f32; 4],
+    fog_color: [f32; 4],
+    fog: [f32; 4],
+    viewport: [f32; 4],
+}
+
+impl PostUniform {
+    fn new(settings: &PostSettings, projection: Matrix4<f32>, width: u32, height: u32) -> Self {
+        let ssao = settings.ssao.map_or([0.0; 4], |s| [1.0, s.radius, s.intensity, s.bias]);
+        let dof = settings.dof.map_or([0.0; 4], |d| [1.0, d.focus_distance, d.focus_range, d.max_blur_px]);
+        let (fog_color, fog) = settings.fog.map_or(([0.0; 4], [0.0; 4]), |f| (f.color, [1.0, f.start, f.density, 0.0]));
+        PostUniform {
+            projection: projection.into(),
+            inv_projection: projection.invert().unwrap_or(Matrix4::identity()).into(),
+            ssao,
+            dof,
+            fog_color,
+            fog,
+            viewport: [width as f32, height as f32, 1.0 / width as f32, 1.0 / height as f32],
+        }
+    }
+}
+
+// group 0 of post.wgsl, what both the ssao and composite passes read
+pub(crate) const DEPTH_BGL_ENTRIES: [BindGroupLayoutEntry; 2] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<PostUniform>() as u64),
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            // read as plain floats, GL backends can't textureLoad a depth texture
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+];
+
+const fn color_entry(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+// group 1 of post.wgsl, only the composite pass reads the scene and the occlusion
+pub(crate) const COMPOSITE_BGL_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    color_entry(0),
+    color_entry(1),
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    },
+];
+
+fn fullscreen_pipeline(driver: &Driver, layout: &wgpu::PipelineLayout, module: &wgpu::ShaderModule, entry_point: &str, format: TextureFormat) -> RenderPipeline {
+    driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            targets: &[Some(format.into())],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn target(driver: &Driver, label: &str, format: TextureFormat, width: u32, height: u32) -> TextureView {
+    driver.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    }).create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn fullscreen_pass(encoder: &mut wgpu::CommandEncoder, label: &str, view: &TextureView, pipeline: &RenderPipeline, bind_groups: &[&BindGroup]) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    for (i, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(i as u32, *bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+// the offscreen scene and occlusion targets plus the two fullscreen pipelines,
+// sized with the surface and rebuilt whenever the depth buffer is
+pub struct PostProcess {
+    depth_bgl: BindGroupLayout,
+    composite_bgl: BindGroupLayout,
+    ssao_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    uniform_buffer: Buffer,
+    sampler: wgpu::Sampler,
+    format: TextureFormat,
+    pub(crate) scene_view: TextureView,
+    ao_view: TextureView,
+    depth_bind_group: BindGroup,
+    composite_bind_group: BindGroup,
+}
+
+impl PostProcess {
+    pub fn new(driver: &Driver, format: TextureFormat, width: u32, height: u32, depth_view: &TextureView) -> Self {
+        let depth_bgl = Cube::create_bgl(driver, &DEPTH_BGL_ENTRIES, Some("post depth bgl"));
+        let composite_bgl = Cube::create_bgl(driver, &COMPOSITE_BGL_ENTRIES, Some("post composite bgl"));
+        let module = Cube::create_shader_from_source(driver, Some("post.wgsl"), include_str!("shaders/post.wgsl"));
+        let ssao_layout = Cube::create_buffer_render_pipeline_layout(driver, &[&depth_bgl]);
+        let composite_layout = Cube::create_buffer_render_pipeline_layout(driver, &[&depth_bgl, &composite_bgl]);
+        let ssao_pipeline = fullscreen_pipeline(driver, &ssao_layout, &module, "fs_ssao", AO_FORMAT);
+        let composite_pipeline = fullscreen_pipeline(driver, &composite_layout, &module, "fs_composite", format);
+        let uniform_buffer = driver.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("post uniform buffer"),
+            size: std::mem::size_of::<PostUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let scene_view = target(driver, "post scene color", format, width, height);
+        let ao_view = target(driver, "ssao", AO_FORMAT, width, height);
+        let depth_bind_group = Self::create_depth_bind_group(driver, &depth_bgl, &uniform_buffer, depth_view);
+        let composite_bind_group = Self::create_composite_bind_group(driver, &composite_bgl, &scene_view, &ao_view, &sampler);
+        PostProcess {
+            depth_bgl,
+            composite_bgl,
+            ssao_pipeline,
+            composite_pipeline,
+            uniform_buffer,
+            sampler,
+            format,
+            scene_view,
+            ao_view,
+            depth_bind_group,
+            composite_bind_group,
+        }
+    }
+
+    fn create_depth_bind_group(driver: &Driver, bgl: &BindGroupLayout, uniform: &Buffer, depth_view: &TextureView) -> BindGroup {
+        Cube::create_bg(driver, bgl, &[
+            BindGroupEntry { binding: 0, resource: uniform.as_entire_binding() },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(depth_view) },
+        ], Some("post depth bg"))
+    }
+
+    fn create_composite_bind_group(driver: &Driver, bgl: &BindGroupLayout, scene: &TextureView, ao: &TextureView, sampler: &wgpu::Sampler) -> BindGroup {
+        Cube::create_bg(driver, bgl, &[
+            BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(scene) },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(ao) },
+            BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ], Some("post composite bg"))
+    }
+
+    pub fn resize(&mut self, driver: &Driver, width: u32, height: u32, depth_view: &TextureView) {
+        self.scene_view = target(driver, "post scene color", self.format, width, height);
+        self.ao_view = target(driver, "ssao", AO_FORMAT, width, height);
+        self.depth_bind_group = Self::create_depth_bind_group(driver, &self.depth_bgl, &self.uniform_buffer, depth_view);
+        self.composite_bind_group = Self::create_composite_bind_group(driver, &self.composite_bgl, &self.scene_view, &self.ao_view, &self.sampler);
+    }
+
+    pub fn write_uniform(&self, driver: &Driver, settings: &PostSettings, projection: Matrix4<f32>, width: u32, height: u32) {
+        let uniform = PostUniform::new(settings, projection, width, height);
+        driver.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    // reads the scene drawn into scene_view and writes the finished frame into view
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, settings: &PostSettings, view: &TextureView) {
+        if settings.ssao.is_some() {
+            fullscreen_pass(encoder, "ssao pass", &self.ao_view, &self.ssao_pipeline, &[&self.depth_bind_group]);
+        }
+        fullscreen_pass(encoder, "post composite pass", view, &self.composite_pipeline, &[&self.depth_bind_group, &self.composite_bind_group]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_effects_are_zeroed_in_the_uniform() {
+        let settings = PostSettings { fog: Some(Fog::default()), ..Default::default() };
+        assert!(settings.is_active());
+        assert!(!PostSettings::default().is_active());
+        let uniform = PostUniform::new(&settings, Matrix4::identity(), 200, 100);
+        assert_eq!(uniform.ssao[0], 0.0);
+        assert_eq!(uniform.dof[0], 0.0);
+        assert_eq!(uniform.fog[0], 1.0);
+        assert_eq!(uniform.viewport, [200.0, 100.0, 0.005, 0.01]);
+    }
+}
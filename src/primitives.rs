@@ -0,0 +1,215 @@
+// Generated meshes in the renderer's format: unindexed, counter clockwise triangle
+// lists with outward unit normals and uvs in 0..1. Vertex colors are white so the
+// material's base color decides how they look. Most shapes are a parametric grid
+// over (u, v), where u runs around the shape and v from bottom to top.
+use std::f32::consts::PI;
+
+use crate::wgpu_helpers::Vertex;
+
+const WHITE: [f32; 4] = [1.0; 4];
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt().max(f32::EPSILON);
+    [v[0] / len, v[1] / len, v[2] / len]
+}
+
+fn area(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    let (u, v) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+    let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt()
+}
+
+// point(u, v) returns (position, normal), the winding is outward when d/du x d/dv points along the normal
+// triangles that collapse (sphere poles, cone apex) are left out
+fn grid(out: &mut Vec<Vertex>, segments_u: u32, segments_v: u32, point: impl Fn(f32, f32) -> ([f32; 3], [f32; 3])) {
+    let vertex = |i: u32, j: u32| {
+        let (u, v) = (i as f32 / segments_u as f32, j as f32 / segments_v as f32);
+        let (position, normal) = point(u, v);
+        Vertex::new(position, WHITE, normalize(normal), [u, 1.0 - v])
+    };
+    let xyz = |v: &Vertex| [v.position[0], v.position[1], v.position[2]];
+    for j in 0..segments_v {
+        for i in 0..segments_u {
+            let (a, b, c, d) = (vertex(i, j), vertex(i + 1, j), vertex(i + 1, j + 1), vertex(i, j + 1));
+            for tri in [[a, b, c], [a, c, d]] {
+                if area(xyz(&tri[0]), xyz(&tri[1]), xyz(&tri[2])) > 1.0e-10 {
+                    out.extend(tri);
+                }
+            }
+        }
+    }
+}
+
+// flat disc at height y facing up or down, uvs map the disc onto the unit square
+fn cap(out: &mut Vec<Vertex>, radius: f32, y: f32, segments: u32, up: bool) {
+    let normal = [0.0, if up { 1.0 } else { -1.0 }, 0.0];
+    let rim = |i: u32| {
+        let phi = 2.0 * PI * i as f32 / segments as f32;
+        let (sin, cos) = phi.sin_cos();
+        Vertex::new([radius * cos, y, -radius * sin], WHITE, normal, [0.5 + 0.5 * cos, 0.5 - 0.5 * sin])
+    };
+    let center = Vertex::new([0.0, y, 0.0], WHITE, normal, [0.5, 0.5]);
+    for i in 0..segments {
+        if up {
+            out.extend([center, rim(i), rim(i + 1)]);
+        } else {
+            out.extend([center, rim(i + 1), rim(i)]);
+        }
+    }
+}
+
+// latitude / longitude sphere, rings from pole to pole
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Vec<Vertex> {
+    let mut out = Vec::new();
+    grid(&mut out, segments.max(3), rings.max(2), |u, v| {
+        let (phi, theta) = (2.0 * PI * u, PI * v);
+        // exactly zero at the poles so their triangles collapse instead of flipping on float noise
+        let ring = if v == 0.0 || v == 1.0 { 0.0 } else { theta.sin() };
+        let n = [ring * phi.cos(), -theta.cos(), -ring * phi.sin()];
+        ([radius * n[0], radius * n[1], radius * n[2]], n)
+    });
+    out
+}
+
+// icosahedron with every face split into 4^subdivisions triangles pushed onto the sphere,
+// uvs are the spherical projection so they wrap with a seam like uv_sphere's
+pub fn icosphere(radius: f32, subdivisions: u32) -> Vec<Vertex> {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let corners = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ].map(normalize);
+    let faces: [[usize; 3]; 20] = [
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+    let mut triangles: Vec<[[f32; 3]; 3]> = faces.iter().map(|f| f.map(|i| corners[i])).collect();
+    for _ in 0..subdivisions {
+        let mid = |a: [f32; 3], b: [f32; 3]| normalize([(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0, (a[2] + b[2]) / 2.0]);
+        triangles = triangles.into_iter().flat_map(|[a, b, c]| {
+            let (ab, bc, ca) = (mid(a, b), mid(b, c), mid(c, a));
+            [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        }).collect();
+    }
+    triangles.into_iter().flatten().map(|n| {
+        let uv = [0.5 - n[2].atan2(n[0]) / (2.0 * PI), 0.5 - n[1].asin() / PI];
+        Vertex::new([radius * n[0], radius * n[1], radius * n[2]], WHITE, n, uv)
+    }).collect()
+}
+
+// capped cylinder centred on the origin along y
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Vec<Vertex> {
+    let segments = segments.max(3);
+    let mut out = Vec::new();
+    grid(&mut out, segments, 1, |u, v| {
+        let (sin, cos) = (2.0 * PI * u).sin_cos();
+        ([radius * cos, height * (v - 0.5), -radius * sin], [cos, 0.0, -sin])
+    });
+    cap(&mut out, radius, height / 2.0, segments, true);
+    cap(&mut out, radius, -height / 2.0, segments, false);
+    out
+}
+
+// base on y = -height / 2, apex on y = height / 2
+pub fn cone(radius: f32, height: f32, segments: u32) -> Vec<Vertex> {
+    let segments = segments.max(3);
+    let mut out = Vec::new();
+    grid(&mut out, segments, 1, |u, v| {
+        let (sin, cos) = (2.0 * PI * u).sin_cos();
+        let ring = radius * (1.0 - v);
+        ([ring * cos, height * (v - 0.5), -ring * sin], [height * cos, radius, -height * sin])
+    });
+    cap(&mut out, radius, -height / 2.0, segments, false);
+    out
+}
+
+// ring in the xz plane, major_radius to the centre of the tube
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Vec<Vertex> {
+    let mut out = Vec::new();
+    grid(&mut out, major_segments.max(3), minor_segments.max(3), |u, v| {
+        let (sin_phi, cos_phi) = (2.0 * PI * u).sin_cos();
+        let (sin_psi, cos_psi) = (2.0 * PI * v).sin_cos();
+        let ring = major_radius + minor_radius * cos_psi;
+        (
+            [ring * cos_phi, minor_radius * sin_psi, -ring * sin_phi],
+            [cos_psi * cos_phi, sin_psi, -cos_psi * sin_phi],
+        )
+    });
+    out
+}
+
+// flat in xz facing +y, subdivisions quads along each side
+pub fn plane(width: f32, depth: f32, subdivisions: u32) -> Vec<Vertex> {
+    let mut out = Vec::new();
+    let n = subdivisions.max(1);
+    grid(&mut out, n, n, |u, v| ([width * (u - 0.5), 0.0, depth * (0.5 - v)], [0.0, 1.0, 0.0]));
+    out
+}
+
+// cube of the given edge length with subdivisions quads along each edge of every face
+pub fn subdivided_cube(size: f32, subdivisions: u32) -> Vec<Vertex> {
+    // (normal, u axis, v axis) with u x v = normal so every face winds outward
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ];
+    let half = size / 2.0;
+    let n = subdivisions.max(1);
+    let mut out = Vec::new();
+    for (normal, du, dv) in faces {
+        grid(&mut out, n, n, |u, v| {
+            let (s, t) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+            let p = [0, 1, 2].map(|k| half * (normal[k] + s * du[k] + t * dv[k]));
+            (p, normal)
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::winding::check_winding;
+
+    fn all() -> Vec<(&'static str, Vec<Vertex>)> {
+        vec![
+            ("uv_sphere", uv_sphere(1.0, 16, 8)),
+            ("icosphere", icosphere(1.0, 2)),
+            ("cylinder", cylinder(0.5, 2.0, 12)),
+            ("cone", cone(0.5, 2.0, 12)),
+            ("torus", torus(1.0, 0.3, 16, 8)),
+            ("plane", plane(2.0, 2.0, 4)),
+            ("subdivided_cube", subdivided_cube(2.0, 3)),
+        ]
+    }
+
+    #[test]
+    fn primitives_wind_outward_with_unit_normals() {
+        for (name, vertices) in all() {
+            assert!(!vertices.is_empty() && vertices.len() % 3 == 0, "{name}");
+            assert_eq!(check_winding(&vertices, wgpu::FrontFace::Ccw), vec![], "{name}");
+            for v in &vertices {
+                let len = (v.normal[0] * v.normal[0] + v.normal[1] * v.normal[1] + v.normal[2] * v.normal[2]).sqrt();
+                assert!((len - 1.0).abs() < 1.0e-4, "{name}: {v:?}");
+                assert!(v.uv.iter().all(|c| (-1.0e-4..=1.0 + 1.0e-4).contains(c)), "{name}: {v:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn segment_counts_set_the_triangle_count() {
+        assert_eq!(plane(1.0, 1.0, 4).len(), 4 * 4 * 6);
+        assert_eq!(subdivided_cube(1.0, 2).len(), 6 * 2 * 2 * 6);
+        assert_eq!(icosphere(1.0, 1).len(), 20 * 4 * 3);
+        // the two pole rings lose their collapsed triangle
+        assert_eq!(uv_sphere(1.0, 8, 4).len(), (8 * 4 * 2 - 2 * 8) * 3);
+        assert_eq!(torus(1.0, 0.25, 8, 6).len(), 8 * 6 * 6);
+    }
+}
@@ -0,0 +1,272 @@
+// Projection matrices that never stretch the scene, whatever shape the surface has.
+// A view is designed for a reference aspect (a square by default): a vertical field of view
+// for perspective, a half height for orthographic. AspectFit decides how that reference view
+// grows or shrinks to the actual aspect, the scale is always the same horizontally and vertically.
+// Switching Projection at runtime blends the matrices and camera elevation over TRANSITION_MS.
+use cgmath::{frustum, ortho, Matrix4, Rad, VectorSpace};
+use std::f32::consts::PI;
+
+// cgmath builds gl clip space with depth -1..1, wgpu's is 0..1
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+pub const DEFAULT_FOV_Y: Rad<f32> = Rad(2.0 * PI / 5.0);
+pub const DEFAULT_ORTHO_HALF_HEIGHT: f32 = 3.0;
+pub const REFERENCE_ASPECT: f32 = 1.0;
+pub const DEFAULT_NEAR: f32 = 0.1;
+pub const DEFAULT_FAR: f32 = 100.0;
+pub const TRANSITION_MS: f64 = 600.0;
+// the orbiting camera's height for perspective and orthographic views, 2 up for 3 out
+const CAMERA_ELEVATION: Rad<f32> = Rad(0.588_002_6);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AspectFit {
+    // the reference width always fills the surface, taller surfaces see more above and below
+    Width,
+    // the reference height always fills the surface, wider surfaces see more to the sides
+    Height,
+    // the whole reference view always shows, with extra room along whichever side is longer
+    #[default]
+    Contain,
+}
+
+impl AspectFit {
+    pub const NAMES: [&'static str; 3] = ["fit-width", "fit-height", "fit-contain"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fit-width" | "width" => Some(AspectFit::Width),
+            "fit-height" | "height" => Some(AspectFit::Height),
+            "fit-contain" | "contain" => Some(AspectFit::Contain),
+            _ => None,
+        }
+    }
+
+    // half width and height of the view that shows the reference half height at this aspect
+    pub fn half_extents(self, reference_half_height: f32, aspect: f32) -> (f32, f32) {
+        let aspect = if aspect.is_finite() && aspect > 0.0 { aspect } else { REFERENCE_ASPECT };
+        let reference_half_width = reference_half_height * REFERENCE_ASPECT;
+        let fit_width = match self {
+            AspectFit::Width => true,
+            AspectFit::Height => false,
+            AspectFit::Contain => aspect < REFERENCE_ASPECT,
+        };
+        match fit_width {
+            true => (reference_half_width, reference_half_width / aspect),
+            false => (reference_half_height * aspect, reference_half_height),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    Orthographic,
+    // orthographic from the elevation where all three axes shorten equally, about 35.26 degrees
+    Isometric,
+    // orthographic from 30 degrees up, the 2:1 pixel art look where two axes shorten equally
+    Dimetric,
+}
+
+impl Projection {
+    pub const NAMES: [&'static str; 4] = ["perspective", "orthographic", "isometric", "dimetric"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "perspective" => Some(Projection::Perspective),
+            "orthographic" | "ortho" => Some(Projection::Orthographic),
+            "isometric" => Some(Projection::Isometric),
+            "dimetric" => Some(Projection::Dimetric),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Isometric,
+            Projection::Isometric => Projection::Dimetric,
+            Projection::Dimetric => Projection::Perspective,
+        }
+    }
+
+    // angle of the camera above the plane it orbits in
+    pub fn elevation(self) -> Rad<f32> {
+        match self {
+            Projection::Perspective | Projection::Orthographic => CAMERA_ELEVATION,
+            Projection::Isometric => Rad((1.0 / 2.0f32.sqrt()).atan()),
+            Projection::Dimetric => Rad(PI / 6.0),
+        }
+    }
+
+    pub fn matrix(self, settings: &ProjectionSettings, aspect: f32) -> Matrix4<f32> {
+        let ProjectionSettings { fov_y, ortho_half_height, near, far, fit } = *settings;
+        match self {
+            Projection::Perspective => perspective(fov_y, aspect, near, far, fit),
+            _ => orthographic(ortho_half_height, aspect, near, far, fit),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProjectionSettings {
+    // vertical at the reference aspect
+    pub fov_y: Rad<f32>,
+    // for all the orthographic projections, also at the reference aspect
+    pub ortho_half_height: f32,
+    pub near: f32,
+    pub far: f32,
+    pub fit: AspectFit,
+}
+
+impl Default for ProjectionSettings {
+    fn default() -> Self {
+        ProjectionSettings {
+            fov_y: DEFAULT_FOV_Y,
+            ortho_half_height: DEFAULT_ORTHO_HALF_HEIGHT,
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            fit: AspectFit::default(),
+        }
+    }
+}
+
+// The projection in use and the one it is blending away from. Progress runs on the wall clock
+// rather than the animation time, so a switch still animates while the animation is paused.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProjectionTransition {
+    from: Projection,
+    to: Projection,
+    elapsed_ms: f64,
+    last_ms: Option<f64>,
+}
+
+impl ProjectionTransition {
+    pub fn new(projection: Projection) -> Self {
+        ProjectionTransition { from: projection, to: projection, elapsed_ms: TRANSITION_MS, last_ms: None }
+    }
+
+    pub fn target(&self) -> Projection {
+        self.to
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.elapsed_ms < TRANSITION_MS
+    }
+
+    pub fn switch(&mut self, to: Projection) {
+        if to == self.to {
+            return;
+        }
+        if to == self.from && self.is_running() {
+            // turning back part way goes back from where it is instead of jumping
+            self.elapsed_ms = TRANSITION_MS - self.elapsed_ms;
+        } else {
+            self.elapsed_ms = 0.0;
+            self.last_ms = None;
+        }
+        self.from = self.to;
+        self.to = to;
+    }
+
+    // true while the blend still needs frames, the first call after a switch only starts the clock
+    pub fn advance(&mut self, now_ms: f64) -> bool {
+        if self.is_running() {
+            if let Some(last) = self.last_ms {
+                self.elapsed_ms = (self.elapsed_ms + (now_ms - last).max(0.0)).min(TRANSITION_MS);
+            }
+            self.last_ms = Some(now_ms);
+        }
+        self.is_running()
+    }
+
+    // eased 0..1 from the old projection to the new one
+    fn blend(&self) -> f32 {
+        let t = (self.elapsed_ms / TRANSITION_MS).clamp(0.0, 1.0) as f32;
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn matrix(&self, settings: &ProjectionSettings, aspect: f32) -> Matrix4<f32> {
+        let to = self.to.matrix(settings, aspect);
+        match self.is_running() {
+            true => self.from.matrix(settings, aspect).lerp(to, self.blend()),
+            false => to,
+        }
+    }
+
+    pub fn elevation(&self) -> Rad<f32> {
+        let (from, to) = (self.from.elevation(), self.to.elevation());
+        match self.is_running() {
+            true => from + (to - from) * self.blend(),
+            false => to,
+        }
+    }
+}
+
+pub fn perspective(fov_y: Rad<f32>, aspect: f32, near: f32, far: f32, fit: AspectFit) -> Matrix4<f32> {
+    let (half_width, half_height) = fit.half_extents(near * (fov_y.0 / 2.0).tan(), aspect);
+    OPENGL_TO_WGPU_MATRIX * frustum(-half_width, half_width, -half_height, half_height, near, far)
+}
+
+pub fn orthographic(half_height: f32, aspect: f32, near: f32, far: f32, fit: AspectFit) -> Matrix4<f32> {
+    let (half_width, half_height) = fit.half_extents(half_height, aspect);
+    OPENGL_TO_WGPU_MATRIX * ortho(-half_width, half_width, -half_height, half_height, near, far)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    #[test]
+    fn fits_keep_square_pixels() {
+        assert_eq!(AspectFit::Height.half_extents(1.0, 2.0), (2.0, 1.0));
+        assert_eq!(AspectFit::Width.half_extents(1.0, 2.0), (1.0, 0.5));
+        // contain never crops the reference square
+        assert_eq!(AspectFit::Contain.half_extents(1.0, 2.0), (2.0, 1.0));
+        assert_eq!(AspectFit::Contain.half_extents(1.0, 0.5), (1.0, 2.0));
+        assert_eq!(AspectFit::Contain.half_extents(1.0, 0.0), (1.0, 1.0));
+        assert_eq!(AspectFit::from_name("fit-width"), Some(AspectFit::Width));
+        assert_eq!(AspectFit::from_name("stretch"), None);
+    }
+
+    #[test]
+    fn clip_depth_runs_zero_to_one() {
+        let depth = |m: Matrix4<f32>, z: f32| {
+            let clip = m * Vector4::new(0.0, 0.0, z, 1.0);
+            clip.z / clip.w
+        };
+        let m = perspective(DEFAULT_FOV_Y, 1.5, 0.1, 100.0, AspectFit::Contain);
+        assert!(depth(m, -0.1).abs() < 1e-5);
+        assert!((depth(m, -100.0) - 1.0).abs() < 1e-5);
+        let m = orthographic(3.0, 1.5, 0.5, 10.0, AspectFit::Contain);
+        assert!(depth(m, -0.5).abs() < 1e-5);
+        assert!((depth(m, -10.0) - 1.0).abs() < 1e-5);
+        // a point on the edge of the reference square lands on the edge of the screen
+        let clip = m * Vector4::new(0.0, 3.0, -1.0, 1.0);
+        assert!((clip.y / clip.w - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn transitions_ease_and_turn_back() {
+        let settings = ProjectionSettings::default();
+        let mut transition = ProjectionTransition::new(Projection::Perspective);
+        assert!(!transition.advance(0.0));
+        transition.switch(Projection::Isometric);
+        assert!(transition.advance(1000.0));
+        assert_eq!(transition.matrix(&settings, 1.0), Projection::Perspective.matrix(&settings, 1.0));
+        assert!(transition.advance(1000.0 + TRANSITION_MS / 4.0));
+        let quarter = transition.elevation();
+        transition.switch(Projection::Perspective);
+        assert!((transition.elevation() - quarter).0.abs() < 1e-6);
+        assert!(!transition.advance(1000.0 + TRANSITION_MS));
+        assert_eq!(transition.elevation(), Projection::Perspective.elevation());
+        assert_eq!(Projection::named("isometric").map(Projection::next), Some(Projection::Dimetric));
+    }
+}
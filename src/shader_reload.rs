@@ -0,0 +1,99 @@
+// Native only hot reload for the wgsl sources in --watch-shaders, src/shaders by default.
+// The watcher only reports which files changed, the new source is checked with
+// shader_validation::compile before anything touches the gpu so a typo never takes the render loop down.
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::shader_validation::compile;
+
+// where the sources were at build time, for running out of a checkout
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
+#[derive(Debug)]
+pub struct ShaderError {
+    pub path: PathBuf,
+    // naga's annotated report with line and column, or the io error
+    pub message: String,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// the same parse and validation the bundled shaders are tested with, so a reload accepts exactly
+// what the tests would
+pub fn check_wgsl(path: &Path, source: &str) -> Result<naga::Module, ShaderError> {
+    compile(source)
+        .map(|(module, _)| module)
+        .map_err(|message| ShaderError { path: path.to_path_buf(), message })
+}
+
+pub struct ShaderWatcher {
+    // dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    // watches every .wgsl file in dir
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    // drains pending file events and returns (file name, checked source) for every shader that changed
+    pub fn poll(&self) -> Vec<(String, Result<String, ShaderError>)> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                    for path in event.paths {
+                        if path.extension().is_some_and(|e| e == "wgsl") && !changed.contains(&path) {
+                            changed.push(path);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("shader watcher error: {e}"),
+            }
+        }
+
+        changed.into_iter().filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let checked = std::fs::read_to_string(&path)
+                .map_err(|e| ShaderError { path: path.clone(), message: e.to_string() })
+                .and_then(|source| check_wgsl(&path, &source).map(|_| source));
+            Some((name, checked))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader_validation::BUNDLED_SHADERS;
+
+    #[test]
+    fn reload_check_matches_the_bundled_check() {
+        for (name, source) in BUNDLED_SHADERS {
+            assert!(check_wgsl(Path::new(name), source).is_ok(), "{name}");
+        }
+        let err = check_wgsl(Path::new("broken.wgsl"), "@fragment fn fs_main() -> @location(0) vec4<f32> { return 1.0; }").unwrap_err();
+        assert_eq!(err.path, Path::new("broken.wgsl"));
+        assert!(err.to_string().starts_with("broken.wgsl: "), "{err}");
+    }
+}
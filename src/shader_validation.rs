@@ -0,0 +1,387 @@
+// Reflects the bundled wgsl with naga so the rust side layouts can be checked
+// against the shaders without a gpu. Everything here works on the parsed module only.
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use naga::{AddressSpace, Binding, ScalarKind, ShaderStage, TypeInner};
+use wgpu::{BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages, VertexBufferLayout, VertexFormat};
+
+// every wgsl file the crate embeds, keep in sync with the include_str! calls
+pub const BUNDLED_SHADERS: &[(&str, &str)] = &[
+    ("shader.wgsl", include_str!("shaders/shader.wgsl")),
+    ("lit.wgsl", include_str!("shaders/lit.wgsl")),
+    ("textured.wgsl", include_str!("shaders/textured.wgsl")),
+    ("wireframe.wgsl", include_str!("shaders/wireframe.wgsl")),
+    ("outline.wgsl", include_str!("shaders/outline.wgsl")),
+    ("procedural.wgsl", include_str!("shaders/procedural.wgsl")),
+    ("oit_composite.wgsl", include_str!("shaders/oit_composite.wgsl")),
+    ("background.wgsl", include_str!("shaders/background.wgsl")),
+    ("pbr.wgsl", include_str!("shaders/pbr.wgsl")),
+    ("ibl_bake.wgsl", include_str!("shaders/ibl_bake.wgsl")),
+    ("ground.wgsl", include_str!("shaders/ground.wgsl")),
+    ("shadow.wgsl", include_str!("shaders/shadow.wgsl")),
+    ("post.wgsl", include_str!("shaders/post.wgsl")),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub kind: ScalarKind,
+    pub components: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingKind {
+    Uniform { size: u32 },
+    Storage { size: u32, read_only: bool },
+    Texture,
+    Sampler,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub kind: BindingKind,
+    // stages whose entry points actually touch the binding
+    pub visibility: ShaderStages,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: ShaderStages,
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+    pub entry_points: Vec<EntryPoint>,
+    pub bindings: Vec<ResourceBinding>,
+}
+
+impl ShaderReflection {
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPoint> {
+        self.entry_points.iter().find(|e| e.name == name)
+    }
+
+    pub fn group(&self, group: u32) -> impl Iterator<Item = &ResourceBinding> {
+        self.bindings.iter().filter(move |b| b.group == group)
+    }
+}
+
+// parses and validates wgsl, errors come back in naga's annotated form with line and column
+pub fn compile(source: &str) -> Result<(naga::Module, ModuleInfo), String> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| e.emit_to_string(source))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| e.emit_to_string(source))?;
+    Ok((module, info))
+}
+
+pub fn reflect(source: &str) -> Result<ShaderReflection, String> {
+    let (module, info) = compile(source)?;
+    Ok(reflect_module(&module, &info))
+}
+
+fn stage_flags(stage: ShaderStage) -> ShaderStages {
+    match stage {
+        ShaderStage::Vertex => ShaderStages::VERTEX,
+        ShaderStage::Fragment => ShaderStages::FRAGMENT,
+        ShaderStage::Compute => ShaderStages::COMPUTE,
+    }
+}
+
+// (scalar kind, component count) of a shader io type
+fn scalar_shape(inner: &TypeInner) -> Option<(ScalarKind, u32)> {
+    match *inner {
+        TypeInner::Scalar(scalar) => Some((scalar.kind, 1)),
+        TypeInner::Vector { size, scalar } => Some((scalar.kind, size as u32)),
+        _ => None,
+    }
+}
+
+pub fn reflect_module(module: &naga::Module, info: &ModuleInfo) -> ShaderReflection {
+    let mut reflection = ShaderReflection::default();
+
+    for ep in module.entry_points.iter() {
+        let mut vertex_inputs = Vec::new();
+        if ep.stage == ShaderStage::Vertex {
+            // inputs are either bare arguments or members of a struct argument
+            let mut push = |binding: &Option<Binding>, ty: naga::Handle<naga::Type>| {
+                if let Some(Binding::Location { location, .. }) = binding
+                    && let Some((kind, components)) = scalar_shape(&module.types[ty].inner) {
+                    vertex_inputs.push(VertexInput { location: *location, kind, components });
+                }
+            };
+            for arg in ep.function.arguments.iter() {
+                match &module.types[arg.ty].inner {
+                    TypeInner::Struct { members, .. } => {
+                        for member in members {
+                            push(&member.binding, member.ty);
+                        }
+                    }
+                    _ => push(&arg.binding, arg.ty),
+                }
+            }
+            vertex_inputs.sort_by_key(|v| v.location);
+        }
+        reflection.entry_points.push(EntryPoint {
+            name: ep.name.clone(),
+            stage: stage_flags(ep.stage),
+            vertex_inputs,
+        });
+    }
+
+    for (handle, var) in module.global_variables.iter() {
+        let Some(res) = &var.binding else { continue };
+        let inner = &module.types[var.ty].inner;
+        let kind = match var.space {
+            AddressSpace::Uniform => BindingKind::Uniform { size: inner.size(module.to_ctx()) },
+            AddressSpace::Storage { access } => BindingKind::Storage {
+                size: inner.size(module.to_ctx()),
+                read_only: !access.contains(naga::StorageAccess::STORE),
+            },
+            AddressSpace::Handle => match inner {
+                TypeInner::Image { .. } => BindingKind::Texture,
+                TypeInner::Sampler { .. } => BindingKind::Sampler,
+                _ => BindingKind::Other,
+            },
+            _ => BindingKind::Other,
+        };
+        let visibility = module.entry_points.iter().enumerate()
+            .filter(|(i, _)| !info.get_entry_point(*i)[handle].is_empty())
+            .fold(ShaderStages::NONE, |acc, (_, ep)| acc | stage_flags(ep.stage));
+        reflection.bindings.push(ResourceBinding {
+            group: res.group,
+            binding: res.binding,
+            kind,
+            visibility,
+        });
+    }
+    reflection.bindings.sort_by_key(|b| (b.group, b.binding));
+    reflection
+}
+
+// (scalar kind, component count) a vertex format shows up as in the shader
+fn vertex_format_shape(format: VertexFormat) -> Option<(ScalarKind, u32)> {
+    use VertexFormat::*;
+    let shape = match format {
+        Uint8 | Uint16 | Uint32 => (ScalarKind::Uint, 1),
+        Uint8x2 | Uint16x2 | Uint32x2 => (ScalarKind::Uint, 2),
+        Uint32x3 => (ScalarKind::Uint, 3),
+        Uint8x4 | Uint16x4 | Uint32x4 => (ScalarKind::Uint, 4),
+        Sint8 | Sint16 | Sint32 => (ScalarKind::Sint, 1),
+        Sint8x2 | Sint16x2 | Sint32x2 => (ScalarKind::Sint, 2),
+        Sint32x3 => (ScalarKind::Sint, 3),
+        Sint8x4 | Sint16x4 | Sint32x4 => (ScalarKind::Sint, 4),
+        Unorm8 | Snorm8 | Unorm16 | Snorm16 | Float16 | Float32 => (ScalarKind::Float, 1),
+        Unorm8x2 | Snorm8x2 | Unorm16x2 | Snorm16x2 | Float16x2 | Float32x2 => (ScalarKind::Float, 2),
+        Float32x3 => (ScalarKind::Float, 3),
+        Unorm8x4 | Snorm8x4 | Unorm16x4 | Snorm16x4 | Float16x4 | Float32x4 => (ScalarKind::Float, 4),
+        _ => return None,
+    };
+    Some(shape)
+}
+
+// every input the vertex entry point reads has to come from one of the buffers with a matching type
+pub fn check_vertex_layout(reflection: &ShaderReflection, entry_point: &str, buffers: &[VertexBufferLayout]) -> Result<(), Vec<String>> {
+    let Some(ep) = reflection.entry_point(entry_point) else {
+        return Err(vec![format!("no entry point named {entry_point}")]);
+    };
+    let mut errors = Vec::new();
+    for input in &ep.vertex_inputs {
+        let attribute = buffers.iter()
+            .flat_map(|b| b.attributes.iter())
+            .find(|a| a.shader_location == input.location);
+        match attribute {
+            None => errors.push(format!("{entry_point}: @location({}) has no vertex attribute", input.location)),
+            Some(attr) => {
+                let expected = (input.kind, input.components);
+                if vertex_format_shape(attr.format) != Some(expected) {
+                    errors.push(format!(
+                        "{entry_point}: @location({}) is {:?}x{} in the shader but {:?} in the layout",
+                        input.location, input.kind, input.components, attr.format
+                    ));
+                }
+            }
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+// every binding the shader declares in the group must be in the layout with a compatible type,
+// big enough and visible to the stages that use it
+pub fn check_bind_group(reflection: &ShaderReflection, group: u32, entries: &[BindGroupLayoutEntry]) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    for shader_binding in reflection.group(group) {
+        let at = format!("@group({group}) @binding({})", shader_binding.binding);
+        let Some(entry) = entries.iter().find(|e| e.binding == shader_binding.binding) else {
+            errors.push(format!("{at} is missing from the bind group layout"));
+            continue;
+        };
+        if !entry.visibility.contains(shader_binding.visibility) {
+            errors.push(format!("{at} is used by {:?} but only visible to {:?}", shader_binding.visibility, entry.visibility));
+        }
+        let compatible = match (&shader_binding.kind, &entry.ty) {
+            (BindingKind::Uniform { size }, BindingType::Buffer { ty: BufferBindingType::Uniform, min_binding_size, .. }) => {
+                if let Some(min) = min_binding_size
+                    && min.get() < *size as u64 {
+                    errors.push(format!("{at} needs {size} bytes but the layout guarantees {min}"));
+                }
+                true
+            }
+            (BindingKind::Storage { read_only, .. }, BindingType::Buffer { ty: BufferBindingType::Storage { read_only: layout_read_only }, .. }) => {
+                *read_only || !layout_read_only
+            }
+            (BindingKind::Texture, BindingType::Texture { .. } | BindingType::StorageTexture { .. }) => true,
+            (BindingKind::Sampler, BindingType::Sampler(_)) => true,
+            _ => false,
+        };
+        if !compatible {
+            errors.push(format!("{at} is {:?} in the shader but {:?} in the layout", shader_binding.kind, entry.ty));
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::BACKGROUND_BGL_ENTRIES;
+    use crate::ibl::{BAKE_BGL_ENTRIES, ENVIRONMENT_BGL_ENTRIES};
+    use crate::material::{MaterialShader, SceneGroup, MATERIAL_BGL_ENTRIES, MATERIAL_UNIFORM_SIZE};
+    use crate::post;
+    use crate::shadow::{CASTER_BGL_ENTRIES, SHADOW_BGL_ENTRIES};
+    use crate::transparency::COMPOSITE_BGL_ENTRIES;
+    use crate::wgpu_helpers::{Vertex, UNIFORM_BGL_ENTRIES, UNIFORM_SIZE};
+
+    fn cube_shader() -> ShaderReflection {
+        reflect(include_str!("shaders/shader.wgsl")).unwrap()
+    }
+
+    #[test]
+    fn bundled_shaders_compile() {
+        for (name, source) in BUNDLED_SHADERS {
+            if let Err(e) = compile(source) {
+                panic!("{name} failed to compile:\n{e}");
+            }
+        }
+    }
+
+    #[test]
+    fn cube_shader_entry_points() {
+        let reflection = cube_shader();
+        assert_eq!(reflection.entry_point("vs_main").unwrap().stage, ShaderStages::VERTEX);
+        assert_eq!(reflection.entry_point("fs_main").unwrap().stage, ShaderStages::FRAGMENT);
+    }
+
+    #[test]
+    fn cube_shader_matches_vertex_layout() {
+        check_vertex_layout(&cube_shader(), "vs_main", &[Vertex::desc()]).unwrap();
+    }
+
+    #[test]
+    fn cube_shader_matches_uniform_layout() {
+        let reflection = cube_shader();
+        check_bind_group(&reflection, 0, &UNIFORM_BGL_ENTRIES).unwrap();
+        let uniform = reflection.group(0).next().unwrap();
+        assert_eq!(uniform.kind, BindingKind::Uniform { size: UNIFORM_SIZE as u32 });
+    }
+
+    #[test]
+    fn material_shaders_match_pipeline_layout() {
+        for shader in MaterialShader::ALL {
+            let reflection = reflect(shader.source()).unwrap();
+            let name = shader.file_name();
+            check_vertex_layout(&reflection, "vs_main", &[Vertex::desc()]).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+            check_bind_group(&reflection, 0, &UNIFORM_BGL_ENTRIES).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+            check_bind_group(&reflection, 1, &MATERIAL_BGL_ENTRIES).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+            let scene_entries: Option<&[BindGroupLayoutEntry]> = match shader.scene_group() {
+                None => None,
+                Some(SceneGroup::Environment) => Some(&ENVIRONMENT_BGL_ENTRIES),
+                Some(SceneGroup::Shadows) => Some(&SHADOW_BGL_ENTRIES),
+            };
+            if let Some(entries) = scene_entries {
+                check_bind_group(&reflection, 2, entries).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+            }
+            let last_group = if scene_entries.is_some() { 2 } else { 1 };
+            assert!(reflection.bindings.iter().all(|b| b.group <= last_group), "{name} binds outside the material layout");
+            if let Some(uniform) = reflection.group(1).find(|b| b.binding == 0) {
+                assert_eq!(uniform.kind, BindingKind::Uniform { size: MATERIAL_UNIFORM_SIZE as u32 });
+            }
+        }
+    }
+
+    #[test]
+    fn oit_shaders_match_their_layouts() {
+        for shader in MaterialShader::ALL.into_iter().filter(|s| s.supports_oit()) {
+            let reflection = reflect(shader.source()).unwrap();
+            assert_eq!(reflection.entry_point("fs_oit").map(|e| e.stage), Some(ShaderStages::FRAGMENT), "{}", shader.file_name());
+        }
+        let composite = reflect(include_str!("shaders/oit_composite.wgsl")).unwrap();
+        check_vertex_layout(&composite, "vs_main", &[]).unwrap();
+        check_bind_group(&composite, 0, &COMPOSITE_BGL_ENTRIES).unwrap();
+    }
+
+    #[test]
+    fn background_shader_matches_its_layout() {
+        let reflection = reflect(include_str!("shaders/background.wgsl")).unwrap();
+        check_vertex_layout(&reflection, "vs_main", &[]).unwrap();
+        check_bind_group(&reflection, 0, &BACKGROUND_BGL_ENTRIES).unwrap();
+    }
+
+    #[test]
+    fn ibl_bake_shader_matches_its_layout() {
+        let reflection = reflect(include_str!("shaders/ibl_bake.wgsl")).unwrap();
+        check_vertex_layout(&reflection, "vs_main", &[]).unwrap();
+        check_bind_group(&reflection, 0, &BAKE_BGL_ENTRIES).unwrap();
+        for entry in ["fs_irradiance", "fs_prefilter", "fs_brdf_lut"] {
+            assert_eq!(reflection.entry_point(entry).map(|e| e.stage), Some(ShaderStages::FRAGMENT));
+        }
+    }
+
+    #[test]
+    fn shadow_shader_matches_its_layout() {
+        let reflection = reflect(include_str!("shaders/shadow.wgsl")).unwrap();
+        check_vertex_layout(&reflection, "vs_main", &[Vertex::desc()]).unwrap();
+        check_bind_group(&reflection, 0, &UNIFORM_BGL_ENTRIES).unwrap();
+        check_bind_group(&reflection, 1, &CASTER_BGL_ENTRIES).unwrap();
+    }
+
+    #[test]
+    fn post_shader_matches_its_layouts() {
+        let reflection = reflect(include_str!("shaders/post.wgsl")).unwrap();
+        check_vertex_layout(&reflection, "vs_main", &[]).unwrap();
+        check_bind_group(&reflection, 0, &post::DEPTH_BGL_ENTRIES).unwrap();
+        check_bind_group(&reflection, 1, &post::COMPOSITE_BGL_ENTRIES).unwrap();
+    }
+
+    #[test]
+    fn mismatched_vertex_format_is_reported() {
+        let attributes = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+        let layout = VertexBufferLayout {
+            array_stride: 28,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &attributes,
+        };
+        let errors = check_vertex_layout(&cube_shader(), "vs_main", &[layout]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("@location(0)"));
+    }
+
+    #[test]
+    fn missing_or_hidden_binding_is_reported() {
+        let reflection = cube_shader();
+        assert!(check_bind_group(&reflection, 0, &[]).is_err());
+
+        let mut fragment_only = UNIFORM_BGL_ENTRIES;
+        fragment_only[0].visibility = ShaderStages::FRAGMENT;
+        assert!(check_bind_group(&reflection, 0, &fragment_only).is_err());
+    }
+
+    #[test]
+    fn compile_errors_carry_a_position() {
+        let err = compile("@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(1.0) }").unwrap_err();
+        assert!(err.contains(":1:"), "{err}");
+    }
+}
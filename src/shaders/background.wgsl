@@ -0,0 +1,94 @@
+// Everything behind the scene, drawn as one fullscreen triangle before the objects.
+// The view ray for each pixel comes from unprojecting its position with the camera's
+// rotation only, so the sky stays at infinity however the camera moves.
+struct background_uniform {
+    inv_view_proj: mat4x4<f32>,
+    top: vec4<f32>,
+    bottom: vec4<f32>,
+    // x: mode (1 gradient, 2 starfield, 3 skybox), y: seconds since start
+    // z: skybox exposure, w: star density
+    params: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> background: background_uniform;
+@binding(1) @group(0) var sky_texture: texture_cube<f32>;
+@binding(2) @group(0) var sky_sampler: sampler;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> vertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: vertexOutput;
+    output.ndc = uv * 2.0 - 1.0;
+    output.position = vec4<f32>(output.ndc, 1.0, 1.0);
+    return output;
+}
+
+fn view_ray(ndc: vec2<f32>) -> vec3<f32> {
+    let far = background.inv_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+    return normalize(far.xyz / far.w);
+}
+
+fn hash3(p: vec3<f32>) -> f32 {
+    let q = fract(p * vec3<f32>(0.1031, 0.1030, 0.0973));
+    let r = q + dot(q, q.yxz + 33.33);
+    return fract((r.x + r.y) * r.z);
+}
+
+fn value_noise(p: vec3<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let s = f * f * (3.0 - 2.0 * f);
+    let x00 = mix(hash3(cell), hash3(cell + vec3<f32>(1.0, 0.0, 0.0)), s.x);
+    let x10 = mix(hash3(cell + vec3<f32>(0.0, 1.0, 0.0)), hash3(cell + vec3<f32>(1.0, 1.0, 0.0)), s.x);
+    let x01 = mix(hash3(cell + vec3<f32>(0.0, 0.0, 1.0)), hash3(cell + vec3<f32>(1.0, 0.0, 1.0)), s.x);
+    let x11 = mix(hash3(cell + vec3<f32>(0.0, 1.0, 1.0)), hash3(cell + vec3<f32>(1.0, 1.0, 1.0)), s.x);
+    return mix(mix(x00, x10, s.y), mix(x01, x11, s.y), s.z);
+}
+
+fn fbm(p: vec3<f32>) -> f32 {
+    var sum = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 5; i++) {
+        sum += amplitude * value_noise(q);
+        q *= 2.03;
+        amplitude *= 0.5;
+    }
+    return sum;
+}
+
+fn starfield(dir: vec3<f32>, time: f32, density: f32) -> vec3<f32> {
+    // nebula: two tints from the gradient colors through low frequency noise
+    let cloud = smoothstep(0.45, 0.8, fbm(dir * 2.5));
+    let wisps = smoothstep(0.5, 0.9, fbm(dir * 5.0 + vec3<f32>(7.0)));
+    var color = mix(background.bottom.rgb * 0.15, background.top.rgb, cloud * 0.6) + background.bottom.rgb * wisps * 0.3;
+
+    // stars: one candidate per cell of a grid laid over the view direction
+    let p = dir * 180.0;
+    let cell = floor(p);
+    let h = hash3(cell);
+    if h > 1.0 - 0.05 * density {
+        let center = cell + vec3<f32>(hash3(cell + 1.7), hash3(cell + 3.1), hash3(cell + 5.3));
+        let d = length(p - center);
+        let twinkle = 0.75 + 0.25 * sin(time * (1.0 + 3.0 * h) + h * 40.0);
+        color += vec3<f32>(smoothstep(0.35, 0.0, d) * twinkle);
+    }
+    return color;
+}
+
+@fragment
+fn fs_main(in: vertexOutput) -> @location(0) vec4<f32> {
+    let mode = u32(background.params.x);
+    let dir = view_ray(in.ndc);
+    var color: vec3<f32>;
+    switch mode {
+        case 2u: { color = starfield(dir, background.params.y, background.params.w); }
+        case 3u: { color = textureSample(sky_texture, sky_sampler, dir).rgb * background.params.z; }
+        default: { color = mix(background.bottom.rgb, background.top.rgb, in.ndc.y * 0.5 + 0.5); }
+    }
+    return vec4<f32>(color, 1.0);
+}
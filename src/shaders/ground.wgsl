@@ -0,0 +1,79 @@
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    // ground color, or the shadow color and its opacity for the catcher
+    base_color: vec4<f32>,
+    // x: 0 lit ground plane, 1 shadow catcher that only draws the shadow
+    // y: ambient term of the plane
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
+struct shadow_uniform {
+    light_view_proj: mat4x4<f32>,
+    // x: depth bias, y: slope bias, z: pcf radius in texels, w: one texel in uv
+    params: vec4<f32>,
+};
+@binding(0) @group(2) var<uniform> shadow: shadow_uniform;
+@binding(1) @group(2) var shadow_map: texture_depth_2d;
+@binding(2) @group(2) var shadow_sampler: sampler_comparison;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(2) normal: vec3<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    output.normal = (uniforms.modelMatrix * vec4<f32>(normal, 0.0)).xyz;
+    output.world_position = (uniforms.modelMatrix * pos).xyz;
+    return output;
+}
+
+// fraction of the pcf kernel that can see the light, 1 outside the shadow map
+fn visibility(world_position: vec3<f32>, n_dot_l: f32) -> f32 {
+    let clip = shadow.light_view_proj * vec4<f32>(world_position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    // steeper surfaces need more bias to avoid acne
+    let slope = sqrt(max(1.0 - n_dot_l * n_dot_l, 0.0)) / max(n_dot_l, 0.05);
+    let depth = ndc.z - shadow.params.x - shadow.params.y * slope;
+    let radius = i32(shadow.params.z);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * shadow.params.w;
+            lit += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, depth);
+        }
+    }
+    let taps = f32((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
+
+@fragment
+fn fs_main(in: vertexOutput) -> @location(0) vec4<f32> {
+    let n = normalize(in.normal);
+    let l = normalize(uniforms.light.xyz);
+    let n_dot_l = max(dot(n, l), 0.0);
+    let lit = visibility(in.world_position, n_dot_l);
+    if material.params.x > 0.5 {
+        return vec4<f32>(material.base_color.rgb, (1.0 - lit) * material.base_color.a);
+    }
+    let ambient = material.params.y;
+    let light = ambient + (1.0 - ambient) * n_dot_l * lit;
+    return vec4<f32>(material.base_color.rgb * light, material.base_color.a);
+}
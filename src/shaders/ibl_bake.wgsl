@@ -0,0 +1,155 @@
+// Startup and skybox-change bakes for image based lighting, each drawn as a fullscreen
+// triangle into one cube face (or the 2D lookup table). Directions follow the same
+// face layout as background.rs face_direction.
+struct bake_uniform {
+    // x: cube face being drawn, y: roughness the prefilter pass integrates for
+    // z: source face size in texels, w: samples per texel
+    params: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> bake: bake_uniform;
+@binding(1) @group(0) var source: texture_cube<f32>;
+@binding(2) @group(0) var source_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+const BRDF_SAMPLES: u32 = 512u;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> vertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: vertexOutput;
+    output.ndc = uv * 2.0 - 1.0;
+    output.position = vec4<f32>(output.ndc, 0.0, 1.0);
+    return output;
+}
+
+// u and v in -1..1 with v pointing down the face, +x, -x, +y, -y, +z, -z
+fn face_direction(face: u32, u: f32, v: f32) -> vec3<f32> {
+    var d: vec3<f32>;
+    switch face {
+        case 0u: { d = vec3<f32>(1.0, -v, -u); }
+        case 1u: { d = vec3<f32>(-1.0, -v, u); }
+        case 2u: { d = vec3<f32>(u, 1.0, v); }
+        case 3u: { d = vec3<f32>(u, -1.0, -v); }
+        case 4u: { d = vec3<f32>(u, -v, 1.0); }
+        default: { d = vec3<f32>(-u, -v, -1.0); }
+    }
+    return normalize(d);
+}
+
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// tangent space sample around n
+fn to_world(local: vec3<f32>, n: vec3<f32>) -> vec3<f32> {
+    let up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return tangent * local.x + bitangent * local.y + n * local.z;
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return to_world(vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// the source mip whose texels cover about the solid angle one sample stands for,
+// filtered importance sampling keeps bright spots from turning into fireflies
+fn sample_lod(pdf: f32, count: f32) -> f32 {
+    let size = bake.params.z;
+    let texel = 4.0 * PI / (6.0 * size * size);
+    let sample = 1.0 / (count * pdf + 1e-4);
+    return max(0.5 * log2(sample / texel) + 1.0, 0.0);
+}
+
+fn texel_direction(in: vertexOutput) -> vec3<f32> {
+    return face_direction(u32(bake.params.x), in.ndc.x, -in.ndc.y);
+}
+
+// cosine weighted hemisphere average, the diffuse term multiplies it with the albedo as is
+@fragment
+fn fs_irradiance(in: vertexOutput) -> @location(0) vec4<f32> {
+    let n = texel_direction(in);
+    let count = u32(bake.params.w);
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < count; i++) {
+        let xi = hammersley(i, count);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt(1.0 - xi.y);
+        let sin_theta = sqrt(xi.y);
+        let l = to_world(vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta), n);
+        let lod = sample_lod(cos_theta / PI, f32(count));
+        sum += textureSampleLevel(source, source_sampler, l, lod).rgb;
+    }
+    return vec4<f32>(sum / f32(count), 1.0);
+}
+
+// GGX lobe for one roughness per mip, with the usual n = v = r assumption
+@fragment
+fn fs_prefilter(in: vertexOutput) -> @location(0) vec4<f32> {
+    let n = texel_direction(in);
+    let roughness = bake.params.y;
+    if roughness <= 0.0 {
+        return vec4<f32>(textureSampleLevel(source, source_sampler, n, 0.0).rgb, 1.0);
+    }
+    let count = u32(bake.params.w);
+    var sum = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < count; i++) {
+        let h = importance_sample_ggx(hammersley(i, count), n, roughness);
+        let n_dot_h = max(dot(n, h), 0.0);
+        let l = 2.0 * n_dot_h * h - n;
+        let n_dot_l = dot(n, l);
+        if n_dot_l > 0.0 {
+            // with n = v the pdf D * n.h / (4 v.h) reduces to D / 4
+            let lod = sample_lod(distribution_ggx(n_dot_h, roughness) / 4.0, f32(count));
+            sum += textureSampleLevel(source, source_sampler, l, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    return vec4<f32>(sum / max(weight, 1e-4), 1.0);
+}
+
+// Smith G with the k the IBL split sum uses
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+// x: n.v across, y: roughness down, out: scale and bias applied to f0
+@fragment
+fn fs_brdf_lut(in: vertexOutput) -> @location(0) vec2<f32> {
+    let n_dot_v = max(in.ndc.x * 0.5 + 0.5, 1e-4);
+    let roughness = 0.5 - in.ndc.y * 0.5;
+    let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let n = vec3<f32>(0.0, 0.0, 1.0);
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < BRDF_SAMPLES; i++) {
+        let h = importance_sample_ggx(hammersley(i, BRDF_SAMPLES), n, roughness);
+        let v_dot_h = max(dot(v, h), 0.0);
+        let l = 2.0 * v_dot_h * h - v;
+        let n_dot_l = max(l.z, 0.0);
+        if n_dot_l > 0.0 {
+            let g_vis = geometry_smith(n_dot_v, n_dot_l, roughness) * v_dot_h / (max(h.z, 1e-4) * n_dot_v);
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+    return vec2<f32>(scale, bias) / f32(BRDF_SAMPLES);
+}
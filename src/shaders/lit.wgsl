@@ -0,0 +1,65 @@
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    base_color: vec4<f32>,
+    // x: ambient term
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) vector_color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(1) color: vec4<f32>, @location(2) normal: vec3<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    output.vector_color = color;
+    output.normal = (uniforms.modelMatrix * vec4<f32>(normal, 0.0)).xyz;
+    return output;
+}
+
+fn shade(in: vertexOutput, facing: bool) -> vec4<f32> {
+    // back faces of two sided materials are lit from their inside
+    let normal = select(-normalize(in.normal), normalize(in.normal), facing);
+    let diffuse = max(dot(normal, normalize(uniforms.light.xyz)), 0.0);
+    let light = material.params.x + (1.0 - material.params.x) * diffuse;
+    let color = in.vector_color * material.base_color;
+    return vec4<f32>(color.rgb * light, color.a);
+}
+
+@fragment
+fn fs_main(in: vertexOutput, @builtin(front_facing) facing: bool) -> @location(0) vec4<f32> {
+    return shade(in, facing);
+}
+
+// weighted blended order independent transparency (McGuire & Bavoil 2013)
+struct oitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) reveal: f32,
+}
+
+fn oit_output(color: vec4<f32>, depth: f32) -> oitOutput {
+    let weight = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - depth * 0.9, 3.0), 1e-2, 3e3);
+    var output: oitOutput;
+    output.accum = vec4<f32>(color.rgb * color.a, color.a) * weight;
+    output.reveal = color.a;
+    return output;
+}
+
+@fragment
+fn fs_oit(in: vertexOutput, @builtin(front_facing) facing: bool) -> oitOutput {
+    return oit_output(shade(in, facing), in.position.z);
+}
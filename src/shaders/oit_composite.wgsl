@@ -0,0 +1,22 @@
+// Resolves the weighted blended transparency targets over the opaque image.
+// Drawn as one fullscreen triangle with src * (1 - revealage) + dst * revealage blending.
+@binding(0) @group(0) var accum_texture: texture_2d<f32>;
+@binding(1) @group(0) var reveal_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    let reveal = textureLoad(reveal_texture, texel, 0).r;
+    if reveal >= 1.0 {
+        discard;
+    }
+    let accum = textureLoad(accum_texture, texel, 0);
+    let average = accum.rgb / clamp(accum.a, 1e-4, 5e4);
+    return vec4<f32>(average, reveal);
+}
@@ -0,0 +1,36 @@
+// Silhouette outline drawn as an inverted hull: only back faces are kept and pushed
+// outwards in screen space so a band of constant pixel width shows around the object.
+// The push direction is the vertex position from the mesh origin, which keeps the hull
+// closed at hard edges for convex meshes centered on their origin.
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    base_color: vec4<f32>,
+    // x: thickness in pixels, zw: viewport size in pixels
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>) -> @builtin(position) vec4<f32> {
+    var clip = uniforms.mvpMatrix * pos;
+    let push = (uniforms.mvpMatrix * vec4<f32>(pos.xyz, 0.0)).xy;
+    if length(push) > 0.0 {
+        let viewport = max(material.params.zw, vec2<f32>(1.0));
+        clip = vec4<f32>(clip.xy + normalize(push) * material.params.x * 2.0 / viewport * clip.w, clip.zw);
+    }
+    return clip;
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return material.base_color;
+}
@@ -0,0 +1,104 @@
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    // glTF baseColorFactor, multiplied with the base color texture
+    base_color: vec4<f32>,
+    // x: metallic, y: roughness, z: environment intensity
+    // w: how much the vertex colors tint the base color (glTF COLOR_0 at 1)
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+@binding(1) @group(1) var base_color_texture: texture_2d<f32>;
+@binding(2) @group(1) var base_color_sampler: sampler;
+
+// baked by ibl.rs from the current environment
+@binding(0) @group(2) var irradiance_map: texture_cube<f32>;
+@binding(1) @group(2) var prefiltered_map: texture_cube<f32>;
+@binding(2) @group(2) var brdf_lut: texture_2d<f32>;
+@binding(3) @group(2) var environment_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+const LIGHT_INTENSITY: f32 = 3.0;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) vector_color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(1) color: vec4<f32>, @location(2) normal: vec3<f32>, @location(3) uv: vec2<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    output.vector_color = color;
+    output.normal = (uniforms.modelMatrix * vec4<f32>(normal, 0.0)).xyz;
+    output.uv = uv;
+    output.world_position = (uniforms.modelMatrix * pos).xyz;
+    return output;
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith G with the k analytic lights use
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// rough surfaces reflect less at grazing angles than plain schlick says
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+@fragment
+fn fs_main(in: vertexOutput, @builtin(front_facing) facing: bool) -> @location(0) vec4<f32> {
+    let tint = mix(vec4<f32>(1.0), in.vector_color, material.params.w);
+    let base = textureSample(base_color_texture, base_color_sampler, in.uv) * material.base_color * tint;
+    let metallic = clamp(material.params.x, 0.0, 1.0);
+    let roughness = clamp(material.params.y, 0.04, 1.0);
+
+    let n = select(-normalize(in.normal), normalize(in.normal), facing);
+    let v = normalize(uniforms.eye_time.xyz - in.world_position);
+    let n_dot_v = max(dot(n, v), 1e-4);
+    let f0 = mix(vec3<f32>(0.04), base.rgb, metallic);
+
+    // key light, cook torrance
+    let l = normalize(uniforms.light.xyz);
+    let h = normalize(v + l);
+    let n_dot_l = max(dot(n, l), 0.0);
+    let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    let specular = distribution_ggx(max(dot(n, h), 0.0), roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) * f
+        / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    let kd = (1.0 - f) * (1.0 - metallic);
+    let direct = (kd * base.rgb / PI + specular) * LIGHT_INTENSITY * n_dot_l;
+
+    // environment, split sum
+    let f_ambient = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    let kd_ambient = (1.0 - f_ambient) * (1.0 - metallic);
+    let irradiance = textureSample(irradiance_map, environment_sampler, n).rgb;
+    let max_lod = f32(textureNumLevels(prefiltered_map) - 1u);
+    let prefiltered = textureSampleLevel(prefiltered_map, environment_sampler, reflect(-v, n), roughness * max_lod).rgb;
+    let brdf = textureSample(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness)).rg;
+    let ambient = (kd_ambient * irradiance * base.rgb + prefiltered * (f_ambient * brdf.x + brdf.y)) * material.params.z;
+
+    return vec4<f32>(direct + ambient, base.a);
+}
@@ -0,0 +1,151 @@
+// Screen space effects over the finished scene, all driven by the depth buffer.
+// fs_ssao writes ambient occlusion into its own target, fs_composite then darkens,
+// blurs out of focus areas and fogs the scene color into the surface.
+struct post_uniform {
+    projection: mat4x4<f32>,
+    inv_projection: mat4x4<f32>,
+    // x: on, y: radius in view units, z: intensity, w: depth bias
+    ssao: vec4<f32>,
+    // x: on, y: focus distance, z: distance over which blur ramps up, w: max blur radius in pixels
+    dof: vec4<f32>,
+    fog_color: vec4<f32>,
+    // x: on, y: start distance, z: density
+    fog: vec4<f32>,
+    // xy: target size in pixels, zw: one pixel in uv
+    viewport: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> post: post_uniform;
+// the depth buffer bound as an unfilterable float texture, depth sits in r
+@binding(1) @group(0) var depth_texture: texture_2d<f32>;
+
+@binding(0) @group(1) var scene_texture: texture_2d<f32>;
+@binding(1) @group(1) var ao_texture: texture_2d<f32>;
+@binding(2) @group(1) var post_sampler: sampler;
+
+const SSAO_SAMPLES: u32 = 16u;
+const DOF_SAMPLES: u32 = 16u;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> vertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: vertexOutput;
+    output.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+fn load_depth(pixel: vec2<i32>) -> f32 {
+    let size = vec2<i32>(post.viewport.xy);
+    return textureLoad(depth_texture, clamp(pixel, vec2<i32>(0), size - 1), 0).r;
+}
+
+// camera space position of a uv and the depth stored there
+fn view_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let p = post.inv_projection * ndc;
+    return p.xyz / p.w;
+}
+
+fn pixel_view_position(pixel: vec2<i32>) -> vec3<f32> {
+    let uv = (vec2<f32>(pixel) + 0.5) * post.viewport.zw;
+    return view_position(uv, load_depth(pixel));
+}
+
+// interleaved gradient noise, rotates the kernel per pixel so banding turns into fine grain
+fn noise(pixel: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+@fragment
+fn fs_ssao(in: vertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    let depth = load_depth(pixel);
+    if depth >= 1.0 {
+        return vec4<f32>(1.0);
+    }
+    let p = pixel_view_position(pixel);
+    // normal from whichever neighbours are closer, so silhouettes don't bend it
+    let right = pixel_view_position(pixel + vec2<i32>(1, 0)) - p;
+    let left = p - pixel_view_position(pixel - vec2<i32>(1, 0));
+    let down = pixel_view_position(pixel + vec2<i32>(0, 1)) - p;
+    let up = p - pixel_view_position(pixel - vec2<i32>(0, 1));
+    let dx = select(left, right, abs(right.z) < abs(left.z));
+    let dy = select(up, down, abs(down.z) < abs(up.z));
+    let n = normalize(cross(dy, dx));
+
+    let radius = post.ssao.y;
+    let bias = post.ssao.w;
+    let rotation = noise(in.position.xy) * 6.28318530718;
+    let helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.x) > 0.9);
+    let tangent = normalize(cross(helper, n));
+    let bitangent = cross(n, tangent);
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < SSAO_SAMPLES; i++) {
+        // points spiralling out through the hemisphere, denser near the centre
+        let t = (f32(i) + 0.5) / f32(SSAO_SAMPLES);
+        let angle = f32(i) * GOLDEN_ANGLE + rotation;
+        let spread = sqrt(t);
+        let local = vec3<f32>(cos(angle) * spread, sin(angle) * spread, sqrt(1.0 - t));
+        let s = p + (tangent * local.x + bitangent * local.y + n * local.z) * radius * mix(0.1, 1.0, t * t);
+
+        let clip = post.projection * vec4<f32>(s, 1.0);
+        let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+        let scene = pixel_view_position(vec2<i32>(uv * post.viewport.xy));
+        let range = smoothstep(0.0, 1.0, radius / max(abs(p.z - scene.z), 1e-4));
+        occlusion += select(0.0, 1.0, scene.z >= s.z + bias) * range;
+    }
+    let ao = 1.0 - occlusion / f32(SSAO_SAMPLES) * post.ssao.z;
+    return vec4<f32>(vec3<f32>(clamp(ao, 0.0, 1.0)), 1.0);
+}
+
+fn blurred_ao(uv: vec2<f32>) -> f32 {
+    var sum = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            sum += textureSampleLevel(ao_texture, post_sampler, uv + vec2<f32>(f32(x), f32(y)) * post.viewport.zw, 0.0).r;
+        }
+    }
+    return sum / 9.0;
+}
+
+@fragment
+fn fs_composite(in: vertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    let depth = load_depth(pixel);
+    let p = view_position(in.uv, depth);
+    let distance = length(p);
+    let is_geometry = depth < 1.0;
+
+    var color = textureSampleLevel(scene_texture, post_sampler, in.uv, 0.0);
+    if post.dof.x > 0.5 {
+        // circle of confusion from how far the pixel is from the focus distance, the sky counts as far
+        let blur_distance = select(1e6, -p.z, is_geometry);
+        let coc = clamp(abs(blur_distance - post.dof.y) / max(post.dof.z, 1e-4), 0.0, 1.0) * post.dof.w;
+        if coc > 0.5 {
+            var sum = color.rgb;
+            for (var i = 0u; i < DOF_SAMPLES; i++) {
+                let r = sqrt((f32(i) + 0.5) / f32(DOF_SAMPLES)) * coc;
+                let angle = f32(i) * GOLDEN_ANGLE;
+                let offset = vec2<f32>(cos(angle), sin(angle)) * r * post.viewport.zw;
+                sum += textureSampleLevel(scene_texture, post_sampler, in.uv + offset, 0.0).rgb;
+            }
+            color = vec4<f32>(sum / f32(DOF_SAMPLES + 1u), color.a);
+        }
+    }
+    if post.ssao.x > 0.5 && is_geometry {
+        color = vec4<f32>(color.rgb * blurred_ao(in.uv), color.a);
+    }
+    // only geometry is fogged so the background keeps reading as the distance
+    if post.fog.x > 0.5 && is_geometry {
+        let amount = 1.0 - exp(-post.fog.z * max(distance - post.fog.y, 0.0));
+        color = vec4<f32>(mix(color.rgb, post.fog_color.rgb, amount * post.fog_color.a), color.a);
+    }
+    return color;
+}
@@ -0,0 +1,101 @@
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    // tint for the grid lines, rim glow and foil highlights
+    base_color: vec4<f32>,
+    // x: effect (0 gradient, 1 plasma, 2 grid, 3 fresnel rim, 4 holographic foil)
+    // y: speed, z: scale, w: intensity
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
+const TAU: f32 = 6.28318530718;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) vector_color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(1) color: vec4<f32>, @location(2) normal: vec3<f32>, @location(3) uv: vec2<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    output.vector_color = color;
+    output.normal = (uniforms.modelMatrix * vec4<f32>(normal, 0.0)).xyz;
+    output.uv = uv;
+    output.world_position = (uniforms.modelMatrix * pos).xyz;
+    return output;
+}
+
+// iq's cosine palette, t in 0..1 walks once around the hue circle
+fn rainbow(t: f32) -> vec3<f32> {
+    return 0.5 + 0.5 * cos(TAU * (vec3<f32>(t) + vec3<f32>(0.0, 0.33, 0.67)));
+}
+
+fn animated_gradient(in: vertexOutput, t: f32, scale: f32) -> vec3<f32> {
+    let s = (in.uv.x + in.uv.y) * 0.5 * scale + t * 0.1;
+    return mix(in.vector_color.rgb, rainbow(s), 0.6);
+}
+
+fn plasma(in: vertexOutput, t: f32, scale: f32) -> vec3<f32> {
+    let p = in.uv * scale * 4.0;
+    var v = sin(p.x + t);
+    v += sin((p.y + t) * 0.5);
+    v += sin((p.x + p.y + t) * 0.5);
+    let c = p + 0.5 * vec2<f32>(sin(t / 5.0), cos(t / 3.0));
+    v += sin(sqrt(c.x * c.x + c.y * c.y + 1.0) + t);
+    return rainbow(v * 0.25) * in.vector_color.rgb * 0.5 + rainbow(v * 0.25) * 0.5;
+}
+
+fn grid(in: vertexOutput, t: f32, scale: f32) -> vec3<f32> {
+    let cells = in.uv * max(scale, 1.0) * 4.0 + vec2<f32>(0.0, t * 0.25);
+    let width = fwidth(cells) * 1.5;
+    let line = smoothstep(width, vec2<f32>(0.0), abs(fract(cells - 0.5) - 0.5));
+    return mix(in.vector_color.rgb * 0.4, material.base_color.rgb, max(line.x, line.y));
+}
+
+fn fresnel(in: vertexOutput, view: vec3<f32>, normal: vec3<f32>, t: f32, scale: f32) -> vec3<f32> {
+    let rim = pow(1.0 - max(dot(normal, view), 0.0), max(scale, 0.5) * 3.0);
+    let pulse = 0.75 + 0.25 * sin(t * 2.0);
+    return in.vector_color.rgb * 0.6 + material.base_color.rgb * rim * pulse * 2.0;
+}
+
+fn holographic_foil(in: vertexOutput, view: vec3<f32>, normal: vec3<f32>, t: f32, scale: f32) -> vec3<f32> {
+    // hue follows the viewing angle so the colors slide as the cube turns
+    let angle = dot(normal, view);
+    let bands = rainbow(angle * 1.5 + (in.uv.x - in.uv.y) * scale * 0.5 + t * 0.05);
+    let sparkle = pow(max(sin((in.uv.x * 37.0 + in.uv.y * 53.0) * scale + t * 3.0), 0.0), 24.0);
+    let base = mix(in.vector_color.rgb, vec3<f32>(0.8), 0.5);
+    return base * bands + material.base_color.rgb * sparkle;
+}
+
+@fragment
+fn fs_main(in: vertexOutput, @builtin(front_facing) facing: bool) -> @location(0) vec4<f32> {
+    let t = uniforms.eye_time.w * material.params.y;
+    let scale = material.params.z;
+    let view = normalize(uniforms.eye_time.xyz - in.world_position);
+    let normal = select(-normalize(in.normal), normalize(in.normal), facing);
+
+    var effect: vec3<f32>;
+    switch u32(material.params.x) {
+        case 0u: { effect = animated_gradient(in, t, scale); }
+        case 1u: { effect = plasma(in, t, scale); }
+        case 2u: { effect = grid(in, t, scale); }
+        case 3u: { effect = fresnel(in, view, normal, t, scale); }
+        default: { effect = holographic_foil(in, view, normal, t, scale); }
+    }
+    // intensity fades from the plain vertex color to the full effect
+    let color = mix(in.vector_color.rgb, effect, clamp(material.params.w, 0.0, 1.0));
+    return vec4<f32>(color, in.vector_color.a);
+}
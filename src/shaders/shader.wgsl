@@ -1,10 +1,22 @@
 struct uniform_buffer {
     mvpMatrix: mat4x4<f32>,
     //model view perspective
+    modelMatrix: mat4x4<f32>,
+    //object to world, used for normals
+    eye_time: vec4<f32>,
+    //camera position in world space, w is seconds since start
+    light: vec4<f32>,
+    //direction towards the directional light
 };
 @binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
 //above line is passing the model view matrix to the shader
 
+struct material_uniform {
+    base_color: vec4<f32>,
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
 struct vertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) vector_color: vec4<f32>,
@@ -20,5 +32,24 @@ fn vs_main(@location(0) pos: vec4<f32>, @location(1) color: vec4<f32>) -> vertex
 
 @fragment
 fn fs_main(@location(0) vector_color: vec4<f32>) -> @location(0) vec4<f32> {
-    return vector_color;
-}
\ No newline at end of file
+    return vector_color * material.base_color;
+}
+
+// weighted blended order independent transparency (McGuire & Bavoil 2013)
+struct oitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) reveal: f32,
+}
+
+fn oit_output(color: vec4<f32>, depth: f32) -> oitOutput {
+    let weight = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - depth * 0.9, 3.0), 1e-2, 3e3);
+    var output: oitOutput;
+    output.accum = vec4<f32>(color.rgb * color.a, color.a) * weight;
+    output.reveal = color.a;
+    return output;
+}
+
+@fragment
+fn fs_oit(in: vertexOutput) -> oitOutput {
+    return oit_output(in.vector_color * material.base_color, in.position.z);
+}
@@ -0,0 +1,22 @@
+// Depth only pass from the directional light, one draw per opaque object.
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct shadow_uniform {
+    light_view_proj: mat4x4<f32>,
+    // x: depth bias, y: slope bias, z: pcf radius in texels, w: one texel in uv
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> shadow: shadow_uniform;
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>) -> @builtin(position) vec4<f32> {
+    return shadow.light_view_proj * uniforms.modelMatrix * pos;
+}
@@ -0,0 +1,55 @@
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    base_color: vec4<f32>,
+    // xy: uv scale
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+@binding(1) @group(1) var t_diffuse: texture_2d<f32>;
+@binding(2) @group(1) var s_diffuse: sampler;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(3) uv: vec2<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    output.uv = uv * material.params.xy;
+    return output;
+}
+
+@fragment
+fn fs_main(in: vertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.uv) * material.base_color;
+}
+
+// weighted blended order independent transparency (McGuire & Bavoil 2013)
+struct oitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) reveal: f32,
+}
+
+fn oit_output(color: vec4<f32>, depth: f32) -> oitOutput {
+    let weight = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - depth * 0.9, 3.0), 1e-2, 3e3);
+    var output: oitOutput;
+    output.accum = vec4<f32>(color.rgb * color.a, color.a) * weight;
+    output.reveal = color.a;
+    return output;
+}
+
+@fragment
+fn fs_oit(in: vertexOutput) -> oitOutput {
+    return oit_output(textureSample(t_diffuse, s_diffuse, in.uv) * material.base_color, in.position.z);
+}
@@ -0,0 +1,44 @@
+// Wireframe for targets without POLYGON_MODE_LINE (WebGL, WebGPU).
+// Triangles are drawn filled and everything but a thin band along their edges is discarded,
+// the band comes from barycentric coords derived from the vertex index of the unindexed mesh.
+struct uniform_buffer {
+    mvpMatrix: mat4x4<f32>,
+    modelMatrix: mat4x4<f32>,
+    // xyz: camera position in world space, w: seconds since start
+    eye_time: vec4<f32>,
+    // xyz: direction towards the directional light
+    light: vec4<f32>,
+};
+@binding(0) @group(0) var<uniform> uniforms: uniform_buffer;
+
+struct material_uniform {
+    base_color: vec4<f32>,
+    // x: line width in pixels
+    params: vec4<f32>,
+};
+@binding(0) @group(1) var<uniform> material: material_uniform;
+
+struct vertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) barycentric: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, @location(0) pos: vec4<f32>) -> vertexOutput {
+    var output: vertexOutput;
+    output.position = uniforms.mvpMatrix * pos;
+    let corner = index % 3u;
+    output.barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+    return output;
+}
+
+@fragment
+fn fs_main(in: vertexOutput) -> @location(0) vec4<f32> {
+    let width = max(material.params.x, 0.5);
+    let edge = smoothstep(vec3<f32>(0.0), fwidth(in.barycentric) * width, in.barycentric);
+    let coverage = 1.0 - min(min(edge.x, edge.y), edge.z);
+    if coverage < 0.01 {
+        discard;
+    }
+    return vec4<f32>(material.base_color.rgb, material.base_color.a * coverage);
+}
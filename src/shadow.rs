@@ -0,0 +1,318 @@
+// Directional light shadows. Opaque objects are drawn depth only from the light into a
+// shadow map, which the ground material then samples through a PCF kernel. The light
+// looks at the origin through an orthographic box sized for the scene around the cube.
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer, RenderPipeline, TextureFormat, TextureView};
+
+use crate::material::{BlendMode, Material, MaterialShader, MaterialUniform};
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::{Cube, Mesh, Vertex};
+
+pub const SHADOW_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+// half the width of the box the light sees, and how far back it sits from the origin
+const SHADOW_EXTENT: f32 = 6.0;
+const LIGHT_DISTANCE: f32 = 15.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    // towards the light, always normalized
+    pub direction: [f32; 3],
+}
+
+impl Default for DirectionalLight {
+    // the key light lit.wgsl has always used
+    fn default() -> Self {
+        DirectionalLight::new([0.4, 1.0, 0.6])
+    }
+}
+
+impl DirectionalLight {
+    // a zero direction keeps the default light
+    pub fn new(direction: [f32; 3]) -> Self {
+        let d = Vector3::from(direction);
+        if d.magnitude2() < 1e-12 {
+            return DirectionalLight::default();
+        }
+        DirectionalLight { direction: d.normalize().into() }
+    }
+
+    pub fn uniform(&self) -> [f32; 4] {
+        let [x, y, z] = self.direction;
+        [x, y, z, 0.0]
+    }
+
+    // clip space of the shadow map, an orthographic box around the origin seen from the light
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        let direction = Vector3::from(self.direction);
+        let up = if direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+        let eye = Point3::new(0.0, 0.0, 0.0) + direction * LIGHT_DISTANCE;
+        let view = Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), up);
+        let e = SHADOW_EXTENT;
+        // gl's -1..1 clip depth to wgpu's 0..1
+        let depth_to_wgpu = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.5)) * Matrix4::from_nonuniform_scale(1.0, 1.0, 0.5);
+        depth_to_wgpu * cgmath::ortho(-e, e, -e, e, 0.1, LIGHT_DISTANCE * 2.0) * view
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowSettings {
+    // shadow map width and height in texels
+    pub resolution: u32,
+    // subtracted from the receiver depth, plus slope_bias scaled by how steeply the light hits
+    pub depth_bias: f32,
+    pub slope_bias: f32,
+    // 0 is a single hardware filtered tap, 1 a 3x3 kernel, 2 a 5x5 one, up to MAX_PCF_RADIUS
+    pub pcf_radius: u32,
+}
+
+// ground.wgsl takes (2r+1)^2 taps per fragment, past a 9x9 kernel that only costs time
+pub const MAX_PCF_RADIUS: u32 = 4;
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings { resolution: 2048, depth_bias: 0.002, slope_bias: 0.002, pcf_radius: 1 }
+    }
+}
+
+pub const GROUND_COLOR: [f32; 4] = [0.55, 0.57, 0.6, 1.0];
+pub const SHADOW_CATCHER_OPACITY: f32 = 0.6;
+
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Ground {
+    #[default]
+    None,
+    // a lit floor under the cube in this color
+    Plane([f32; 4]),
+    // invisible except for the shadow, which is drawn at this opacity
+    ShadowCatcher(f32),
+}
+
+impl Ground {
+    pub fn material(self) -> Option<Material> {
+        let (base_color, mode, blend) = match self {
+            Ground::None => return None,
+            Ground::Plane(color) => (color, 0.0, BlendMode::Opaque),
+            Ground::ShadowCatcher(opacity) => ([0.0, 0.0, 0.0, opacity], 1.0, BlendMode::AlphaBlend),
+        };
+        Some(Material {
+            shader: MaterialShader::Ground,
+            blend,
+            depth_write: blend == BlendMode::Opaque,
+            cull_mode: Some(wgpu::Face::Back),
+            uniform: MaterialUniform { base_color, params: [mode, 0.3, 0.0, 0.0] },
+            ..Default::default()
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ShadowUniform {
+    light_view_proj: [[f32; 4]; 4],
+    params: [f32; 4],
+}
+
+const SHADOW_UNIFORM_ENTRY: BindGroupLayoutEntry = BindGroupLayoutEntry {
+    binding: 0,
+    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ShadowUniform>() as u64),
+    },
+    count: None,
+};
+
+// group 1 of shadow.wgsl
+pub(crate) const CASTER_BGL_ENTRIES: [BindGroupLayoutEntry; 1] = [SHADOW_UNIFORM_ENTRY];
+
+// group 2 of the shaders that receive shadows
+pub(crate) const SHADOW_BGL_ENTRIES: [BindGroupLayoutEntry; 3] = [
+    SHADOW_UNIFORM_ENTRY,
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+        count: None,
+    },
+];
+
+// the shadow map, the depth only pipeline that fills it and the bind group receivers read it through
+pub struct ShadowMap {
+    pub(crate) bgl: BindGroupLayout,
+    pipeline: RenderPipeline,
+    uniform_buffer: Buffer,
+    caster_bind_group: BindGroup,
+    sampler: wgpu::Sampler,
+    depth_view: TextureView,
+    bind_group: BindGroup,
+    settings: ShadowSettings,
+}
+
+impl ShadowMap {
+    pub fn new(driver: &Driver, object_bgl: &BindGroupLayout, settings: ShadowSettings) -> Self {
+        let bgl = Cube::create_bgl(driver, &SHADOW_BGL_ENTRIES, Some("shadow bgl"));
+        let caster_bgl = Cube::create_bgl(driver, &CASTER_BGL_ENTRIES, Some("shadow caster bgl"));
+        let layout = Cube::create_buffer_render_pipeline_layout(driver, &[object_bgl, &caster_bgl]);
+        let shader = Cube::create_shader_from_source(driver, Some("shadow.wgsl"), include_str!("shaders/shadow.wgsl"));
+        let pipeline = driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::desc()],
+                compilation_options: Default::default(),
+            },
+            // no culling so open meshes and single sided planes still cast
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: None,
+            multiview: None,
+            cache: None,
+        });
+        let uniform_buffer = driver.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow uniform buffer"),
+            size: std::mem::size_of::<ShadowUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let caster_bind_group = Cube::create_bg(driver, &caster_bgl, &[
+            BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+        ], Some("shadow caster bg"));
+        let sampler = driver.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let depth_view = Self::create_depth_view(driver, settings.resolution);
+        let bind_group = Self::create_bind_group(driver, &bgl, &uniform_buffer, &depth_view, &sampler);
+        ShadowMap { bgl, pipeline, uniform_buffer, caster_bind_group, sampler, depth_view, bind_group, settings }
+    }
+
+    fn create_depth_view(driver: &Driver, resolution: u32) -> TextureView {
+        let texture = driver.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size: wgpu::Extent3d { width: resolution, height: resolution, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(driver: &Driver, bgl: &BindGroupLayout, uniform: &Buffer, depth: &TextureView, sampler: &wgpu::Sampler) -> BindGroup {
+        Cube::create_bg(driver, bgl, &[
+            BindGroupEntry { binding: 0, resource: uniform.as_entire_binding() },
+            BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(depth) },
+            BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ], Some("shadow bg"))
+    }
+
+    pub fn settings(&self) -> ShadowSettings {
+        self.settings
+    }
+
+    // only a new resolution reallocates the map, the rest is uniform data
+    pub fn set_settings(&mut self, driver: &Driver, settings: ShadowSettings) {
+        let max = driver.device.limits().max_texture_dimension_2d;
+        let settings = ShadowSettings {
+            resolution: settings.resolution.clamp(64, max),
+            pcf_radius: settings.pcf_radius.min(MAX_PCF_RADIUS),
+            ..settings
+        };
+        if settings.resolution != self.settings.resolution {
+            self.depth_view = Self::create_depth_view(driver, settings.resolution);
+            self.bind_group = Self::create_bind_group(driver, &self.bgl, &self.uniform_buffer, &self.depth_view, &self.sampler);
+        }
+        self.settings = settings;
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    pub fn write_uniform(&self, driver: &Driver, light: &DirectionalLight) {
+        let s = self.settings;
+        let uniform = ShadowUniform {
+            light_view_proj: light.view_proj().into(),
+            params: [s.depth_bias, s.slope_bias, s.pcf_radius as f32, 1.0 / s.resolution as f32],
+        };
+        driver.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    // casters are an object's group 0 bind group and its triangle mesh
+    pub fn draw<'a>(&self, encoder: &mut wgpu::CommandEncoder, casters: impl Iterator<Item = (&'a BindGroup, &'a Mesh)>) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, &self.caster_bind_group, &[]);
+        for (uniform_bg, mesh) in casters {
+            if mesh.vertex_count == 0 {
+                continue;
+            }
+            render_pass.set_bind_group(0, uniform_bg, &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.draw(0..mesh.vertex_count, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    #[test]
+    fn light_box_holds_the_scene_and_orders_depth_towards_the_light() {
+        let light = DirectionalLight::new([0.0, 2.0, 1.0]);
+        let project = |p: [f32; 3]| {
+            let clip = light.view_proj() * Vector4::new(p[0], p[1], p[2], 1.0);
+            clip.truncate() / clip.w
+        };
+        let origin = project([0.0, 0.0, 0.0]);
+        assert!(origin.x.abs() < 1e-5 && origin.y.abs() < 1e-5);
+        assert!(origin.z > 0.0 && origin.z < 1.0);
+        // a point further along the light direction is closer to the light
+        let towards = project(light.direction.map(|c| c * 2.0));
+        assert!(towards.z < origin.z);
+        // straight down still has a usable up vector
+        assert!(DirectionalLight::new([0.0, 1.0, 0.0]).view_proj().x.x.is_finite());
+        assert_eq!(DirectionalLight::new([0.0; 3]), DirectionalLight::default());
+    }
+}
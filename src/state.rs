@@ -1,396 +1,244 @@
-use cgmath::{Matrix4, Point3, Vector3};
-use std::iter;
+use cgmath::{Point3, Vector3};
+use winit::{event::WindowEvent, window::Window};
 
-use cgmath::prelude::*;
-use wgpu::{util::{BufferInitDescriptor, DeviceExt}, BlendComponent, BufferUsages, ColorTargetState, FragmentState};
-use winit::{
-    event::*,
-    event_loop::EventLoop,
-    keyboard::{KeyCode, PhysicalKey},
-    window::{Window, WindowBuilder},
-};
+use crate::{background::{Background, CubemapImage}, benchmark::FrameStats, capture::{CaptureError, CaptureTarget, Screenshot}, cli::{BackgroundOption, Options, Showcase}, controls::Controls, export::{ExportError, ExportSettings, FrameWriter}, input::{Action, InputState}, material::Material, model::load_obj, shader_reload::ShaderWatcher, shadow::ShadowSettings, wasm_driver::Driver, wgpu_helpers::Cube};
 
+const SKYBOX_FACE_SIZE: u32 = 512;
+// what a --model is drawn with
+const MODEL_COLOR: [f32; 4] = [0.8, 0.8, 0.82, 1.0];
+const BENCHMARK_FRAME_MS: f32 = 1000.0 / 60.0;
 
-use crate::{camera::{self, Camera}, wgpu_helpers};
-
-const ANIMATION_SPEED:f32 = 1.0;
-const IS_PERSPECTIVE: bool = true;
-
+// Native front end: owns the window size and hands the driver to the shared cube renderer.
 pub struct State<'a> {
-    #[allow(dead_code)]
-    instance: wgpu::Instance,
-    #[allow(dead_code)]
-    adapter: wgpu::Adapter,
-    surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
+    driver: Driver<'a>,
+    cube: Cube,
+    shader_watcher: Option<ShaderWatcher>,
+    input: InputState,
+    controls: Controls,
+    quit: bool,
+    // where the screenshot action saves
+    screenshot_dir: std::path::PathBuf,
+    // scales the clock handed to update, 0 freezes the animation
+    animation_speed: f32,
     pub size: winit::dpi::PhysicalSize<u32>,
-    clear_color: wgpu::Color,
-    use_color: bool,
-    // The window must be declared after the surface so
-    // it gets dropped after it as the surface contains
-    // unsafe references to the window's resources.
-    window: &'a Window,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    uniform_buffer: wgpu::Buffer,
-    uniform_bind_group: wgpu::BindGroup,
-    model_mat: Matrix4<f32>,
-    view_mat: Matrix4<f32>,
-    project_mat: Matrix4<f32>,
-    //challenge_render_pipeline: wgpu::RenderPipeline,
-    //line_primitive_render_pipeline: wgpu::RenderPipeline
 }
 
 impl<'a> State<'a> {
-    pub async fn new(window: &'a winit::window::Window) -> State<'a> {
-        let size = window.inner_size();
-
-
-        // The instance is a handle to our GPU
-        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::GL,
-            ..Default::default()
-        });
-
-        let surface = unsafe {instance.create_surface(window)}.unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("ERROR IN CREATING ADAPTER");
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    // WebGL doesn't support all of wgpu's features, so if
-                    // we're building for the web we'll have to disable some.
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    memory_hints: Default::default(),
-                },
-                None, // Trace path 
-            ).await
-            .unwrap();
-
-        let surface_caps = surface.get_capabilities(&adapter);
-        // Shader code in this tutorial assumes an Srgb surface texture. Using a different
-        // one will result all the colors comming out darker. If you want to support non
-        // Srgb surfaces, you'll need to account for that when drawing to the frame.
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
+    pub async fn new(window: &'a Window, options: &Options) -> State<'a> {
+        let driver = Driver::native(Some(window), options.backends, window.inner_size(), options.pipeline_cache.clone()).await;
+        Self::with_driver(driver, options)
+    }
 
-        surface.configure(&device, &config);
-        let clear_color = wgpu::Color::BLACK;
-/* 
-    let camera = Camera {
-        // remember build_view_projection_matrix focuses the perspectivev
-        // on the center using the eye as a "pov"
-        // camera is placed one unit "up" and 2 "back"
-        eye: (3.0, 1.5, 3.0).into(),
-        //the origin of the coord-space but really the focus of where the "eye" is looking 
-        target: (1.0, 0.0, 0.0).into(),
-        up: cgmath::Vector3::unit_y(),
-        aspect: config.width as f32 / config.height as f32,
-        fovy: 45.0,
-        znear: 0.1,
-        zfar: 100.0,
-    };
+    // no window and no surface, frames only come out through capture_frame, export and benchmark
+    pub async fn headless(options: &Options) -> State<'static> {
+        let (width, height) = options.offscreen_size();
+        let driver = Driver::native(None, options.backends, winit::dpi::PhysicalSize::new(width, height), options.pipeline_cache.clone()).await;
+        State::with_driver(driver, options)
+    }
 
-    let mut camera_uniform = camera::CameraUniform::new();
-    camera_uniform.update_view_proj(&camera);
-////////////////AHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHHH
-    let camera_buffer = device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor{
-           label: Some("First camera buffer"),
-           contents: bytemuck::cast_slice(&[camera_uniform]),
-           usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    fn with_driver(driver: Driver<'a>, options: &Options) -> State<'a> {
+        let size = driver.size;
+        let mut config = driver.surface_config(size.width.max(1), size.height.max(1));
+        config.present_mode = driver.resolve_present_mode(options.present_mode);
+        config.desired_maximum_frame_latency = options.frame_latency;
+        driver.configure(&config);
+
+        let cam_eye : Point3<f32> = (3.0, 1.5, 3.0).into();
+        let look_dir: Point3<f32> = (0.0,0.0,0.0).into();
+        let up_dir: Vector3<f32> = cgmath::Vector3::unit_y();
+        let mut cube = Cube::new(&driver, &config, cam_eye, look_dir, up_dir, options.projection);
+        cube.set_msaa(&driver, options.msaa);
+        cube.set_projection_settings(&driver, options.projection_settings);
+        if let Some(path) = &options.model {
+            match load_obj(path) {
+                // white vertex colors would come out flat unlit, a matte material shows the shape
+                Ok(vertices) => {
+                    cube.set_main_mesh(&driver, &vertices);
+                    let material = Material::pbr(MODEL_COLOR, 0.0, 0.5).with_culling(options.model_cull, options.model_front_face);
+                    cube.set_main_material(&driver, material);
+                }
+                Err(e) => log::error!("could not load {}: {e}", path.display()),
+            }
+        }
+        // in a fixed order, glass turns the main cube into glass after the others are placed
+        for showcase in Showcase::ALL.into_iter().filter(|s| options.showcases.contains(s)) {
+            match showcase {
+                Showcase::Materials => cube.add_material_showcase(&driver),
+                Showcase::Primitives => cube.add_primitive_showcase(&driver),
+                Showcase::Pbr => cube.add_pbr_showcase(&driver),
+                Showcase::Glass => cube.add_glass_scene(&driver),
+            }
         }
-    );
-*/
-    let cam_eye : Point3<f32> = (3.0, 1.5, 3.0).into();
-    let look_dir: Point3<f32> = (0.0,0.0,0.0).into();
-    let up_dir: Vector3<f32> = cgmath::Vector3::unit_y();
-
-    let model_mat = wgpu_helpers::create_transforms([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
-    let (view_mat, project_mat, view_project_mat) = wgpu_helpers::create_view_projection(cam_eye, look_dir, up_dir, config.width as f32/ config.height as f32, IS_PERSPECTIVE);
-    let mvp_mat = view_project_mat * model_mat;
-
-    let mvp_ref: &[f32; 16] = mvp_mat.as_ref();
-
-    let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("uniform buffer"),
-        contents: bytemuck::cast_slice(mvp_ref),
-        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-    });
-    
-    let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{
-        label: Some("uniform bind group layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::VERTEX,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-                },
-            count: None,
-        }]
-    });
-
-    let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor{
-        label:  Some("uniform buffer layout"),
-        layout: &uniform_bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-           binding: 0,
-           resource: uniform_buffer.as_entire_binding()
-        }],
-    });
-/* 
-    let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-        label: Some("uniform buffer"),
-        contents: bytemuck::cast_slice(mvp_ref),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
-    });
-
-*/
-    let buffer_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor { 
-        label: Some("shader"), 
-        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
-     });
-
-     let buffer_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: Some("pipeline_layout"),
-        bind_group_layouts: &[&uniform_bind_group_layout],
-        push_constant_ranges: &[]
-    });
 
-    let buffer_render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor{
-        label: Some("buffer pipeline"),
-        layout: Some(&buffer_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &buffer_shader,
-             entry_point: Some("vs_main"),
-               buffers: &[wgpu_helpers::Vertex::desc()],
-            compilation_options: Default::default(),
+        let has_skybox = match &options.background {
+            Some(BackgroundOption::Gradient) => {
+                cube.set_background(Background::gradient());
+                false
+            }
+            Some(BackgroundOption::Stars) => {
+                cube.set_background(Background::starfield());
+                false
+            }
+            // the path was there when the options were parsed, it can still be unreadable
+            Some(BackgroundOption::Skybox(path)) => match CubemapImage::load(path, SKYBOX_FACE_SIZE, driver.device.limits().max_texture_dimension_2d) {
+                Ok(cubemap) => {
+                    cube.set_skybox(&driver, &cubemap);
+                    true
+                }
+                Err(e) => {
+                    log::error!("could not load skybox {}: {e}", path.display());
+                    false
+                }
             },
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            ..Default::default()
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth24Plus,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::LessEqual,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState::default(),
-        fragment: Some(FragmentState {
-            module: &buffer_shader,
-            entry_point: Some("fs_main"),
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-            targets: &[Some(ColorTargetState {
-                format: config.format,
-                blend: Some(wgpu::BlendState {
-                        color: BlendComponent::REPLACE,
-                        alpha: BlendComponent::REPLACE,
-                    }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        multiview: None,
-        cache: None,
-    });
-
-    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor{
-        label: Some("First vertex buffer"),
-        contents: bytemuck::cast_slice(&State::create_vertices()),
-        usage: BufferUsages::VERTEX,
-    });
+            None => false,
+        };
 
+        cube.set_ground(&driver, options.ground);
+        if let Some(resolution) = options.shadow_resolution {
+            cube.set_shadow_settings(&driver, ShadowSettings { resolution, ..cube.shadow_settings() });
+        }
+        cube.set_post_settings(&driver, options.post);
 
-    
+        let shader_watcher = options.watch_shaders.as_deref().and_then(|dir| {
+            ShaderWatcher::new(dir)
+                .inspect(|_| log::info!("watching {} for shader changes", dir.display()))
+                .inspect_err(|e| log::error!("could not watch {}: {e}", dir.display()))
+                .ok()
+        });
 
         Self {
-            surface,
-            device,
-            queue,
-            config,
+            driver,
+            cube,
+            shader_watcher,
+            input: InputState::new(options.bindings.clone()),
+            controls: Controls::new(has_skybox),
+            quit: false,
+            screenshot_dir: options.screenshot_dir.clone(),
+            animation_speed: options.speed,
             size,
-            window : window,
-            instance,
-            adapter,
-            clear_color,
-            render_pipeline: buffer_render_pipeline,
-            use_color: true,
-            vertex_buffer,
-            uniform_buffer,
-            uniform_bind_group,
-            model_mat,
-            view_mat,
-            project_mat,
- 
         }
     }
 
-    fn window(&self) -> &Window {
-        &self.window
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.cube.set_pixel_ratio(&self.driver, scale_factor as f32);
     }
 
-    pub fn create_vertices() -> Vec<[f32; 3]> {
-    vec![
-        [-0.5, -0.5, 0.0], // Bottom-left
-        [0.5, -0.5, 0.0],  // Bottom-right
-        [0.0, 0.5, 0.0],   // Top-center
-    ]
-}
-
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.project_mat = wgpu_helpers::create_projection((new_size.width/new_size.height) as f32, IS_PERSPECTIVE);
-            let mvp_mat = self.project_mat * self.view_mat * self.model_mat;
-            let mvp_ref: &[f32; 16] = mvp_mat.as_ref();
-            self.queue.write_buffer(&self.uniform_buffer,0,bytemuck::cast_slice(mvp_ref));
+            self.cube.resize(&self.driver, new_size.width, new_size.height);
         }
     }
 
-    #[allow(unused_variables)]
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        
-        match event {
-            WindowEvent::CursorMoved { position,.. } => {
-                println!("SOMETHING HAOPPEND");
-                self.clear_color = wgpu::Color::BLUE; /*wgpu::Color {
-                 r: position.x as f64 / self.size.width as f64,
-                    g: position.y as f64 / self.size.height as f64,
-                    b: 1.0,
-                    a: 1.0,
-                };
-                */
-                true
-            },
-            WindowEvent::KeyboardInput { device_id, event: KeyEvent {
-                 physical_key: PhysicalKey::Code(KeyCode::Space),
-                  state,
-                   .. },
-                    .. } => {
-                        self.use_color = *state == ElementState::Released;
-                        true
+    // true when the event changed what is on screen, the keys are in Bindings::default and --bind
+    pub fn input(&mut self, event: &WindowEvent, now_ms: f64) -> bool {
+        if !self.input.handle_event(event, now_ms) {
+            return false;
+        }
+        let mut changed = self.update_hover();
+        changed |= self.controls.zoom(&mut self.cube, &self.driver, self.input.take_zoom());
+        for action in self.input.take_actions() {
+            match action {
+                Action::Select => {
+                    if let Some(face) = self.cube.hovered_face() {
+                        log::info!("clicked the {} face", face.name());
                     }
-            _ => false
+                }
+                Action::Screenshot => {
+                    let stamp = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |d| d.as_millis());
+                    self.save_screenshot(self.screenshot_dir.join(format!("cube-{stamp}.png")));
+                }
+                Action::Quit => self.quit = true,
+                action => changed |= self.controls.perform(&mut self.cube, &self.driver, action),
+            }
         }
+        changed
     }
 
-    pub fn update(&mut self, dur: std::time::Duration) {
-        let dur = ANIMATION_SPEED * dur.as_secs_f32();
-        let transf_mat =    wgpu_helpers::create_transforms(
-            [0.0, 0.0, 0.0],
-             [dur.sin(), dur.cos(), 0.0],
-              [1.0,1.0, 1.0]);
-        let mvp_mat = self.project_mat * self.view_mat * transf_mat;
-        let mvp_ref: &[f32; 16] = mvp_mat.as_ref();
-        self.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(mvp_ref));
-
+    // a quit action came in, the window should close
+    pub fn quit_requested(&self) -> bool {
+        self.quit
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("depth texture"),
-            size: wgpu::Extent3d {
-                width: self.config.width,
-                height: self.config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format:wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
+    pub fn save_screenshot(&self, path: std::path::PathBuf) {
+        match self.capture_frame().and_then(|shot| shot.save_png(&path)) {
+            Ok(()) => log::info!("saved {}", path.display()),
+            Err(e) => log::error!("could not save {}: {e}", path.display()),
+        }
+    }
 
+    // draws the current frame offscreen and blocks until it is read back
+    pub fn capture_frame(&self) -> Result<Screenshot, CaptureError> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.cube.capture(&self.driver, move |shot| {
+            let _ = sender.send(shot);
         });
+        let _ = self.driver.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().expect("waiting on the device runs the capture callback")
+    }
 
-        let depth_view = depth_texture.create_view( &wgpu::TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {r: 0.05, g:0.062, b:0.08, a:1.0}),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+    // renders every frame of the export at its fixed time, independent of how long drawing takes
+    pub fn export(&mut self, settings: &ExportSettings) -> Result<(), ExportError> {
+        self.cube.resize_targets(&self.driver, settings.width, settings.height);
+        let mut writer = FrameWriter::create(settings)?;
+        let frames = settings.frame_count();
+        for frame in 0..frames {
+            self.cube.update_cube_render(&self.driver, settings.frame_time_ms(frame) * self.animation_speed);
+            writer.write(&self.capture_frame()?)?;
+            log::info!("frame {}/{frames}", frame + 1);
+        }
+        writer.finish()
+    }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-            render_pass.draw(0..36,0..1);
+    // draws offscreen at a fixed 60 fps timestep and waits for the gpu after every frame
+    pub fn benchmark(&mut self, frames: u32) -> Result<FrameStats, CaptureError> {
+        let target = CaptureTarget::new(&self.driver, self.cube.config.format, self.cube.config.width, self.cube.config.height)?;
+        let mut times = Vec::with_capacity(frames as usize);
+        for frame in 0..frames {
+            let start = std::time::Instant::now();
+            self.cube.update_cube_render(&self.driver, frame as f32 * BENCHMARK_FRAME_MS * self.animation_speed);
+            self.cube.draw(&self.driver, &target.view);
+            let _ = self.driver.device.poll(wgpu::Maintain::Wait);
+            times.push(start.elapsed());
         }
+        Ok(FrameStats::from_times(&times).expect("benchmarks draw at least one frame"))
+    }
 
-        self.queue.submit(iter::once(encoder.finish()));
-        output.present();
+    // true while a projection switch still needs frames, even with the animation paused
+    pub fn advance_projection(&mut self, now_ms: f64) -> bool {
+        self.cube.advance_projection(&self.driver, now_ms)
+    }
 
-        Ok(())
+    pub fn update(&mut self, dur: std::time::Duration) {
+        self.reload_shaders();
+        self.cube.update_cube_render(&self.driver, dur.as_secs_f32() * 1000.0 * self.animation_speed);
+        // the cube turns under a still pointer too
+        self.update_hover();
     }
 
-}
\ No newline at end of file
+    // true when the hovered face changed
+    fn update_hover(&mut self) -> bool {
+        let face = self.input.cursor().and_then(|cursor| self.cube.face_at(cursor));
+        self.cube.set_hovered_face(&self.driver, face)
+    }
+
+    // compile errors are reported and the previous pipelines keep drawing
+    fn reload_shaders(&mut self) {
+        let Some(watcher) = &self.shader_watcher else { return };
+        for (name, checked) in watcher.poll() {
+            match checked {
+                Ok(source) => match self.cube.reload_shader(&self.driver, &name, &source) {
+                    Ok(()) => log::info!("{name} reloaded"),
+                    Err(e) => log::error!("{name} rejected by wgpu: {e}"),
+                },
+                Err(e) => log::error!("shader compile error: {e}"),
+            }
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.cube.render(&self.driver)
+    }
+}
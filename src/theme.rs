@@ -0,0 +1,209 @@
+// Face colors for the main cube. A theme fills each of the six faces with a solid
+// color or a gradient between its four corners, and is baked into the vertex colors
+// so changing it is a buffer write, the pipelines never notice.
+// Float colors are linear, hex strings are sRGB like everywhere else on the web.
+use std::fmt;
+
+use crate::wgpu_helpers::{Cube, Vertex};
+
+// in the order cube_data lays the faces out, six vertices each
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Right,
+    Back,
+    Left,
+    Top,
+    Bottom,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Front, Face::Right, Face::Back, Face::Left, Face::Top, Face::Bottom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Face::Front => "front",
+            Face::Right => "right",
+            Face::Back => "back",
+            Face::Left => "left",
+            Face::Top => "top",
+            Face::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, ThemeError> {
+        Self::ALL.into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| ThemeError::UnknownFace(name.to_string()))
+    }
+
+    pub fn from_index(index: usize) -> Result<Self, ThemeError> {
+        Self::ALL.get(index).copied().ok_or_else(|| ThemeError::UnknownFace(index.to_string()))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FaceFill {
+    Solid([f32; 4]),
+    // corners at uv (0,0), (1,0), (1,1), (0,1), blended bilinearly across the face
+    Gradient([[f32; 4]; 4]),
+}
+
+impl FaceFill {
+    pub fn color_at(&self, uv: [f32; 2]) -> [f32; 4] {
+        match self {
+            FaceFill::Solid(color) => *color,
+            FaceFill::Gradient([c00, c10, c11, c01]) => {
+                let [u, v] = uv;
+                std::array::from_fn(|i| {
+                    let bottom = c00[i] + (c10[i] - c00[i]) * u;
+                    let top = c01[i] + (c11[i] - c01[i]) * u;
+                    bottom + (top - bottom) * v
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeError {
+    BadColor(String),
+    // gradients take exactly four corner colors
+    BadGradient(usize),
+    UnknownFace(String),
+    UnknownTheme(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::BadColor(s) => write!(f, "{s:?} is not a #rgb, #rrggbb or #rrggbbaa color"),
+            ThemeError::BadGradient(n) => write!(f, "a gradient needs 4 corner colors, got {n}"),
+            ThemeError::UnknownFace(s) => write!(f, "no face {s:?}, expected one of front/right/back/left/top/bottom or 0-5"),
+            ThemeError::UnknownTheme(s) => write!(f, "no theme {s:?}, expected one of {}", CubeTheme::NAMES.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+// "#rgb", "#rrggbb" or "#rrggbbaa" (the # is optional) to a linear rgba color
+pub fn parse_hex(hex: &str) -> Result<[f32; 4], ThemeError> {
+    let bad = || ThemeError::BadColor(hex.to_string());
+    let digits = hex.trim().trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad());
+    }
+    let channel = |i: usize, width: usize| {
+        let v = u8::from_str_radix(&digits[i * width..(i + 1) * width], 16).unwrap_or(0);
+        if width == 1 { v * 17 } else { v }
+    };
+    let bytes = match digits.len() {
+        3 => [channel(0, 1), channel(1, 1), channel(2, 1), 255],
+        6 => [channel(0, 2), channel(1, 2), channel(2, 2), 255],
+        8 => [channel(0, 2), channel(1, 2), channel(2, 2), channel(3, 2)],
+        _ => return Err(bad()),
+    };
+    let unit = |b: u8| b as f32 / 255.0;
+    Ok([srgb_to_linear(unit(bytes[0])), srgb_to_linear(unit(bytes[1])), srgb_to_linear(unit(bytes[2])), unit(bytes[3])])
+}
+
+fn hex(color: &str) -> [f32; 4] {
+    parse_hex(color).expect("built in theme colors are valid")
+}
+
+fn solid(colors: [&str; 6]) -> [FaceFill; 6] {
+    colors.map(|c| FaceFill::Solid(hex(c)))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CubeTheme {
+    pub faces: [FaceFill; 6],
+}
+
+impl Default for CubeTheme {
+    fn default() -> Self {
+        CubeTheme::classic()
+    }
+}
+
+impl CubeTheme {
+    pub const NAMES: [&'static str; 6] = ["classic", "pastel", "mono", "neon", "sunset", "ocean"];
+
+    // the saturated primaries the cube has always had
+    pub fn classic() -> Self {
+        CubeTheme {
+            faces: [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 1.0]]
+                .map(|[r, g, b]| FaceFill::Solid([r, g, b, 1.0])),
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self, ThemeError> {
+        let faces = match name.to_ascii_lowercase().as_str() {
+            "classic" => return Ok(CubeTheme::classic()),
+            "pastel" => solid(["#a7c7e7", "#f4a7b9", "#fdfd96", "#b5ead7", "#c7ceea", "#ffdac1"]),
+            "mono" => solid(["#f2f2f2", "#cccccc", "#a6a6a6", "#808080", "#e0e0e0", "#5a5a5a"]),
+            "neon" => solid(["#00f0ff", "#ff2bd6", "#faff00", "#39ff14", "#ff6b00", "#8a2be2"]),
+            "sunset" => {
+                let face = FaceFill::Gradient([hex("#ff5e62"), hex("#ff5e62"), hex("#ffc371"), hex("#ffc371")]);
+                [face, face, face, face, FaceFill::Solid(hex("#ffc371")), FaceFill::Solid(hex("#6a3093"))]
+            }
+            "ocean" => {
+                let face = FaceFill::Gradient([hex("#0b3d91"), hex("#1565c0"), hex("#4dd0e1"), hex("#26a69a")]);
+                [face, face, face, face, FaceFill::Solid(hex("#b2ebf2")), FaceFill::Solid(hex("#062a63"))]
+            }
+            _ => return Err(ThemeError::UnknownTheme(name.to_string())),
+        };
+        Ok(CubeTheme { faces })
+    }
+
+    pub fn with_face(mut self, face: Face, fill: FaceFill) -> Self {
+        self.faces[face as usize] = fill;
+        self
+    }
+
+    // the cube mesh with every vertex colored from its face's fill at its uv
+    pub fn cube_vertices(&self) -> Vec<Vertex> {
+        let mut vertices = Cube::create_cube_vertices();
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            vertex.color = self.faces[i / 6].color_at(vertex.uv);
+        }
+        vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic_theme_matches_the_original_cube() {
+        assert_eq!(CubeTheme::classic().cube_vertices(), Cube::create_cube_vertices());
+        for name in CubeTheme::NAMES {
+            assert_eq!(CubeTheme::named(name).unwrap().cube_vertices().len(), 36);
+        }
+        assert!(CubeTheme::named("plaid").is_err());
+    }
+
+    #[test]
+    fn hex_colors_are_converted_to_linear() {
+        assert_eq!(parse_hex("#fff"), Ok([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(parse_hex("000000"), Ok([0.0, 0.0, 0.0, 1.0]));
+        let [r, g, b, a] = parse_hex("#80ff0040").unwrap();
+        assert!((r - 0.2158).abs() < 1.0e-3 && g == 1.0 && b == 0.0 && (a - 0.251).abs() < 1.0e-3);
+        assert!(parse_hex("#12345").is_err());
+        assert!(parse_hex("#ggg").is_err());
+    }
+
+    #[test]
+    fn gradients_blend_between_corners() {
+        let fill = FaceFill::Gradient([[0.0; 4], [1.0, 0.0, 0.0, 1.0], [1.0; 4], [0.0, 0.0, 1.0, 1.0]]);
+        assert_eq!(fill.color_at([0.0, 0.0]), [0.0; 4]);
+        assert_eq!(fill.color_at([1.0, 1.0]), [1.0; 4]);
+        assert_eq!(fill.color_at([0.5, 0.5]), [0.5, 0.25, 0.5, 0.75]);
+    }
+}
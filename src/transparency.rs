@@ -0,0 +1,218 @@
+// Blended materials are either sorted back to front and drawn over the opaque pass,
+// or accumulated order independently into two offscreen targets that a fullscreen
+// pass then resolves over the frame (weighted blended OIT).
+use cgmath::{Matrix4, Vector4};
+use wgpu::{BindGroup, BindGroupLayoutEntry, RenderPipeline, TextureFormat, TextureView};
+
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::Cube;
+
+pub const OIT_ACCUM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+pub const OIT_REVEAL_FORMAT: TextureFormat = TextureFormat::R16Float;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TransparencyMode {
+    // exact for non overlapping objects, two sided materials draw their back faces first
+    #[default]
+    Sorted,
+    // no sorting at all, approximate but stable for intersecting glass
+    WeightedBlended,
+}
+
+// the ways one material gets drawn within a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DrawPass {
+    // the material's own cull mode, everything opaque only ever draws this
+    Single,
+    // two sided blended materials, the inside first and the outside over it
+    BackFaces,
+    FrontFaces,
+    // into the OitTargets instead of the surface
+    OitAccumulate,
+}
+
+// accumulation sums premultiplied color and alpha, revealage multiplies in (1 - alpha)
+pub const OIT_ACCUM_BLEND: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+pub const OIT_REVEAL_BLEND: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+pub(crate) const COMPOSITE_BGL_ENTRIES: [BindGroupLayoutEntry; 2] = [
+    BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+    BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    },
+];
+
+// view space depth of the object's origin, larger is further from the camera
+pub fn view_depth(view_mat: Matrix4<f32>, model_mat: Matrix4<f32>) -> f32 {
+    let origin = view_mat * model_mat * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    -origin.z
+}
+
+// the offscreen targets and resolve pipeline, created the first time OIT is switched on
+pub struct OitTargets {
+    pub accum_view: TextureView,
+    pub reveal_view: TextureView,
+    composite_bgl: wgpu::BindGroupLayout,
+    composite_bg: BindGroup,
+    composite_pipeline: RenderPipeline,
+}
+
+impl OitTargets {
+    pub fn new(driver: &Driver, color_format: TextureFormat, width: u32, height: u32) -> Self {
+        let composite_bgl = Cube::create_bgl(driver, &COMPOSITE_BGL_ENTRIES, Some("oit composite bgl"));
+        let layout = Cube::create_buffer_render_pipeline_layout(driver, &[&composite_bgl]);
+        let shader = Cube::create_shader_from_source(driver, Some("oit_composite.wgsl"), include_str!("shaders/oit_composite.wgsl"));
+        let composite_pipeline = driver.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("oit composite pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            dst_factor: wgpu::BlendFactor::SrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::OVER,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let (accum_view, reveal_view) = Self::create_views(driver, width, height);
+        let composite_bg = Self::create_bind_group(driver, &composite_bgl, &accum_view, &reveal_view);
+        OitTargets {
+            accum_view,
+            reveal_view,
+            composite_bgl,
+            composite_bg,
+            composite_pipeline,
+        }
+    }
+
+    fn create_views(driver: &Driver, width: u32, height: u32) -> (TextureView, TextureView) {
+        let create = |label, format| {
+            driver.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }).create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        (create("oit accum", OIT_ACCUM_FORMAT), create("oit reveal", OIT_REVEAL_FORMAT))
+    }
+
+    fn create_bind_group(driver: &Driver, layout: &wgpu::BindGroupLayout, accum: &TextureView, reveal: &TextureView) -> BindGroup {
+        Cube::create_bg(driver, layout, &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(accum) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(reveal) },
+        ], Some("oit composite bg"))
+    }
+
+    pub fn resize(&mut self, driver: &Driver, width: u32, height: u32) {
+        let (accum_view, reveal_view) = Self::create_views(driver, width, height);
+        self.composite_bg = Self::create_bind_group(driver, &self.composite_bgl, &accum_view, &reveal_view);
+        self.accum_view = accum_view;
+        self.reveal_view = reveal_view;
+    }
+
+    // attachments for the accumulation pass, cleared to "nothing drawn yet"
+    pub fn color_attachments(&self) -> [Option<wgpu::RenderPassColorAttachment<'_>>; 2] {
+        [
+            Some(wgpu::RenderPassColorAttachment {
+                view: &self.accum_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+            Some(wgpu::RenderPassColorAttachment {
+                view: &self.reveal_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+        ]
+    }
+
+    pub fn composite(&self, encoder: &mut wgpu::CommandEncoder, target: &TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("oit composite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.composite_pipeline);
+        pass.set_bind_group(0, &self.composite_bg, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
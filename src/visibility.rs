@@ -0,0 +1,56 @@
+// Whether the canvas can be seen at all, so the web loop can stop drawing while it can't.
+// An IntersectionObserver tracks the canvas scrolling out of the viewport and the document's
+// visibilitychange event tracks the tab being hidden. Either change wakes the event loop.
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{Document, Element, IntersectionObserver, IntersectionObserverEntry};
+use winit::event_loop::EventLoopProxy;
+
+pub struct Visibility {
+    on_screen: Rc<Cell<bool>>,
+    document: Document,
+    observer: IntersectionObserver,
+    _on_intersect: Closure<dyn FnMut(js_sys::Array)>,
+    on_visibility_change: Closure<dyn FnMut()>,
+}
+
+impl Visibility {
+    pub fn watch(element: &Element, waker: EventLoopProxy<()>) -> Result<Self, JsValue> {
+        let document = web_sys::window().and_then(|w| w.document()).ok_or("no document")?;
+
+        // assume visible until the observer's first callback says otherwise
+        let on_screen = Rc::new(Cell::new(true));
+        let on_intersect = {
+            let (on_screen, waker) = (on_screen.clone(), waker.clone());
+            Closure::<dyn FnMut(js_sys::Array)>::new(move |entries: js_sys::Array| {
+                // entries are in order, the last one is the current state
+                if let Some(entry) = entries.iter().last() {
+                    on_screen.set(entry.unchecked_into::<IntersectionObserverEntry>().is_intersecting());
+                    let _ = waker.send_event(());
+                }
+            })
+        };
+        let observer = IntersectionObserver::new(on_intersect.as_ref().unchecked_ref())?;
+        observer.observe(element);
+
+        let on_visibility_change = Closure::<dyn FnMut()>::new(move || {
+            let _ = waker.send_event(());
+        });
+        document.add_event_listener_with_callback("visibilitychange", on_visibility_change.as_ref().unchecked_ref())?;
+
+        Ok(Visibility { on_screen, document, observer, _on_intersect: on_intersect, on_visibility_change })
+    }
+
+    pub fn visible(&self) -> bool {
+        self.on_screen.get() && !self.document.hidden()
+    }
+}
+
+impl Drop for Visibility {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+        let _ = self.document.remove_event_listener_with_callback("visibilitychange", self.on_visibility_change.as_ref().unchecked_ref());
+    }
+}
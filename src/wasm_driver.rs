@@ -1,15 +1,22 @@
-use std::io::Error;
+use web_sys::HtmlCanvasElement;
+use wgpu::{SurfaceConfiguration, SurfaceTarget};
 
-use bytemuck::Contiguous;
-use web_sys::{console, HtmlCanvasElement};
-use wgpu::{ rwh::HasWindowHandle, Surface, SurfaceTarget};
-use winit::raw_window_handle;
+// frames the cpu may queue ahead of the gpu, lower trades throughput for input latency
+pub const DEFAULT_FRAME_LATENCY: u32 = 2;
+// used when there is no surface to ask, everything is drawn offscreen then
+const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// Owns the gpu handles shared by the native and web front ends.
+// The surface borrows the window, so the window has to outlive the driver.
+// Headless drivers have no surface and can only draw into offscreen targets.
 pub struct Driver<'a>{
-     pub size: winit::dpi::PhysicalSize<u32>,
-    pub surface: wgpu::Surface<'a>,
+    pub size: winit::dpi::PhysicalSize<u32>,
+    pub surface: Option<wgpu::Surface<'a>>,
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    // where PipelineCache keeps the driver's compiled pipelines, None for the temp directory
+    pub pipeline_cache_dir: Option<std::path::PathBuf>,
 }
 
 impl<'a> Driver<'a>{
@@ -17,31 +24,49 @@ impl<'a> Driver<'a>{
         let size = window.inner_size();
         let instance = wgpu::Instance::default();
 
- console::log_1(&"before new surface".into());
+        log::info!("before new surface");
         #[cfg(target_arch="wasm32")]
         let surface = {
-            if canvas.is_some(){
-                let surface_target = wgpu::SurfaceTarget::Canvas(canvas.unwrap());
+            if let Some(canvas) = canvas {
+                let surface_target = wgpu::SurfaceTarget::Canvas(canvas);
                 instance.create_surface(surface_target).expect("Failed to create surface for wasm32 target")
             }
             else{
                 let surface_target = SurfaceTarget::from(window);
                 instance.create_surface(surface_target).expect("could not create surface with native target")
             }
-};
+        };
 
         #[cfg(not(target_arch="wasm32"))]
         let surface = {
+            // canvases only exist on the web
+            let _ = canvas;
             let surface_target = SurfaceTarget::from(window);
             instance.create_surface(surface_target).expect("could not create surface with native target")
         };
-         console::log_1(&"create surface".into());
+        log::info!("create surface");
+        Self::with_surface(&instance, Some(surface), size).await
+    }
+
+    // native only: any mix of backends, with a window or headless at the given size
+    #[cfg(not(target_arch="wasm32"))]
+    pub async fn native(window: Option<&'a winit::window::Window>, backends: wgpu::Backends, size: winit::dpi::PhysicalSize<u32>, pipeline_cache_dir: Option<std::path::PathBuf>) -> Driver<'a>{
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends, ..Default::default() });
+        let surface = window.map(|window| {
+            instance.create_surface(SurfaceTarget::from(window)).expect("could not create surface with native target")
+        });
+        let size = window.map_or(size, |window| window.inner_size());
+        Driver { pipeline_cache_dir, ..Self::with_surface(&instance, surface, size).await }
+    }
+
+    async fn with_surface(instance: &wgpu::Instance, surface: Option<wgpu::Surface<'a>>, size: winit::dpi::PhysicalSize<u32>) -> Driver<'a>{
         let adapter = instance.
         request_adapter( &wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
             force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            compatible_surface: surface.as_ref(),
         }).await.expect("Failed to find an appropriate adapter");
+        log::info!("adapter: {:?}", adapter.get_info());
         let (device, queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
@@ -54,13 +79,68 @@ impl<'a> Driver<'a>{
         )
         .await
         .expect("Failed to create device");
-        console::log_1(&"driver created".into());
-    Self{ 
-        size, 
-        surface, 
-        adapter, 
-        device, 
-        queue
+        log::info!("driver created");
+        Self{
+            size,
+            surface,
+            adapter,
+            device,
+            queue,
+            pipeline_cache_dir: None,
+        }
     }
+
+    // builds the surface config both front ends start from
+    // prefers an srgb format since the shader writes linear colors, vsync and an opaque surface
+    pub fn surface_config(&self, width: u32, height: u32) -> SurfaceConfiguration {
+        let Some(surface) = &self.surface else {
+            return SurfaceConfiguration{
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: HEADLESS_FORMAT,
+                width,
+                height,
+                present_mode: wgpu::PresentMode::Fifo,
+                desired_maximum_frame_latency: DEFAULT_FRAME_LATENCY,
+                alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                view_formats: vec![],
+            };
+        };
+        let surface_capabilities = surface.get_capabilities(&self.adapter);
+        let surface_format = surface_capabilities.formats.iter().copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_capabilities.formats[0]);
+        SurfaceConfiguration{
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            desired_maximum_frame_latency: DEFAULT_FRAME_LATENCY,
+            alpha_mode: [wgpu::CompositeAlphaMode::Opaque, wgpu::CompositeAlphaMode::Auto].into_iter()
+                .find(|mode| surface_capabilities.alpha_modes.contains(mode))
+                .unwrap_or(surface_capabilities.alpha_modes[0]),
+            view_formats: vec![],
+        }
+    }
+
+    // a no-op for headless drivers
+    pub fn configure(&self, config: &SurfaceConfiguration) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, config);
+        }
     }
-    }   
+
+    // the auto modes always resolve to something the surface has, the others fall back to fifo
+    // which every surface supports
+    pub fn resolve_present_mode(&self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        use wgpu::PresentMode::{AutoNoVsync, AutoVsync, Fifo};
+        let supported = self.surface.as_ref()
+            .is_some_and(|surface| surface.get_capabilities(&self.adapter).present_modes.contains(&mode));
+        if supported || matches!(mode, AutoVsync | AutoNoVsync) {
+            mode
+        } else {
+            log::warn!("present mode {mode:?} is not supported, using {Fifo:?}");
+            Fifo
+        }
+    }
+}
@@ -0,0 +1,511 @@
+// The object `run` hands back to JS. Calls on it only queue commands, the web event
+// loop owns the renderer and applies whatever is queued each time it wakes, drawn or not.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use cgmath::{Deg, Rad};
+use wasm_bindgen::prelude::*;
+use winit::event_loop::EventLoopProxy;
+
+use crate::background::{Background, CubemapImage, NEBULA_COLOR, NEBULA_GLOW};
+use crate::effects::{EffectKind, FaceEffect};
+use crate::input::{Action, Trigger};
+use crate::material::Material;
+use crate::pacing::{present_mode_from_name, PRESENT_MODE_NAMES};
+use crate::post::{DepthOfField, Fog, PostSettings, Ssao};
+use crate::projection::{AspectFit, Projection, ProjectionSettings};
+use crate::shadow::{DirectionalLight, Ground, ShadowSettings, GROUND_COLOR, SHADOW_CATCHER_OPACITY};
+use crate::theme::{parse_hex, CubeTheme, Face, FaceFill, ThemeError};
+use crate::wasm_driver::Driver;
+use crate::wgpu_helpers::Cube;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CubeCommand {
+    Theme(Box<CubeTheme>),
+    Face(Face, FaceFill),
+    // None turns the procedural effect off
+    Effect(Option<EffectKind>),
+    EffectParams { speed: f32, scale: f32, intensity: f32 },
+    EffectTint([f32; 4]),
+    Background(Background),
+    Skybox(Box<CubemapImage>),
+    MainMaterial(Material),
+    Light(DirectionalLight),
+    Ground(Ground),
+    // fields left as None keep their current value
+    Ssao(Option<Ssao>),
+    DepthOfField(Option<DepthOfField>),
+    Fog(Option<Fog>),
+    AspectFit(AspectFit),
+    Projection(Projection),
+    FieldOfView(Rad<f32>),
+    ClipPlanes { near: f32, far: f32 },
+    PresentMode(wgpu::PresentMode),
+    FrameLatency(u32),
+    // these change the event loop rather than the cube, run_wasm takes them out before apply
+    FrameCap(Option<f32>),
+    Idle(bool),
+    // None lets the backing store follow devicePixelRatio all the way
+    MaxPixelRatio(Option<f64>),
+    // None unbinds the trigger
+    Bind(Trigger, Option<Action>),
+    // settled with the png bytes once the frame has been read back
+    Screenshot { resolve: js_sys::Function, reject: js_sys::Function },
+    Shadows { resolution: Option<u32>, depth_bias: Option<f32>, slope_bias: Option<f32>, pcf_radius: Option<u32> },
+}
+
+impl CubeCommand {
+    pub fn apply(self, cube: &mut Cube, driver: &Driver) {
+        match self {
+            CubeCommand::Theme(theme) => cube.set_theme(driver, *theme),
+            CubeCommand::Face(face, fill) => cube.set_face(driver, face, fill),
+            CubeCommand::Effect(kind) => cube.set_effect(driver, kind.map(FaceEffect::new)),
+            // tweaks without an effect on have nothing to change
+            CubeCommand::EffectParams { speed, scale, intensity } => {
+                if let Some(effect) = cube.effect() {
+                    cube.set_effect(driver, Some(FaceEffect { speed, scale, intensity, ..effect }));
+                }
+            }
+            CubeCommand::Background(background) => cube.set_background(background),
+            CubeCommand::Skybox(cubemap) => cube.set_skybox(driver, &cubemap),
+            CubeCommand::MainMaterial(material) => cube.set_main_material(driver, material),
+            CubeCommand::Light(light) => cube.set_light(driver, light),
+            CubeCommand::Ground(ground) => cube.set_ground(driver, ground),
+            CubeCommand::Ssao(ssao) => cube.set_post_settings(driver, PostSettings { ssao, ..cube.post_settings() }),
+            CubeCommand::DepthOfField(dof) => cube.set_post_settings(driver, PostSettings { dof, ..cube.post_settings() }),
+            CubeCommand::Fog(fog) => cube.set_post_settings(driver, PostSettings { fog, ..cube.post_settings() }),
+            CubeCommand::AspectFit(fit) => cube.set_aspect_fit(driver, fit),
+            CubeCommand::Projection(projection) => cube.set_projection(driver, projection),
+            CubeCommand::FieldOfView(fov_y) => cube.set_projection_settings(driver, ProjectionSettings { fov_y, ..cube.projection_settings() }),
+            CubeCommand::ClipPlanes { near, far } => cube.set_projection_settings(driver, ProjectionSettings { near, far, ..cube.projection_settings() }),
+            CubeCommand::PresentMode(mode) => cube.set_present_mode(driver, mode),
+            CubeCommand::FrameLatency(frames) => cube.set_frame_latency(driver, frames),
+            CubeCommand::FrameCap(_) | CubeCommand::Idle(_) | CubeCommand::MaxPixelRatio(_) | CubeCommand::Bind(..) => {}
+            // js functions are not Send, which native map callbacks have to be
+            #[cfg(target_arch = "wasm32")]
+            CubeCommand::Screenshot { resolve, reject } => cube.capture(driver, move |shot| {
+                let _ = match shot.and_then(|shot| shot.to_png()) {
+                    Ok(png) => resolve.call1(&JsValue::NULL, &js_sys::Uint8Array::from(png.as_slice())),
+                    Err(e) => reject.call1(&JsValue::NULL, &JsError::new(&e.to_string()).into()),
+                };
+            }),
+            #[cfg(not(target_arch = "wasm32"))]
+            CubeCommand::Screenshot { .. } => log::warn!("screenshot() is only answered in the browser"),
+            CubeCommand::Shadows { resolution, depth_bias, slope_bias, pcf_radius } => {
+                let current = cube.shadow_settings();
+                cube.set_shadow_settings(driver, ShadowSettings {
+                    resolution: resolution.unwrap_or(current.resolution),
+                    depth_bias: depth_bias.unwrap_or(current.depth_bias),
+                    slope_bias: slope_bias.unwrap_or(current.slope_bias),
+                    pcf_radius: pcf_radius.unwrap_or(current.pcf_radius),
+                });
+            }
+            CubeCommand::EffectTint(tint) => {
+                if let Some(effect) = cube.effect() {
+                    cube.set_effect(driver, Some(FaceEffect { tint, ..effect }));
+                }
+            }
+        }
+    }
+}
+
+// faces by name ("top") or by their index in cube_data ("4")
+fn parse_face(face: &str) -> Result<Face, ThemeError> {
+    match face.trim().parse::<usize>() {
+        Ok(index) => Face::from_index(index),
+        Err(_) => Face::from_name(face.trim()),
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct CubeHandle {
+    commands: Rc<RefCell<Vec<CubeCommand>>>,
+    // wakes an idle event loop so queued commands get drawn
+    waker: Rc<RefCell<Option<EventLoopProxy<()>>>>,
+    on_face_hover: Rc<RefCell<Option<js_sys::Function>>>,
+    on_face_click: Rc<RefCell<Option<js_sys::Function>>>,
+    on_action: Rc<RefCell<Option<js_sys::Function>>>,
+    // the device's max_texture_dimension_2d, 0 until run_wasm has a device
+    max_texture_size: Rc<Cell<u32>>,
+}
+
+impl CubeHandle {
+    pub fn push(&self, command: CubeCommand) {
+        self.commands.borrow_mut().push(command);
+        if let Some(waker) = &*self.waker.borrow() {
+            let _ = waker.send_event(());
+        }
+    }
+
+    pub fn set_waker(&self, waker: EventLoopProxy<()>) {
+        *self.waker.borrow_mut() = Some(waker);
+    }
+
+    pub fn set_max_texture_size(&self, size: u32) {
+        self.max_texture_size.set(size);
+    }
+
+    // before the device is up only the webgl2 minimum is certain
+    fn max_texture_size(&self) -> u32 {
+        match self.max_texture_size.get() {
+            0 => wgpu::Limits::downlevel_webgl2_defaults().max_texture_dimension_2d,
+            size => size,
+        }
+    }
+
+    // the event loop reports pointer changes, JS gets (name, index) or null once the pointer leaves the cube
+    pub fn face_hovered(&self, face: Option<Face>) {
+        if let Some(callback) = &*self.on_face_hover.borrow() {
+            let result = match face {
+                Some(face) => callback.call2(&JsValue::NULL, &face.name().into(), &(face as u32).into()),
+                None => callback.call1(&JsValue::NULL, &JsValue::NULL),
+            };
+            if let Err(e) = result {
+                log::warn!("onFaceHover callback threw {e:?}");
+            }
+        }
+    }
+
+    pub fn face_clicked(&self, face: Face) {
+        let Some(callback) = &*self.on_face_click.borrow() else { return };
+        if let Err(e) = callback.call2(&JsValue::NULL, &face.name().into(), &(face as u32).into()) {
+            log::warn!("onFaceClick callback threw {e:?}");
+        }
+    }
+
+    // every action input triggered, screenshot and quit only ever get here
+    pub fn action_triggered(&self, action: Action) {
+        let Some(callback) = &*self.on_action.borrow() else { return };
+        if let Err(e) = callback.call1(&JsValue::NULL, &action.name().into()) {
+            log::warn!("onAction callback threw {e:?}");
+        }
+    }
+
+    // everything queued since the last frame, oldest first
+    pub fn drain(&self) -> Vec<CubeCommand> {
+        std::mem::take(&mut *self.commands.borrow_mut())
+    }
+
+    fn queue(&self, command: Result<CubeCommand, ThemeError>) -> Result<(), JsError> {
+        let command = command.map_err(|e| JsError::new(&e.to_string()))?;
+        self.push(command);
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl CubeHandle {
+    #[wasm_bindgen(js_name = themeNames)]
+    pub fn theme_names() -> Vec<String> {
+        CubeTheme::NAMES.iter().map(|n| n.to_string()).collect()
+    }
+
+    // one of themeNames()
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&self, name: &str) -> Result<(), JsError> {
+        self.queue(CubeTheme::named(name).map(|theme| CubeCommand::Theme(Box::new(theme))))
+    }
+
+    // color is a css style hex string, "#4af" or "#44aaff" or "#44aaff80"
+    #[wasm_bindgen(js_name = setFaceColor)]
+    pub fn set_face_color(&self, face: &str, color: &str) -> Result<(), JsError> {
+        self.queue(parse_face(face).and_then(|face| Ok(CubeCommand::Face(face, FaceFill::Solid(parse_hex(color)?)))))
+    }
+
+    // linear 0..1 floats
+    #[wasm_bindgen(js_name = setFaceRgba)]
+    pub fn set_face_rgba(&self, face: &str, r: f32, g: f32, b: f32, a: f32) -> Result<(), JsError> {
+        self.queue(parse_face(face).map(|face| CubeCommand::Face(face, FaceFill::Solid([r, g, b, a]))))
+    }
+
+    // four hex colors for the corners at uv (0,0), (1,0), (1,1), (0,1)
+    #[wasm_bindgen(js_name = setFaceGradient)]
+    pub fn set_face_gradient(&self, face: &str, corners: Vec<String>) -> Result<(), JsError> {
+        let fill = || -> Result<FaceFill, ThemeError> {
+            let colors = corners.iter().map(|c| parse_hex(c)).collect::<Result<Vec<_>, _>>()?;
+            let corners: [[f32; 4]; 4] = colors.try_into()
+                .map_err(|colors: Vec<_>| ThemeError::BadGradient(colors.len()))?;
+            Ok(FaceFill::Gradient(corners))
+        };
+        self.queue(parse_face(face).and_then(|face| Ok(CubeCommand::Face(face, fill()?))))
+    }
+
+    #[wasm_bindgen(js_name = effectNames)]
+    pub fn effect_names() -> Vec<String> {
+        EffectKind::ALL.iter().map(|k| k.name().to_string()).collect()
+    }
+
+    // one of effectNames(), with that effect's default parameters
+    #[wasm_bindgen(js_name = setEffect)]
+    pub fn set_effect(&self, name: &str) -> Result<(), JsError> {
+        let kind = EffectKind::from_name(name)
+            .ok_or_else(|| JsError::new(&format!("no effect {name:?}, expected one of {}", Self::effect_names().join(", "))))?;
+        self.push(CubeCommand::Effect(Some(kind)));
+        Ok(())
+    }
+
+    // back to the plain face colors
+    #[wasm_bindgen(js_name = clearEffect)]
+    pub fn clear_effect(&self) {
+        self.push(CubeCommand::Effect(None));
+    }
+
+    // speed scales time (0 freezes), scale sets the pattern density, intensity fades the effect in 0..1
+    #[wasm_bindgen(js_name = setEffectParams)]
+    pub fn set_effect_params(&self, speed: f32, scale: f32, intensity: f32) {
+        self.push(CubeCommand::EffectParams { speed, scale, intensity });
+    }
+
+    // color of grid lines, rim glow and foil sparkle as a hex string
+    #[wasm_bindgen(js_name = setEffectTint)]
+    pub fn set_effect_tint(&self, color: &str) -> Result<(), JsError> {
+        self.queue(parse_hex(color).map(CubeCommand::EffectTint))
+    }
+
+    #[wasm_bindgen(js_name = setBackgroundColor)]
+    pub fn set_background_color(&self, color: &str) -> Result<(), JsError> {
+        self.queue(parse_hex(color).map(|c| CubeCommand::Background(Background::Solid(c))))
+    }
+
+    // top and bottom of the canvas
+    #[wasm_bindgen(js_name = setBackgroundGradient)]
+    pub fn set_background_gradient(&self, top: &str, bottom: &str) -> Result<(), JsError> {
+        self.queue(parse_hex(top).and_then(|top| Ok(CubeCommand::Background(Background::Gradient { top, bottom: parse_hex(bottom)? }))))
+    }
+
+    // density 1 is the default sky, the two hex colors tint the nebula
+    #[wasm_bindgen(js_name = setStarfield)]
+    pub fn set_starfield(&self, density: f32, nebula: Option<String>, glow: Option<String>) -> Result<(), JsError> {
+        let nebula = nebula.as_deref().map_or(Ok(NEBULA_COLOR), parse_hex);
+        let glow = glow.as_deref().map_or(Ok(NEBULA_GLOW), parse_hex);
+        self.queue(nebula.and_then(|nebula| Ok(CubeCommand::Background(Background::Starfield { nebula, glow: glow?, density }))))
+    }
+
+    // encoded png / jpeg / hdr bytes for the +x, -x, +y, -y, +z, -z faces
+    #[wasm_bindgen(js_name = setSkyboxFaces)]
+    pub fn set_skybox_faces(&self, px: &[u8], nx: &[u8], py: &[u8], ny: &[u8], pz: &[u8], nz: &[u8]) -> Result<(), JsError> {
+        let cubemap = CubemapImage::from_faces([px, nx, py, ny, pz, nz]).map_err(|e| JsError::new(&e.to_string()))?;
+        self.push(CubeCommand::Skybox(Box::new(cubemap)));
+        Ok(())
+    }
+
+    // one 2:1 panorama, resampled into faces of face_size texels, 1 up to the device's largest texture
+    #[wasm_bindgen(js_name = setSkyboxEquirect)]
+    pub fn set_skybox_equirect(&self, bytes: &[u8], face_size: u32) -> Result<(), JsError> {
+        let cubemap = CubemapImage::from_equirect(bytes, face_size, self.max_texture_size()).map_err(|e| JsError::new(&e.to_string()))?;
+        self.push(CubeCommand::Skybox(Box::new(cubemap)));
+        Ok(())
+    }
+
+    // metallic and roughness in 0..1, the optional hex color replaces the face colors as the base color
+    #[wasm_bindgen(js_name = setPbr)]
+    pub fn set_pbr(&self, metallic: f32, roughness: f32, color: Option<String>) -> Result<(), JsError> {
+        let material = match color {
+            Some(color) => parse_hex(&color).map(|c| {
+                let mut material = Material::pbr(c, metallic, roughness);
+                material.uniform.params[3] = 0.0;
+                material
+            }),
+            None => Ok(Material::pbr([1.0; 4], metallic, roughness)),
+        };
+        self.queue(material.map(CubeCommand::MainMaterial))
+    }
+
+    #[wasm_bindgen(js_name = setPolishedMetal)]
+    pub fn set_polished_metal(&self) {
+        self.push(CubeCommand::MainMaterial(Material::polished_metal()));
+    }
+
+    // back to the plain unlit face colors
+    #[wasm_bindgen(js_name = setUnlit)]
+    pub fn set_unlit(&self) {
+        self.push(CubeCommand::MainMaterial(Material::unlit()));
+    }
+
+    // the direction the light comes from, it does not need to be normalized
+    #[wasm_bindgen(js_name = setLightDirection)]
+    pub fn set_light_direction(&self, x: f32, y: f32, z: f32) {
+        self.push(CubeCommand::Light(DirectionalLight::new([x, y, z])));
+    }
+
+    // "none", "plane" with an optional hex color, or "catcher" where the optional value is the shadow opacity
+    #[wasm_bindgen(js_name = setGround)]
+    pub fn set_ground(&self, mode: &str, color: Option<String>, opacity: Option<f32>) -> Result<(), JsError> {
+        let ground = match mode {
+            "none" => Ground::None,
+            "plane" => Ground::Plane(color.as_deref().map_or(Ok(GROUND_COLOR), parse_hex).map_err(|e| JsError::new(&e.to_string()))?),
+            "catcher" => Ground::ShadowCatcher(opacity.unwrap_or(SHADOW_CATCHER_OPACITY)),
+            _ => return Err(JsError::new(&format!("no ground {mode:?}, expected none, plane or catcher"))),
+        };
+        self.push(CubeCommand::Ground(ground));
+        Ok(())
+    }
+
+    // shadow map size in texels, depth and slope bias against acne, pcf kernel radius in texels
+    #[wasm_bindgen(js_name = setShadowSettings)]
+    pub fn set_shadow_settings(&self, resolution: Option<u32>, depth_bias: Option<f32>, slope_bias: Option<f32>, pcf_radius: Option<u32>) {
+        self.push(CubeCommand::Shadows { resolution, depth_bias, slope_bias, pcf_radius });
+    }
+
+    // screen space ambient occlusion, omitted values fall back to the defaults
+    #[wasm_bindgen(js_name = setSsao)]
+    pub fn set_ssao(&self, enabled: bool, radius: Option<f32>, intensity: Option<f32>, bias: Option<f32>) {
+        let d = Ssao::default();
+        let ssao = enabled.then(|| Ssao {
+            radius: radius.unwrap_or(d.radius),
+            intensity: intensity.unwrap_or(d.intensity),
+            bias: bias.unwrap_or(d.bias),
+        });
+        self.push(CubeCommand::Ssao(ssao));
+    }
+
+    // sharp at focusDistance from the camera, fully blurred (maxBlur pixels) focusRange away from it
+    #[wasm_bindgen(js_name = setDepthOfField)]
+    pub fn set_depth_of_field(&self, enabled: bool, focus_distance: Option<f32>, focus_range: Option<f32>, max_blur: Option<f32>) {
+        let d = DepthOfField::default();
+        let dof = enabled.then(|| DepthOfField {
+            focus_distance: focus_distance.unwrap_or(d.focus_distance),
+            focus_range: focus_range.unwrap_or(d.focus_range),
+            max_blur_px: max_blur.unwrap_or(d.max_blur_px),
+        });
+        self.push(CubeCommand::DepthOfField(dof));
+    }
+
+    // exponential fog starting at start units from the camera, color as a hex string
+    #[wasm_bindgen(js_name = setFog)]
+    pub fn set_fog(&self, enabled: bool, color: Option<String>, start: Option<f32>, density: Option<f32>) -> Result<(), JsError> {
+        let d = Fog::default();
+        let fog = match enabled {
+            false => Ok(None),
+            true => color.as_deref().map_or(Ok(d.color), parse_hex).map(|color| Some(Fog {
+                color,
+                start: start.unwrap_or(d.start),
+                density: density.unwrap_or(d.density),
+            })),
+        };
+        self.queue(fog.map(CubeCommand::Fog))
+    }
+
+    // resolves to the png bytes of the next frame as a Uint8Array,
+    // new Blob([bytes], { type: "image/png" }) turns it into something shareable
+    pub fn screenshot(&self) -> js_sys::Promise {
+        js_sys::Promise::new(&mut |resolve, reject| self.push(CubeCommand::Screenshot { resolve, reject }))
+    }
+
+    // callback(face, index) whenever the pointer moves onto another face, callback(null) when it leaves the cube,
+    // undefined removes it
+    #[wasm_bindgen(js_name = onFaceHover)]
+    pub fn on_face_hover(&self, callback: Option<js_sys::Function>) {
+        *self.on_face_hover.borrow_mut() = callback;
+    }
+
+    // callback(face, index) for a click or tap on a face
+    #[wasm_bindgen(js_name = onFaceClick)]
+    pub fn on_face_click(&self, callback: Option<js_sys::Function>) {
+        *self.on_face_click.borrow_mut() = callback;
+    }
+
+    #[wasm_bindgen(js_name = projectionNames)]
+    pub fn projection_names() -> Vec<String> {
+        Projection::NAMES.iter().map(|n| n.to_string()).collect()
+    }
+
+    // one of projectionNames(), the view blends over to it in a little over half a second
+    #[wasm_bindgen(js_name = setProjection)]
+    pub fn set_projection(&self, name: &str) -> Result<(), JsError> {
+        let projection = Projection::named(name)
+            .ok_or_else(|| JsError::new(&format!("no projection {name:?}, expected one of {}", Projection::NAMES.join(", "))))?;
+        self.push(CubeCommand::Projection(projection));
+        Ok(())
+    }
+
+    // vertical, in degrees, for the perspective projection
+    #[wasm_bindgen(js_name = setFieldOfView)]
+    pub fn set_field_of_view(&self, degrees: f32) -> Result<(), JsError> {
+        if !(degrees > 0.0 && degrees < 180.0) {
+            return Err(JsError::new("the field of view has to be between 0 and 180 degrees"));
+        }
+        self.push(CubeCommand::FieldOfView(Deg(degrees).into()));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setClipPlanes)]
+    pub fn set_clip_planes(&self, near: f32, far: f32) -> Result<(), JsError> {
+        if !(near > 0.0 && far > near && far.is_finite()) {
+            return Err(JsError::new("clip planes need 0 < near < far"));
+        }
+        self.push(CubeCommand::ClipPlanes { near, far });
+        Ok(())
+    }
+
+    // fit-width, fit-height or fit-contain (the default), how the view adapts to the canvas shape
+    #[wasm_bindgen(js_name = setAspectFit)]
+    pub fn set_aspect_fit(&self, name: &str) -> Result<(), JsError> {
+        let fit = AspectFit::from_name(name)
+            .ok_or_else(|| JsError::new(&format!("no aspect fit {name:?}, expected one of {}", AspectFit::NAMES.join(", "))))?;
+        self.push(CubeCommand::AspectFit(fit));
+        Ok(())
+    }
+
+    // one of fifo, fifo-relaxed, mailbox, immediate, auto-vsync (the default) or auto-no-vsync
+    #[wasm_bindgen(js_name = setPresentMode)]
+    pub fn set_present_mode(&self, name: &str) -> Result<(), JsError> {
+        let mode = present_mode_from_name(name)
+            .ok_or_else(|| JsError::new(&format!("no present mode {name:?}, expected one of {}", PRESENT_MODE_NAMES.join(", "))))?;
+        self.push(CubeCommand::PresentMode(mode));
+        Ok(())
+    }
+
+    // frames queued ahead of the display, 2 by default
+    #[wasm_bindgen(js_name = setFrameLatency)]
+    pub fn set_frame_latency(&self, frames: u32) {
+        self.push(CubeCommand::FrameLatency(frames));
+    }
+
+    // at most fps frames a second, null or undefined removes the cap
+    #[wasm_bindgen(js_name = setFrameCap)]
+    pub fn set_frame_cap(&self, fps: Option<f32>) {
+        self.push(CubeCommand::FrameCap(fps));
+    }
+
+    // idle freezes the animation and only redraws after a call on this handle or a resize
+    #[wasm_bindgen(js_name = setIdle)]
+    pub fn set_idle(&self, idle: bool) {
+        self.push(CubeCommand::Idle(idle));
+    }
+
+    // caps the backing store at ratio pixels per css pixel, trading sharpness for fill rate on
+    // very dense screens, undefined or 0 takes the cap off
+    #[wasm_bindgen(js_name = setMaxPixelRatio)]
+    pub fn set_max_pixel_ratio(&self, ratio: Option<f64>) {
+        self.push(CubeCommand::MaxPixelRatio(ratio.filter(|ratio| *ratio > 0.0)));
+    }
+
+    #[wasm_bindgen(js_name = actionNames)]
+    pub fn action_names() -> Vec<String> {
+        Action::ALL.iter().map(|a| a.name().to_string()).collect()
+    }
+
+    // trigger is a KeyboardEvent.code ("KeyW", "Digit1"), MouseLeft, MouseRight, MouseMiddle,
+    // Gamepad0 to Gamepad16 in the standard gamepad layout, Tap or LongPress, action one of actionNames()
+    pub fn bind(&self, trigger: &str, action: &str) -> Result<(), JsError> {
+        let trigger = Trigger::named(trigger).map_err(|e| JsError::new(&e.to_string()))?;
+        let action = Action::named(action).map_err(|e| JsError::new(&e.to_string()))?;
+        self.push(CubeCommand::Bind(trigger, Some(action)));
+        Ok(())
+    }
+
+    pub fn unbind(&self, trigger: &str) -> Result<(), JsError> {
+        let trigger = Trigger::named(trigger).map_err(|e| JsError::new(&e.to_string()))?;
+        self.push(CubeCommand::Bind(trigger, None));
+        Ok(())
+    }
+
+    // callback(name) for every action a key, button, gesture or gamepad triggers, undefined removes it.
+    // screenshot and quit are left to the page, e.g. calling screenshot() on this handle
+    #[wasm_bindgen(js_name = onAction)]
+    pub fn on_action(&self, callback: Option<js_sys::Function>) {
+        *self.on_action.borrow_mut() = callback;
+    }
+}
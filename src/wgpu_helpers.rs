@@ -1,30 +1,62 @@
 
 use bytemuck::{Pod, Zeroable};
 use cgmath::*;
-use lazy_static::lazy_static;
-use web_sys::console;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BlendComponent, Buffer, BufferUsages, ColorTargetState, FragmentState, PipelineLayout, RenderPipeline, RenderPipelineDescriptor, ShaderModule, SurfaceConfiguration, TextureFormat};
-use std::{f32::consts::PI, sync::Mutex};
-use std::{iter, mem, option};
-
+use wgpu::{BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer, BufferUsages, ColorTargetState, FragmentState, PipelineLayout, RenderPipeline, RenderPipelineDescriptor, ShaderModule, SurfaceConfiguration, TextureFormat, TextureView, WasmNotSend};
+use std::f32::consts::PI;
+use std::{iter, mem};
+
+use crate::background::{view_rotation, Background, BackgroundPass, CubemapImage};
+use crate::capture::{CaptureError, CaptureTarget, Screenshot};
+use crate::effects::FaceEffect;
+use crate::material::{Material, MaterialId, MaterialLibrary};
+use crate::pipeline_cache::{PassKind, RenderPipelineKey};
+use crate::post::{PostProcess, PostSettings};
+use crate::picking;
+use crate::primitives;
+use crate::projection::{AspectFit, Projection, ProjectionSettings, ProjectionTransition};
+use crate::shadow::{DirectionalLight, Ground, ShadowSettings};
+use crate::theme::{CubeTheme, Face, FaceFill};
+use crate::transparency::{view_depth, DrawPass, OitTargets, TransparencyMode, OIT_ACCUM_BLEND, OIT_ACCUM_FORMAT, OIT_REVEAL_BLEND, OIT_REVEAL_FORMAT};
 use crate::wasm_driver::Driver;
+use crate::winding::check_winding;
+use crate::wireframe::{draws_outline, feature_edges, tesseract_edges, DisplayMode, OutlineStyle};
 
 
 const ANIMATION_SPEED: f32 = 0.001;
+// one full turn of the cube and the orbiting camera, frames this far apart match
+pub const LOOP_MS: f32 = 2.0 * PI / ANIMATION_SPEED;
+
+// Cube::new always adds the themed cube first
+const MAIN_CUBE: usize = 0;
+// far enough below the origin for the spinning cube's corners to clear it
+const GROUND_HEIGHT: f32 = -2.0;
+const GROUND_SIZE: f32 = 12.0;
 
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
 pub struct Vertex {
-    position: [f32; 4],
-    color: [f32; 4]
+    pub position: [f32; 4],
+    pub color: [f32; 4],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
 }
 
 impl Vertex {
-    // each elem has a given shader loc, 0,1 = shader loc at 0 (vec2 format) and 1 (vec3 format)
-    // correlates with whats in shader.wgsl
-    const ATTRIBUTES : [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+    // each elem has a given shader loc, 0 = position, 1 = color, 2 = normal, 3 = uv
+    // correlates with whats in the wgsl files, each shader only declares the ones it reads
+    const ATTRIBUTES : [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x3, 3 => Float32x2];
+
+    pub fn new(position: [f32; 3], color: [f32; 4], normal: [f32; 3], uv: [f32; 2]) -> Self {
+        Vertex {
+            position: [position[0], position[1], position[2], 1.0],
+            color,
+            normal,
+            uv,
+        }
+    }
+
     //render pipeline needs this to map gpu buffer to shader code
     // wgpu uses this layout to tell the remder pipeline how to read the data from the gpu buffer
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -42,14 +74,6 @@ impl Vertex {
 
 
 
-pub enum AnimationAttrs {
-    Speed,
-    Angle,
-    Direction,
-
-}
-
-
 // used rn to "initialize" project view
 pub fn create_view(camera_position: Point3<f32>, look_direction: Point3<f32>, up_direction: Vector3<f32>) -> Matrix4<f32> {
     Matrix4::look_at_rh(camera_position, look_direction, up_direction)
@@ -62,32 +86,18 @@ pub fn create_transforms(translation_matrix: [f32;3],rotation:[f32;3], scaling:[
     let rot_z_mat = Matrix4::from_angle_z(Rad(rotation[2]));
     let scale_mat = Matrix4::from_nonuniform_scale(scaling[0], scaling[1], scaling[2]);
 
-    return trans_mat * rot_x_mat * rot_y_mat * rot_z_mat * scale_mat;
-
+    trans_mat * rot_x_mat * rot_y_mat * rot_z_mat * scale_mat
 }
 
 
-#[rustfmt::skip]
-pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
-    1.0, 0.0, 0.0, 0.0,
-    0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
-);
-
 pub fn create_view_projection(camera_position: Point3<f32>, look_direction: Point3<f32>, up_direction: Vector3<f32>,
-    aspect:f32, is_perspective:bool) -> (Matrix4<f32>, Matrix4<f32>, Matrix4<f32>) {
+    aspect:f32, projection: Projection, settings: &ProjectionSettings) -> (Matrix4<f32>, Matrix4<f32>, Matrix4<f32>) {
     
     // construct view matrix
     let view_mat = Matrix4::look_at_rh(camera_position, look_direction, up_direction);     
 
     // construct projection matrix
-    let project_mat:Matrix4<f32>;
-    if is_perspective {
-        project_mat = OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0*PI/5.0), aspect, 0.1, 100.0);
-    } else {
-        project_mat = OPENGL_TO_WGPU_MATRIX * ortho(-4.0, 4.0, -3.0, 3.0, -1.0, 6.0);
-    }
+    let project_mat = projection.matrix(settings, aspect);
     
     // contruct view-projection matrix
     let view_project_mat = project_mat * view_mat;
@@ -96,28 +106,141 @@ pub fn create_view_projection(camera_position: Point3<f32>, look_direction: Poin
     (view_mat, project_mat, view_project_mat)
 } 
 
-pub fn create_projection(aspect:f32, is_perspective:bool) -> Matrix4<f32> {
-    let project_mat: Matrix4<f32>;
-    if is_perspective {
-        project_mat = OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0*PI/5.0), aspect, 0.1, 100.0);
-    } else {
-        project_mat = OPENGL_TO_WGPU_MATRIX * ortho(-4.0, 4.0, -3.0, 3.0, -1.0, 6.0);
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth24Plus;
+
+// the uniform_buffer struct every shader reads from group 0, one per object
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct ObjectUniform {
+    pub mvp: [[f32; 4]; 4],
+    pub model: [[f32; 4]; 4],
+    // camera position for view dependent effects, w is the time in seconds
+    pub eye_time: [f32; 4],
+    // xyz points towards the directional light
+    pub light: [f32; 4],
+}
+
+pub const UNIFORM_SIZE: u64 = mem::size_of::<ObjectUniform>() as u64;
+
+// group 0 of every shader, shader_validation checks the two stay in sync
+pub const UNIFORM_BGL_ENTRIES: [BindGroupLayoutEntry; 1] = [BindGroupLayoutEntry {
+    binding: 0,
+    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: wgpu::BufferSize::new(UNIFORM_SIZE),
+    },
+    count: None,
+}];
+
+// vertices uploaded once, drawn with whatever topology the material asks for
+pub struct Mesh {
+    pub vertex_buffer: Buffer,
+    pub vertex_count: u32,
+}
+
+impl Mesh {
+    pub fn new(driver: &Driver, vertices: &[Vertex]) -> Self {
+        Mesh {
+            vertex_buffer: Cube::create_vertex_buffer(driver, vertices),
+            vertex_count: vertices.len() as u32,
+        }
     }
-    project_mat
+}
 
+// creases sharper than ~8 degrees show up as edges
+const EDGE_CREASE_COS: f32 = 0.99;
+
+// multisampled color and depth for the main pass, color resolves into the real target
+struct MsaaTargets {
+    color: TextureView,
+    depth: TextureView,
+    sample_count: u32,
+    size: (u32, u32),
 }
 
+impl MsaaTargets {
+    fn new(driver: &Driver, format: TextureFormat, width: u32, height: u32, sample_count: u32) -> Self {
+        let texture = |label, format| driver.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default());
+        MsaaTargets {
+            color: texture("msaa color", format),
+            depth: texture("msaa depth", DEPTH_FORMAT),
+            sample_count,
+            size: (width, height),
+        }
+    }
+}
+
+// one drawable: a mesh placed in the world, shaded by a material from the library
+pub struct RenderObject {
+    pub mesh: Mesh,
+    // line list of the mesh's feature edges for the wireframe display modes
+    pub edges: Mesh,
+    pub transform: Matrix4<f32>,
+    pub material: MaterialId,
+    uniform_buffer: Buffer,
+    uniform_bg: BindGroup,
+}
+
+// The renderer core shared by the native State and the wasm front end.
+// It only knows about the driver and the surface config, the front ends
+// own the window, the event loop and the clock.
 pub struct Cube {
     pub(crate) config: SurfaceConfiguration,
     pub model_mat: Matrix4<f32>,
+    // the spin update_cube_render last applied, kept so blended objects can be depth sorted
+    animation_mat: Matrix4<f32>,
     pub view_mat: Matrix4<f32>,
+    // camera position and seconds since start, what procedural effects animate with
+    eye: Point3<f32>,
+    time_s: f32,
     pub project_mat: Matrix4<f32>,
-    pub uniform_bg: BindGroup,
-    pub uniform_buffer: Buffer,
-    pub uniform_bgl: BindGroupLayout,
-    pub vertex_buffer: Buffer,
-    pub(crate) buffer_render_pipeline: RenderPipeline,
-
+    pub materials: MaterialLibrary,
+    pub objects: Vec<RenderObject>,
+    pub display_mode: DisplayMode,
+    theme: CubeTheme,
+    // what the main cube is drawn with when no effect is on
+    main_material: MaterialId,
+    // set once a loaded model replaces the cube mesh, themes then only color the cube that is gone
+    has_model: bool,
+    // highlighted in the vertex colors
+    hovered: Option<Face>,
+    effect: Option<FaceEffect>,
+    edge_material: MaterialId,
+    outline: Option<OutlineStyle>,
+    // made the first time an effect or outline is turned on and kept for the next time
+    effect_material: Option<MaterialId>,
+    outline_material: Option<MaterialId>,
+    // physical pixels per point, outline thickness is given in points
+    pixel_ratio: f32,
+    pub(crate) depth_view: TextureView,
+    pub transparency: TransparencyMode,
+    oit: Option<OitTargets>,
+    pub background: Background,
+    background_pass: BackgroundPass,
+    pub light: DirectionalLight,
+    ground: Ground,
+    // kept out of objects so it neither spins nor casts, built the first time a ground is turned on
+    // and kept while it is off
+    ground_object: Option<RenderObject>,
+    post_settings: PostSettings,
+    // allocated the first time an effect is turned on
+    post: Option<PostProcess>,
+    // requested MSAA sample count, the main pass only uses it while nothing reads its depth afterwards
+    msaa: u32,
+    msaa_targets: Option<MsaaTargets>,
+    projection: ProjectionTransition,
+    projection_settings: ProjectionSettings,
 }
 impl Cube {
     pub fn new(
@@ -126,92 +249,433 @@ impl Cube {
         camera_position: Point3<f32>,
         look_direction: Point3<f32>,
         up_direction: Vector3<f32>,
-        aspect: f32,
-        is_perspective: bool,
+        projection: Projection,
     ) -> Self {
         // create perspective and view mats
-        let (view_mat, project_mat, view_project_mat) = create_view_projection(
+        let (view_mat, project_mat, _) = create_view_projection(
             camera_position,
             look_direction,
             up_direction,
-            aspect,
-            is_perspective,
+            config.width as f32 / config.height as f32,
+            projection,
+            &ProjectionSettings::default(),
         );
         let model_mat = create_transforms([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
-        let mvp_matrix = project_mat * model_mat * view_mat;
-        let mvp_ref: &[f32; 16] = mvp_matrix.as_ref();
 
-        // create uniform buffer
-        let uniform_buffer = Cube::create_buffer(driver, mvp_ref);
+        // shared layouts, shaders and pipelines
+        let mut materials = MaterialLibrary::new(driver, config.format);
+        let unlit = materials.add(driver, Material::unlit());
+        let edge_material = materials.add(driver, Material::edges([1.0, 1.0, 1.0, 1.0]));
 
-        // create bind group layout
-        let uniform_bgl = Cube::create_bgl(
-            driver,
-            &[BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            Some("uniform_bgl"),
-        );
+        let depth_view = Cube::create_depth_view(driver, config.width, config.height);
 
-        // create bind group
+        let mut cube = Cube {
+            config: config.clone(),
+            model_mat,
+            animation_mat: Matrix4::identity(),
+            view_mat,
+            eye: camera_position,
+            time_s: 0.0,
+            project_mat,
+            materials,
+            objects: Vec::new(),
+            display_mode: DisplayMode::default(),
+            theme: CubeTheme::default(),
+            main_material: unlit,
+            has_model: false,
+            hovered: None,
+            effect: None,
+            effect_material: None,
+            edge_material,
+            outline: None,
+            outline_material: None,
+            pixel_ratio: 1.0,
+            depth_view,
+            transparency: TransparencyMode::default(),
+            oit: None,
+            background: Background::default(),
+            background_pass: BackgroundPass::new(driver, config.format, 1),
+            light: DirectionalLight::default(),
+            ground: Ground::None,
+            ground_object: None,
+            post_settings: PostSettings::default(),
+            post: None,
+            msaa: 1,
+            msaa_targets: None,
+            projection: ProjectionTransition::new(projection),
+            projection_settings: ProjectionSettings::default(),
+        };
+        cube.add_object(driver, &cube.theme.cube_vertices(), Matrix4::identity(), unlit);
+        cube.materials.persist_pipelines();
+        cube
+    }
+
+    pub fn add_object(&mut self, driver: &Driver, vertices: &[Vertex], transform: Matrix4<f32>, material: MaterialId) -> usize {
+        let object = self.create_object(driver, vertices, transform, material);
+        self.objects.push(object);
+        self.objects.len() - 1
+    }
+
+    fn create_object(&self, driver: &Driver, vertices: &[Vertex], transform: Matrix4<f32>, material: MaterialId) -> RenderObject {
+        let uniform_buffer = Cube::create_buffer(driver, &self.object_uniform(transform));
         let uniform_bg = Cube::create_bg(
             driver,
-            &uniform_bgl,
+            &self.materials.object_bgl,
             &[BindGroupEntry {
                 binding: 0,
                 resource: uniform_buffer.as_entire_binding(),
             }],
             Some("uniform_bg"),
         );
+        // only triangle lists have faces to pull edges from
+        let shading = *self.materials.material(material);
+        let edges = match shading.topology {
+            wgpu::PrimitiveTopology::TriangleList => feature_edges(vertices, EDGE_CREASE_COS),
+            _ => Vec::new(),
+        };
+        if cfg!(debug_assertions) && shading.topology == wgpu::PrimitiveTopology::TriangleList && shading.cull_mode.is_some() {
+            let issues = check_winding(vertices, shading.front_face);
+            for issue in issues.iter().take(8) {
+                log::warn!("object {}: {issue}", self.objects.len());
+            }
+            if issues.len() > 8 {
+                log::warn!("object {}: {} more winding problems", self.objects.len(), issues.len() - 8);
+            }
+        }
+        RenderObject {
+            mesh: Mesh::new(driver, vertices),
+            edges: Mesh::new(driver, &edges),
+            transform,
+            material,
+            uniform_buffer,
+            uniform_bg,
+        }
+    }
 
-        // create shader module
-        let buffer_shader = Cube::create_shader(driver, Some("buffer_shader"));
+    pub fn theme(&self) -> &CubeTheme {
+        &self.theme
+    }
 
-        // create render pipeline layout
-        let buffer_render_pipeline_layout =
-            Cube::create_buffer_render_pipeline_layout(driver, &[&uniform_bgl]);
+    // recolors the main cube in place, the mesh layout is the same so only the vertex buffer is rewritten
+    pub fn set_theme(&mut self, driver: &Driver, theme: CubeTheme) {
+        self.theme = theme;
+        self.write_cube_colors(driver);
+    }
 
-        // create render pipeline
-        let buffer_render_pipeline = Cube::create_buffer_render_pipeline(
-            driver,
-            Some(&buffer_render_pipeline_layout),
-            &buffer_shader,
-            config.format,
-        );
+    // the theme's colors with the hovered face lifted
+    fn write_cube_colors(&self, driver: &Driver) {
+        if let Some(cube) = self.objects.get(MAIN_CUBE).filter(|_| !self.has_model) {
+            let mut vertices = self.theme.cube_vertices();
+            if let Some(face) = self.hovered {
+                for vertex in &mut vertices[face as usize * 6..face as usize * 6 + 6] {
+                    vertex.color = picking::highlight(vertex.color);
+                }
+            }
+            driver.queue.write_buffer(&cube.mesh.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+    }
 
-        // create vertex buffer
-        let vertex_buffer = Cube::create_vertex_buffer(driver);
+    // cursor in physical pixels from the top left of the surface, None off the cube or with a model loaded
+    pub fn face_at(&self, cursor: [f32; 2]) -> Option<Face> {
+        let cube = self.objects.get(MAIN_CUBE).filter(|_| !self.has_model)?;
+        let mvp = self.project_mat * self.view_mat * cube.transform * self.model_mat * self.animation_mat;
+        let ray = picking::ray_from_cursor(cursor, [self.config.width as f32, self.config.height as f32], mvp)?;
+        picking::pick_face(&ray, &Cube::create_cube_vertices())
+    }
 
-        Cube {
-            config: config.clone(),
-            model_mat,
-            view_mat,
-            project_mat,
-            uniform_bg,
-            uniform_buffer,
-            uniform_bgl,
-            vertex_buffer,
-            buffer_render_pipeline,
+    pub fn hovered_face(&self) -> Option<Face> {
+        self.hovered
+    }
+
+    // true when that changed the highlight
+    pub fn set_hovered_face(&mut self, driver: &Driver, face: Option<Face>) -> bool {
+        if face == self.hovered {
+            return false;
+        }
+        self.hovered = face;
+        self.write_cube_colors(driver);
+        true
+    }
+
+    // swaps the main cube's mesh for another one, keeping its material and transform
+    pub fn set_main_mesh(&mut self, driver: &Driver, vertices: &[Vertex]) {
+        let (transform, material) = (self.objects[MAIN_CUBE].transform, self.objects[MAIN_CUBE].material);
+        self.objects[MAIN_CUBE] = self.create_object(driver, vertices, transform, material);
+        self.has_model = true;
+    }
+
+    pub fn set_face(&mut self, driver: &Driver, face: Face, fill: FaceFill) {
+        self.set_theme(driver, self.theme.with_face(face, fill));
+    }
+
+    pub fn effect(&self) -> Option<FaceEffect> {
+        self.effect
+    }
+
+    // None goes back to the main material, one procedural material is reused for every effect
+    pub fn set_effect(&mut self, driver: &Driver, effect: Option<FaceEffect>) {
+        self.effect = effect;
+        if let Some(effect) = effect {
+            match self.effect_material {
+                Some(id) => self.materials.set_uniform(driver, id, effect.uniform()),
+                None => self.effect_material = Some(self.materials.add(driver, Material::procedural(effect))),
+            }
+        }
+        let material = match (self.effect, self.effect_material) {
+            (Some(_), Some(id)) => id,
+            _ => self.main_material,
+        };
+        if let Some(cube) = self.objects.get_mut(MAIN_CUBE) {
+            cube.material = material;
+        }
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    // uploads the cubemap, switches to showing it and relights pbr materials with it
+    pub fn set_skybox(&mut self, driver: &Driver, cubemap: &CubemapImage) {
+        self.background_pass.set_skybox(driver, cubemap);
+        self.materials.environment.bake(driver, &self.background_pass.sky_view, cubemap.size);
+        self.background = Background::Skybox { exposure: 1.0 };
+    }
+
+    // what the main cube is shaded with when no effect is on, always behind the same id
+    pub fn set_main_material(&mut self, driver: &Driver, material: Material) {
+        self.materials.replace(driver, self.main_material, material);
+        self.effect = None;
+        if let Some(cube) = self.objects.get_mut(MAIN_CUBE) {
+            cube.material = self.main_material;
+        }
+    }
+
+    // direction towards the light, shared by lit shading and the shadow map
+    pub fn set_light(&mut self, driver: &Driver, light: DirectionalLight) {
+        self.light = light;
+        self.write_object_uniforms(driver, self.animation_mat);
+    }
+
+    pub fn ground(&self) -> Ground {
+        self.ground
+    }
+
+    // the plane is built the first time, switching modes after that only touches its material
+    pub fn set_ground(&mut self, driver: &Driver, ground: Ground) {
+        self.ground = ground;
+        let Some(material) = ground.material() else { return };
+        match &self.ground_object {
+            Some(object) => self.materials.replace(driver, object.material, material),
+            None => {
+                let material = self.materials.add(driver, material);
+                let transform = Matrix4::from_translation(Vector3::new(0.0, GROUND_HEIGHT, 0.0));
+                self.ground_object = Some(self.create_object(driver, &primitives::plane(GROUND_SIZE, GROUND_SIZE, 1), transform, material));
+            }
+        }
+        self.write_object_uniforms(driver, self.animation_mat);
+    }
+
+    pub fn shadow_settings(&self) -> ShadowSettings {
+        self.materials.shadows.settings()
+    }
+
+    pub fn set_shadow_settings(&mut self, driver: &Driver, settings: ShadowSettings) {
+        self.materials.shadows.set_settings(driver, settings);
+    }
+
+    pub fn post_settings(&self) -> PostSettings {
+        self.post_settings
+    }
+
+    // with every effect off the scene goes straight to the surface again
+    pub fn set_post_settings(&mut self, driver: &Driver, settings: PostSettings) {
+        self.post_settings = settings;
+        if settings.is_active() && self.post.is_none() {
+            self.post = Some(PostProcess::new(driver, self.config.format, self.config.width, self.config.height, &self.depth_view));
+        }
+        self.update_samples(driver);
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    // unsupported modes fall back to fifo, see Driver::resolve_present_mode
+    pub fn set_present_mode(&mut self, driver: &Driver, mode: wgpu::PresentMode) {
+        self.config.present_mode = driver.resolve_present_mode(mode);
+        driver.configure(&self.config);
+    }
+
+    // how many frames may be queued before get_current_texture blocks, at least 1
+    pub fn set_frame_latency(&mut self, driver: &Driver, frames: u32) {
+        self.config.desired_maximum_frame_latency = frames.max(1);
+        driver.configure(&self.config);
+    }
+
+    pub fn msaa(&self) -> u32 {
+        self.msaa
+    }
+
+    // sample counts the adapter can't render the surface format with fall back to 1
+    pub fn set_msaa(&mut self, driver: &Driver, sample_count: u32) {
+        let flags = driver.adapter.get_texture_format_features(self.config.format).flags;
+        self.msaa = match sample_count <= 1 || flags.sample_count_supported(sample_count) {
+            true => sample_count.max(1),
+            false => {
+                log::warn!("{sample_count}x MSAA is not supported for {:?}, drawing without it", self.config.format);
+                1
+            }
+        };
+        self.update_samples(driver);
+    }
+
+    // post effects and the OIT pass read the single sampled depth buffer, so they turn MSAA off while they are on
+    fn sample_count(&self) -> u32 {
+        match self.post_settings.is_active() || self.transparency == TransparencyMode::WeightedBlended {
+            true => 1,
+            false => self.msaa,
+        }
+    }
+
+    fn update_samples(&mut self, driver: &Driver) {
+        let samples = self.sample_count();
+        if samples != self.materials.sample_count() {
+            self.background_pass.set_target(driver, self.config.format, samples);
+        }
+        self.materials.set_target(driver, self.config.format, samples);
+        let size = (self.config.width, self.config.height);
+        self.msaa_targets = match self.msaa_targets.take() {
+            _ if samples == 1 => None,
+            Some(targets) if targets.sample_count == samples && targets.size == size => Some(targets),
+            _ => Some(MsaaTargets::new(driver, self.config.format, size.0, size.1, samples)),
+        };
+    }
+
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+    }
+
+    pub fn set_edge_color(&mut self, driver: &Driver, color: [f32; 4]) {
+        let mut uniform = self.materials.material(self.edge_material).uniform;
+        uniform.base_color = color;
+        self.materials.set_uniform(driver, self.edge_material, uniform);
+    }
+
+    pub fn outline_style(&self) -> Option<OutlineStyle> {
+        self.outline
+    }
+
+    pub fn pixel_ratio(&self) -> f32 {
+        self.pixel_ratio
+    }
+
+    // the window's scale factor, or devicePixelRatio on the web
+    pub fn set_pixel_ratio(&mut self, driver: &Driver, ratio: f32) {
+        if ratio > 0.0 && ratio != self.pixel_ratio {
+            self.pixel_ratio = ratio;
+            self.write_outline_uniform(driver);
         }
     }
+
+    // None turns the silhouette pass off, one material is reused for every style
+    pub fn set_outline(&mut self, driver: &Driver, style: Option<OutlineStyle>) {
+        self.outline = style;
+        if let (Some(style), None) = (style, self.outline_material) {
+            self.outline_material = Some(self.materials.add(driver, Material::outline(style)));
+        }
+        self.write_outline_uniform(driver);
+    }
+
+    fn write_outline_uniform(&mut self, driver: &Driver) {
+        if let (Some(style), Some(id)) = (self.outline, self.outline_material) {
+            let mut uniform = Material::outline(style).uniform;
+            uniform.params[0] *= self.pixel_ratio;
+            uniform.params[2] = self.config.width as f32;
+            uniform.params[3] = self.config.height as f32;
+            self.materials.set_uniform(driver, id, uniform);
+        }
+    }
+
+    // the OIT targets are only allocated once weighted blended mode is first used
+    pub fn set_transparency_mode(&mut self, driver: &Driver, mode: TransparencyMode) {
+        self.transparency = mode;
+        if mode == TransparencyMode::WeightedBlended && self.oit.is_none() {
+            self.oit = Some(OitTargets::new(driver, self.config.format, self.config.width, self.config.height));
+        }
+        self.update_samples(driver);
+    }
+
+    // turns the main cube into tinted glass with a tesseract wireframe floating inside it
+    pub fn add_glass_scene(&mut self, driver: &Driver) {
+        self.set_main_material(driver, Material::glass([0.75, 0.9, 1.0, 0.35]));
+        let lines = self.materials.add(driver, Material::edges([1.0, 0.6, 0.2, 1.0]));
+        self.add_object(driver, &tesseract_edges(0.45), Matrix4::identity(), lines);
+        self.materials.persist_pipelines();
+    }
+
+    // a lit row of every generated primitive behind the main cube, for comparing shapes
+    pub fn add_primitive_showcase(&mut self, driver: &Driver) {
+        let shapes = [
+            primitives::uv_sphere(0.7, 24, 12),
+            primitives::icosphere(0.7, 2),
+            primitives::cylinder(0.5, 1.4, 24),
+            primitives::cone(0.6, 1.4, 24),
+            primitives::torus(0.55, 0.2, 32, 12),
+            primitives::subdivided_cube(1.2, 4),
+        ];
+        let lit = self.materials.add(driver, Material::lit(0.25));
+        for (i, vertices) in shapes.iter().enumerate() {
+            let x = -5.0 + 2.0 * i as f32;
+            self.add_object(driver, vertices, create_transforms([x, 0.0, 3.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]), lit);
+        }
+        let floor = self.materials.add(driver, Material::textured(6.0));
+        self.add_object(driver, &primitives::plane(12.0, 4.0, 1), create_transforms([0.0, -1.0, 3.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]), floor);
+        self.materials.persist_pipelines();
+    }
+
+    // spheres going from smooth to rough left to right, dielectric on top and metal below
+    pub fn add_pbr_showcase(&mut self, driver: &Driver) {
+        let sphere = primitives::uv_sphere(0.4, 32, 16);
+        for row in 0..2 {
+            for column in 0..5 {
+                let roughness = column as f32 / 4.0;
+                let metallic = row as f32;
+                let material = self.materials.add(driver, Material::pbr([0.9, 0.55, 0.2, 1.0], metallic, roughness));
+                let transform = create_transforms([-2.0 + column as f32, 1.0 - row as f32, -3.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+                self.add_object(driver, &sphere, transform, material);
+            }
+        }
+        self.materials.persist_pipelines();
+    }
+
+    // lines up one small cube per built in material next to the main one
+    pub fn add_material_showcase(&mut self, driver: &Driver) {
+        let showcase = [
+            Material::lit(0.25),
+            Material::textured(2.0),
+            Material::transparent(0.4),
+            Material::wireframe([1.0, 1.0, 1.0, 1.0]),
+        ];
+        let vertices = Cube::create_cube_vertices();
+        for (i, material) in showcase.into_iter().enumerate() {
+            let id = self.materials.add(driver, material);
+            let x = -3.0 + 2.0 * i as f32;
+            let transform = create_transforms([x, 0.0, -3.0], [0.0, 0.0, 0.0], [0.6, 0.6, 0.6]);
+            self.add_object(driver, &vertices, transform, id);
+        }
+        self.materials.persist_pipelines();
+    }
 }
 
 
 impl Cube{
     
 
-    //create uniform buffer for cube
-    pub fn create_buffer(driver: &Driver, mvp_ref: &[f32;16]) -> Buffer{
+    //create uniform buffer for an object
+    pub fn create_buffer(driver: &Driver, uniform: &ObjectUniform) -> Buffer{
         driver.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("uniform buffer"),
-        contents: bytemuck::cast_slice(mvp_ref),
+        contents: bytemuck::bytes_of(uniform),
         usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
     })
 }
@@ -223,51 +687,192 @@ impl Cube{
             push_constant_ranges: &[],
         })
     }
-    // for cube
-   pub fn create_buffer_render_pipeline(driver: &Driver, layout: Option<&PipelineLayout>, buffer_shader: &ShaderModule, texture_format: TextureFormat) -> RenderPipeline {
+    // builds the pipeline a cache key describes, callers go through PipelineCache so each key compiles once
+   pub fn create_buffer_render_pipeline(driver: &Driver, layout: Option<&PipelineLayout>, buffer_shader: &ShaderModule, key: &RenderPipelineKey, cache: Option<&wgpu::PipelineCache>) -> RenderPipeline {
+        let material = &key.material;
+        let (fs_entry, targets) = match key.pass {
+            PassKind::Forward => ("fs_main", vec![Some(ColorTargetState {
+                format: key.color_format,
+                blend: Some(material.blend.blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })]),
+            PassKind::OitAccumulate => ("fs_oit", vec![
+                Some(ColorTargetState {
+                    format: OIT_ACCUM_FORMAT,
+                    blend: Some(OIT_ACCUM_BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(ColorTargetState {
+                    format: OIT_REVEAL_FORMAT,
+                    blend: Some(OIT_REVEAL_BLEND),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ]),
+        };
         driver.device.create_render_pipeline(&RenderPipelineDescriptor{
             label: Some("Cube render pipeline"),
-            layout: layout,
+            layout,
             vertex: wgpu::VertexState {
                 module: buffer_shader,
                  entry_point: Some("vs_main"),
-                   buffers: &[Vertex::desc()],
+                   buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: key.vertex_layout.array_stride,
+                    step_mode: key.vertex_layout.step_mode,
+                    attributes: &key.vertex_layout.attributes,
+                   }],
                 compilation_options: Default::default(),
                 },
             primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
+            topology: material.topology,
             strip_index_format: None,
+            front_face: material.front_face,
+            cull_mode: material.cull_mode,
+            polygon_mode: material.polygon_mode,
             ..Default::default()
         },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth24Plus,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::LessEqual,
+        depth_stencil: key.depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: material.depth_write,
+            depth_compare: material.depth_compare,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
-        multisample: wgpu::MultisampleState::default(),
+        multisample: wgpu::MultisampleState {
+            count: key.sample_count,
+            ..Default::default()
+        },
         fragment: Some(FragmentState {
-            module: &buffer_shader,
-            entry_point: Some("fs_main"),
+            module: buffer_shader,
+            entry_point: Some(fs_entry),
             compilation_options: wgpu::PipelineCompilationOptions::default(),
-            targets: &[Some(ColorTargetState {
-                format: texture_format,
-                blend: Some(wgpu::BlendState {
-                        color: BlendComponent::REPLACE,
-                        alpha: BlendComponent::REPLACE,
-                    }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets: &targets,
         }),
         multiview: None,
-        cache: None,
-        
-            
+        cache,
         })
     }
-    
-    pub fn update_cube_render(&mut self, driver: &Driver ,mut dur: f32){
+
+    // the depth buffer lives as long as the surface size does, resize() replaces it
+    // it is sampleable so the post effects can read it once the scene is drawn
+    pub fn create_depth_view(driver: &Driver, width: u32, height: u32) -> TextureView {
+        let depth_texture = driver.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth texture"),
+            size: wgpu::Extent3d{
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        depth_texture.create_view(&wgpu::TextureViewDescriptor{
+            label: Some("depth view texture"),
+            ..Default::default()
+        })
+    }
+
+    // reconfigures the surface and rebuilds everything tied to its size
+    pub fn resize(&mut self, driver: &Driver, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.resize_targets(driver, width, height);
+        driver.configure(&self.config);
+    }
+
+    // everything resize rebuilds except the surface, for drawing offscreen at another size than the window
+    pub fn resize_targets(&mut self, driver: &Driver, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.update_samples(driver);
+        self.depth_view = Cube::create_depth_view(driver, width, height);
+        if let Some(oit) = &mut self.oit {
+            oit.resize(driver, width, height);
+        }
+        if let Some(post) = &mut self.post {
+            post.resize(driver, width, height, &self.depth_view);
+        }
+        self.update_projection(driver);
+        self.write_outline_uniform(driver);
+    }
+
+    fn aspect(&self) -> f32 {
+        self.config.width as f32 / self.config.height as f32
+    }
+
+    fn update_projection(&mut self, driver: &Driver) {
+        self.project_mat = self.projection.matrix(&self.projection_settings, self.aspect());
+        self.write_object_uniforms(driver, self.animation_mat);
+    }
+
+    pub fn aspect_fit(&self) -> AspectFit {
+        self.projection_settings.fit
+    }
+
+    pub fn set_aspect_fit(&mut self, driver: &Driver, fit: AspectFit) {
+        self.set_projection_settings(driver, ProjectionSettings { fit, ..self.projection_settings });
+    }
+
+    // the projection being switched to if a transition is running
+    pub fn projection(&self) -> Projection {
+        self.projection.target()
+    }
+
+    // blends over projection::TRANSITION_MS, driven by advance_projection
+    pub fn set_projection(&mut self, driver: &Driver, projection: Projection) {
+        self.projection.switch(projection);
+        self.update_projection(driver);
+    }
+
+    pub fn projection_settings(&self) -> ProjectionSettings {
+        self.projection_settings
+    }
+
+    pub fn set_projection_settings(&mut self, driver: &Driver, settings: ProjectionSettings) {
+        self.projection_settings = settings;
+        self.update_projection(driver);
+    }
+
+    // call once a frame with the wall clock, true while a transition needs more frames
+    pub fn advance_projection(&mut self, driver: &Driver, now_ms: f64) -> bool {
+        if !self.projection.is_running() {
+            return false;
+        }
+        let running = self.projection.advance(now_ms);
+        self.update_projection(driver);
+        running
+    }
+
+    fn object_uniform(&self, model_mat: Matrix4<f32>) -> ObjectUniform {
+        let mvp_matrix = self.project_mat * self.view_mat * model_mat;
+        ObjectUniform {
+            mvp: mvp_matrix.into(),
+            model: model_mat.into(),
+            eye_time: [self.eye.x, self.eye.y, self.eye.z, self.time_s],
+            light: self.light.uniform(),
+        }
+    }
+
+    // animation is applied on top of each object's own placement
+    fn write_object_uniforms(&self, driver: &Driver, animation: Matrix4<f32>) {
+        for object in &self.objects {
+            let uniform = self.object_uniform(object.transform * self.model_mat * animation);
+            driver.queue.write_buffer(&object.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        }
+        if let Some(ground) = &self.ground_object {
+            driver.queue.write_buffer(&ground.uniform_buffer, 0, bytemuck::bytes_of(&self.object_uniform(ground.transform)));
+        }
+    }
+
+    // dur is the time since the front end started rendering, in milliseconds
+    pub fn update_cube_render(&mut self, driver: &Driver, mut dur: f32){
 
         dur *= ANIMATION_SPEED;
         
@@ -281,7 +886,7 @@ impl Cube{
               
                let camera_position = Point3::new(
         3.0 * dur.cos(), // X position (orbiting)
-        2.0,                        // Y position (fixed height)
+        3.0 * self.projection.elevation().0.tan(), // Y position (height set by the projection)
         3.0 * dur.sin(), // Z position (orbiting)
 
         );
@@ -294,53 +899,54 @@ impl Cube{
     );
 
     // Combine the matrices: projection -> view -> model
-    let mvp_matrix = self.project_mat * self.view_mat * self.model_mat * transf_matrix;
-        //let mvp_matrix = self.project_mat * transf_matrix * self.view_mat * self.model_mat;
-        let mvp_ref: &[f32; 16] = mvp_matrix.as_ref();
-        driver.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(mvp_ref));
-        
+        self.eye = camera_position;
+        self.time_s = dur / ANIMATION_SPEED / 1000.0;
+        self.animation_mat = transf_matrix;
+        self.write_object_uniforms(driver, transf_matrix);
     }
     // for cube vertices
-   pub fn create_vertex_buffer(driver: &Driver) ->Buffer{
+   pub fn create_vertex_buffer(driver: &Driver, vertices: &[Vertex]) ->Buffer{
         driver.device.create_buffer_init(&BufferInitDescriptor{
             label: Some("Cube vertex Buffer"),
-            contents: bytemuck::cast_slice(&Cube::create_cube_vertices()),
-            usage: BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(vertices),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
         })
     }
 
     pub fn create_bgl(driver: &Driver, bgls: &[BindGroupLayoutEntry], label: Option<&str>) -> BindGroupLayout{
         driver.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor{
-            label: label,
+            label,
             entries: bgls
     })
     }
 
     pub fn create_bg(driver: &Driver, layout: &BindGroupLayout, entries: &[BindGroupEntry], label: Option<&str>) -> BindGroup{
         driver.device.create_bind_group(&wgpu::BindGroupDescriptor{
-            label: label,
-            layout: layout,
-            entries: entries,
+            label,
+            layout,
+            entries,
         })
     }
 
 
-    pub fn create_shader(driver: &Driver, label: Option<&str>) ->ShaderModule{
+    pub fn create_shader_from_source(driver: &Driver, label: Option<&str>, source: &str) -> ShaderModule{
         driver.device.create_shader_module(wgpu::ShaderModuleDescriptor{
-            label: label,
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+            label,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         })
     }
 
-    pub fn create_pll(){
-
+    // rebuilds the pipelines of whichever material shader file_name belongs to
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_shader(&mut self, driver: &Driver, file_name: &str, source: &str) -> Result<(), wgpu::Error> {
+        match crate::material::MaterialShader::from_file_name(file_name) {
+            Some(shader) => self.materials.reload_shader(driver, shader, source),
+            None => Ok(()),
+        }
     }
 
-    pub fn create_rpl(){}
-
-
-
-   pub fn cube_data() -> (Vec<[i8; 3]>, Vec<[i8; 3]>, Vec<[i8; 2]>, Vec<[i8; 3]>){
+    #[allow(clippy::type_complexity)]
+    pub fn cube_data() -> (Vec<[i8; 3]>, Vec<[i8; 3]>, Vec<[i8; 2]>, Vec<[i8; 3]>){
         // we have to build the face with two triangle primitives cant be easy ig
     // using 4 vertices and indices will not let us have a typical cube with unique face colors
 
@@ -409,66 +1015,69 @@ impl Cube{
     (vertex_positions.to_vec(), colors.to_vec(), uvs.to_vec(), normalized_coords.to_vec())
     }
 
-    fn create_vertex(p:[i8;3], c:[i8;3]) -> Vertex{
-        Vertex {
-            position: [p[0] as f32, p[1] as f32, p[2] as f32, 1.0],
-            color: [c[0] as f32, c[1] as f32, c[2] as f32, 1.0],
-            }
+    fn create_vertex(p:[i8;3], c:[i8;3], uv:[i8;2], n:[i8;3]) -> Vertex{
+        Vertex::new(
+            [p[0] as f32, p[1] as f32, p[2] as f32],
+            [c[0] as f32, c[1] as f32, c[2] as f32, 1.0],
+            [n[0] as f32, n[1] as f32, n[2] as f32],
+            [uv[0] as f32, uv[1] as f32],
+        )
     }
 
-    fn create_cube_vertices() -> Vec<Vertex>{
-        let (pos, col, _uv, _normal) = Cube::cube_data();
-        let mut data: Vec<Vertex> = Vec::with_capacity(pos.len());
-        for i in 0..pos.len() {
-            data.push(Cube::create_vertex(pos[i],col[i]));
-        }
-        data.to_vec()
-    }
 
-    pub fn render(&mut self, driver: &Driver<'_>) -> Result<(), wgpu::SurfaceError> {
-        let output = driver.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        console::log_1(&format!("config.width: {}, config.height: {}", self.config.width, self.config.height).into());
+    pub fn create_cube_vertices() -> Vec<Vertex>{
+        let (pos, col, uv, normal) = Cube::cube_data();
+        (0..pos.len())
+            .map(|i| Cube::create_vertex(pos[i], col[i], uv[i], normal[i]))
+            .collect()
+    }
 
-        let depth_texture = driver.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("depth texture"),
-            size: wgpu::Extent3d{
-                width: self.config.width,
-                height: self.config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
-        });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor{
-            label: Some("depth view texture"),
-            ..Default::default()
-        });
+    // records the scene into whatever target view the front end hands over
+    // opaque objects go first so blended ones have the depth buffer to test against,
+    // blended ones follow back to front or, in weighted blended mode, go to the OIT pass
+    pub fn draw(&self, driver: &Driver, view: &TextureView) {
         let mut encoder = driver.device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Cube encoder")
         });
-       console::log_1(&"before render pass".into()); 
+
+        let oit = self.oit.as_ref().filter(|_| self.transparency == TransparencyMode::WeightedBlended);
+        // with post effects on the scene is drawn offscreen and composited into view at the end
+        let post = self.post.as_ref().filter(|_| self.post_settings.is_active());
+        let target = post.map_or(view, |p| &p.scene_view);
+        let msaa = self.msaa_targets.as_ref();
+        let (opaque, mut blended): (Vec<&RenderObject>, Vec<&RenderObject>) = self.objects.iter()
+            .partition(|o| !self.materials.material(o.material).blend.is_transparent());
+        let depth = |o: &RenderObject| view_depth(self.view_mat, o.transform * self.model_mat * self.animation_mat);
+        blended.sort_by(|a, b| depth(b).total_cmp(&depth(a)));
+        let accumulated = |o: &RenderObject| oit.is_some() && self.materials.has_pass(o.material, DrawPass::OitAccumulate);
+        // only the ground receives shadows, without it the shadow pass has no one to draw for
+        if self.ground != Ground::None {
+            self.materials.shadows.write_uniform(driver, &self.light);
+            let casters = opaque.iter()
+                .filter(|o| self.materials.material(o.material).topology == wgpu::PrimitiveTopology::TriangleList)
+                .map(|o| (&o.uniform_bg, &o.mesh));
+            self.materials.shadows.draw(&mut encoder, casters);
+        }
+        let draws_background = !matches!(self.background, Background::Solid(_));
+        if draws_background {
+            // the sky always uses a perspective ray, an orthographic one would smear it into one color
+            let projection = Projection::Perspective.matrix(&self.projection_settings, self.aspect());
+            self.background_pass.write_uniform(driver, &self.background, projection, view_rotation(self.view_mat), self.time_s);
+        }
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: msaa.map_or(target, |m| &m.color),
+                    resolve_target: msaa.map(|_| target),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {r: 0.05, g:0.062, b:0.08, a:1.0}),
+                        load: wgpu::LoadOp::Clear(self.background.clear_color()),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
+                    view: msaa.map_or(&self.depth_view, |m| &m.depth),
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
@@ -479,17 +1088,100 @@ impl Cube{
                 timestamp_writes: None,
             });
 
-            console::log_1(&"After render pass".into());
-            render_pass.set_pipeline(&self.buffer_render_pipeline);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_bind_group(0, &self.uniform_bg, &[]);
-            render_pass.draw(0..36,0..1);
-        } 
+            if draws_background {
+                self.background_pass.draw(&mut render_pass);
+            }
+            // under everything else, so the shadow catcher can blend straight onto the background
+            if let Some(ground) = self.ground_object.as_ref().filter(|_| self.ground != Ground::None) {
+                render_pass.set_bind_group(0, &ground.uniform_bg, &[]);
+                self.draw_mesh(&mut render_pass, &ground.mesh, ground.material, DrawPass::Single);
+            }
+            for &object in opaque.iter().chain(&blended) {
+                render_pass.set_bind_group(0, &object.uniform_bg, &[]);
+                let outline_material = self.outline.and(self.outline_material);
+                if let Some(outline_material) = outline_material.filter(|_| draws_outline(self.materials.material(object.material))) {
+                    self.draw_mesh(&mut render_pass, &object.mesh, outline_material, DrawPass::Single);
+                }
+                if self.display_mode.draws_solid() && !accumulated(object) {
+                    if self.materials.has_pass(object.material, DrawPass::BackFaces) {
+                        self.draw_mesh(&mut render_pass, &object.mesh, object.material, DrawPass::BackFaces);
+                        self.draw_mesh(&mut render_pass, &object.mesh, object.material, DrawPass::FrontFaces);
+                    } else {
+                        self.draw_mesh(&mut render_pass, &object.mesh, object.material, DrawPass::Single);
+                    }
+                }
+                if self.display_mode.draws_edges() {
+                    self.draw_mesh(&mut render_pass, &object.edges, self.edge_material, DrawPass::Single);
+                }
+            }
+        }
+
+        if let Some(oit) = oit {
+            let accumulate: Vec<&RenderObject> = blended.into_iter().filter(|&o| accumulated(o)).collect();
+            if self.display_mode.draws_solid() && !accumulate.is_empty() {
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("OIT accumulate pass"),
+                        color_attachments: &oit.color_attachments(),
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: &self.depth_view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+                    for object in accumulate {
+                        render_pass.set_bind_group(0, &object.uniform_bg, &[]);
+                        self.draw_mesh(&mut render_pass, &object.mesh, object.material, DrawPass::OitAccumulate);
+                    }
+                }
+                oit.composite(&mut encoder, target);
+            }
+        }
+        if let Some(post) = post {
+            post.write_uniform(driver, &self.post_settings, self.project_mat, self.config.width, self.config.height);
+            post.draw(&mut encoder, &self.post_settings, view);
+        }
         driver.queue.submit(iter::once(encoder.finish()));
+    }
+
+    fn draw_mesh(&self, render_pass: &mut wgpu::RenderPass<'_>, mesh: &Mesh, material: MaterialId, pass: DrawPass) {
+        if mesh.vertex_count == 0 {
+            return;
+        }
+        self.materials.apply(render_pass, material, pass);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.draw(0..mesh.vertex_count, 0..1);
+    }
+
+    // draws the current frame again into an offscreen copy of the surface and reads it back,
+    // on_done runs once the gpu has finished, native callers poll the device to get there
+    pub fn capture(&self, driver: &Driver, on_done: impl FnOnce(Result<Screenshot, CaptureError>) + WasmNotSend + 'static) {
+        match CaptureTarget::new(driver, self.config.format, self.config.width, self.config.height) {
+            Ok(target) => {
+                self.draw(driver, &target.view);
+                target.read_back(driver, on_done);
+            }
+            Err(e) => on_done(Err(e)),
+        }
+    }
+
+    pub fn render(&mut self, driver: &Driver<'_>) -> Result<(), wgpu::SurfaceError> {
+        // headless drivers have nothing to present to, they draw through capture instead
+        let Some(surface) = &driver.surface else {
+            return Err(wgpu::SurfaceError::Lost);
+        };
+        let output = surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(driver, &view);
         output.present();
-    
-                console::log_1(&"leaving func".into());
         Ok(())
     }
 
-}
\ No newline at end of file
+}
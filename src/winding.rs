@@ -0,0 +1,88 @@
+// Debug check that a triangle list winds the way its material says the front is.
+// Each triangle's geometric normal (from the winding) is compared against the
+// normals stored on its vertices, which point outward for every mesh we generate.
+use std::fmt;
+
+use crate::wgpu_helpers::Vertex;
+use crate::wireframe::face_normal;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WindingProblem {
+    // the winding disagrees with the vertex normals, the face gets culled from outside
+    Inverted,
+    // zero area, no winding at all
+    Degenerate,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WindingIssue {
+    // index of the triangle, its vertices are 3 * triangle .. 3 * triangle + 3
+    pub triangle: usize,
+    pub problem: WindingProblem,
+    pub positions: [[f32; 3]; 3],
+}
+
+impl fmt::Display for WindingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.problem {
+            WindingProblem::Inverted => "winds against its normals",
+            WindingProblem::Degenerate => "has no area",
+        };
+        write!(f, "triangle {} {problem}: {:?}", self.triangle, self.positions)
+    }
+}
+
+// every triangle whose winding doesn't match front_face, empty for a consistent mesh
+pub fn check_winding(vertices: &[Vertex], front_face: wgpu::FrontFace) -> Vec<WindingIssue> {
+    vertices.chunks_exact(3).enumerate().filter_map(|(triangle, tri)| {
+        let [a, b, c] = [tri[0].position, tri[1].position, tri[2].position];
+        let cross = [
+            (b[1] - a[1]) * (c[2] - a[2]) - (b[2] - a[2]) * (c[1] - a[1]),
+            (b[2] - a[2]) * (c[0] - a[0]) - (b[0] - a[0]) * (c[2] - a[2]),
+            (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]),
+        ];
+        let area = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+        let problem = if area < 1.0e-8 {
+            Some(WindingProblem::Degenerate)
+        } else {
+            let mut geometric = face_normal(a, b, c);
+            if front_face == wgpu::FrontFace::Cw {
+                geometric = geometric.map(|n| -n);
+            }
+            let stored: f32 = tri.iter()
+                .map(|v| v.normal[0] * geometric[0] + v.normal[1] * geometric[1] + v.normal[2] * geometric[2])
+                .sum();
+            (stored < 0.0).then_some(WindingProblem::Inverted)
+        };
+        problem.map(|problem| WindingIssue {
+            triangle,
+            problem,
+            positions: [a, b, c].map(|p| [p[0], p[1], p[2]]),
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wgpu_helpers::Cube;
+
+    #[test]
+    fn cube_winds_outward() {
+        let vertices = Cube::create_cube_vertices();
+        assert_eq!(check_winding(&vertices, wgpu::FrontFace::Ccw), vec![]);
+        // the same mesh read as clockwise is inside out everywhere
+        assert_eq!(check_winding(&vertices, wgpu::FrontFace::Cw).len(), 12);
+    }
+
+    #[test]
+    fn flipped_and_degenerate_triangles_are_reported() {
+        let mut vertices = Cube::create_cube_vertices();
+        vertices.swap(7, 8);
+        vertices[32] = vertices[31];
+        let issues = check_winding(&vertices, wgpu::FrontFace::Ccw);
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].triangle, issues[0].problem), (2, WindingProblem::Inverted));
+        assert_eq!((issues[1].triangle, issues[1].problem), (10, WindingProblem::Degenerate));
+    }
+}
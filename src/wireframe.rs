@@ -0,0 +1,175 @@
+// Edge extraction and the display modes built on it. Meshes are unindexed triangle
+// lists, so edges are matched by position and an edge shared by two triangles facing
+// the same way (a quad's diagonal) is not a real edge of the shape and gets dropped.
+use std::collections::HashMap;
+
+use crate::material::Material;
+use crate::wgpu_helpers::Vertex;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+    #[default]
+    Solid,
+    Wireframe,
+    SolidWithEdges,
+}
+
+impl DisplayMode {
+    pub fn next(self) -> Self {
+        match self {
+            DisplayMode::Solid => DisplayMode::Wireframe,
+            DisplayMode::Wireframe => DisplayMode::SolidWithEdges,
+            DisplayMode::SolidWithEdges => DisplayMode::Solid,
+        }
+    }
+
+    pub fn draws_solid(self) -> bool {
+        self != DisplayMode::Wireframe
+    }
+
+    pub fn draws_edges(self) -> bool {
+        self != DisplayMode::Solid
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutlineStyle {
+    pub thickness_px: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        OutlineStyle {
+            thickness_px: 3.0,
+            color: [1.0, 0.85, 0.4, 1.0],
+        }
+    }
+}
+
+// the silhouette pass pushes triangles out along their normals, line meshes like the tesseract
+// edges have no triangles and would come out as garbage through its triangle list pipeline
+pub fn draws_outline(material: &Material) -> bool {
+    material.topology == wgpu::PrimitiveTopology::TriangleList
+}
+
+// positions are compared after snapping so float noise from generated meshes still matches
+type PointKey = [i32; 3];
+
+fn point_key(p: [f32; 4]) -> PointKey {
+    let snap = |v: f32| (v * 1.0e4).round() as i32;
+    [snap(p[0]), snap(p[1]), snap(p[2])]
+}
+
+pub(crate) fn face_normal(a: [f32; 4], b: [f32; 4], c: [f32; 4]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt().max(f32::EPSILON);
+    [n[0] / len, n[1] / len, n[2] / len]
+}
+
+// line list vertices (two per edge) for the outline of every face in a triangle list
+// creases sharper than crease_cos (cosine of the angle between face normals) are kept
+pub fn feature_edges(vertices: &[Vertex], crease_cos: f32) -> Vec<Vertex> {
+    struct Edge {
+        ends: (Vertex, Vertex),
+        normals: Vec<[f32; 3]>,
+    }
+    let mut edges: HashMap<(PointKey, PointKey), Edge> = HashMap::new();
+    let mut order = Vec::new();
+
+    for tri in vertices.chunks_exact(3) {
+        let normal = face_normal(tri[0].position, tri[1].position, tri[2].position);
+        for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+            let (ka, kb) = (point_key(a.position), point_key(b.position));
+            if ka == kb {
+                continue;
+            }
+            let key = if ka < kb { (ka, kb) } else { (kb, ka) };
+            let edge = edges.entry(key).or_insert_with(|| {
+                order.push(key);
+                Edge { ends: (a, b), normals: Vec::new() }
+            });
+            edge.normals.push(normal);
+        }
+    }
+
+    let white = [1.0, 1.0, 1.0, 1.0];
+    order.into_iter().filter_map(|key| {
+        let edge = &edges[&key];
+        let keep = match edge.normals.as_slice() {
+            [n0, n1] => n0[0] * n1[0] + n0[1] * n1[1] + n0[2] * n1[2] < crease_cos,
+            // open boundaries and non manifold edges always show
+            _ => true,
+        };
+        keep.then_some([
+            Vertex { color: white, ..edge.ends.0 },
+            Vertex { color: white, ..edge.ends.1 },
+        ])
+    }).flatten().collect()
+}
+
+// line list of a 4D hypercube's 32 edges, turned a little in the xw and yw planes and
+// projected to 3D through w so the inner cube sits inside the outer one
+pub fn tesseract_edges(scale: f32) -> Vec<Vertex> {
+    let (sin_a, cos_a) = 0.5f32.sin_cos();
+    let (sin_b, cos_b) = 0.3f32.sin_cos();
+    let project = |corner: u32| {
+        let c = |bit: u32| if corner & (1 << bit) != 0 { 1.0 } else { -1.0 };
+        let (x, y, z, w) = (c(0), c(1), c(2), c(3));
+        let (x, w) = (x * cos_a - w * sin_a, x * sin_a + w * cos_a);
+        let (y, w) = (y * cos_b - w * sin_b, y * sin_b + w * cos_b);
+        let k = scale * 2.0 / (3.0 - w);
+        let color = [0.5 + 0.5 * c(0), 0.5 + 0.5 * c(1), 0.5 + 0.5 * c(3), 1.0];
+        Vertex::new([x * k, y * k, z * k], color, [0.0, 0.0, 0.0], [0.0, 0.0])
+    };
+    (0..16u32).flat_map(|corner| {
+        // each edge once, from the corner with the bit cleared to the one with it set
+        (0..4).filter(move |bit| corner & (1 << bit) == 0)
+            .flat_map(move |bit| [project(corner), project(corner | (1 << bit))])
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wgpu_helpers::Cube;
+
+    #[test]
+    fn cube_has_twelve_edges_and_no_diagonals() {
+        let edges = feature_edges(&Cube::create_cube_vertices(), 0.99);
+        assert_eq!(edges.len(), 24);
+        for pair in edges.chunks_exact(2) {
+            // every cube edge runs along exactly one axis
+            let changed = (0..3).filter(|&i| pair[0].position[i] != pair[1].position[i]).count();
+            assert_eq!(changed, 1, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn lone_triangle_keeps_its_border() {
+        let tri = [
+            Vertex::new([0.0, 0.0, 0.0], [1.0; 4], [0.0, 0.0, 1.0], [0.0, 0.0]),
+            Vertex::new([1.0, 0.0, 0.0], [1.0; 4], [0.0, 0.0, 1.0], [1.0, 0.0]),
+            Vertex::new([0.0, 1.0, 0.0], [1.0; 4], [0.0, 0.0, 1.0], [0.0, 1.0]),
+        ];
+        assert_eq!(feature_edges(&tri, 0.99).len(), 6);
+    }
+
+    #[test]
+    fn only_triangle_meshes_get_an_outline() {
+        // what add_glass_scene puts next to the cube, lit and glass solids around the tesseract's lines
+        assert!(draws_outline(&Material::unlit()));
+        assert!(draws_outline(&Material::lit(0.25)));
+        assert!(draws_outline(&Material::glass([0.75, 0.9, 1.0, 0.35])));
+        assert!(!draws_outline(&Material::edges([1.0, 0.6, 0.2, 1.0])));
+    }
+
+    #[test]
+    fn tesseract_fits_inside_the_cube() {
+        let edges = tesseract_edges(0.45);
+        assert_eq!(edges.len(), 64);
+        assert!(edges.iter().all(|v| v.position[..3].iter().all(|p| p.abs() < 1.0)));
+    }
+}